@@ -4,20 +4,10 @@
 //! "Engine.PlayerReplicationInfo:PlayerName". This example shows both methods. The error handling
 //! demonstrated is minimal, relying on stringly typed errors. In practice, prefer a richer error
 //! type.
-use boxcars::{HeaderProp, ParserBuilder};
+use boxcars::ParserBuilder;
 use std::error;
 use std::io::{self, Read};
 
-/// Given an array of objects (represented as a slice of key-value pairs), find all the instances
-/// of the "Name" key and extract the string value
-fn names_in_header(stats: &[Vec<(String, HeaderProp)>]) -> impl Iterator<Item = &str> {
-    stats
-        .iter()
-        .flat_map(|v| v.iter())
-        .filter(|(prop_name, _)| *prop_name == "Name")
-        .filter_map(|(_, prop_val)| prop_val.as_string())
-}
-
 /// Given network frames and the object id to "Engine.PlayerReplicationInfo:PlayerName", comb
 /// through all the attributes looking for attributes that have our object id.
 fn names_in_network(frames: &[boxcars::Frame], name_attribute_id: boxcars::ObjectId) -> Vec<&str> {
@@ -64,20 +54,9 @@ fn main() -> Result<(), Box<dyn error::Error>> {
         .ignore_network_data_on_error()
         .parse()?;
 
-    let stats_prop = replay
-        .properties
-        .iter()
-        .find(|(prop, _)| *prop == "PlayerStats");
-
-    if let Some((_, stats)) = stats_prop {
+    if let Some(stats) = replay.header().player_stats() {
         println!("Names in the header data:");
-        let stats = match stats {
-            HeaderProp::Array(arr) => arr,
-            _ => return Err("expected player stats to be an array".into()),
-        };
-
-        let header_names = names_in_header(stats);
-        for name in header_names {
+        for name in stats.iter().filter_map(|player| player.name()) {
             println!("{}", name);
         }
     } else {
@@ -37,6 +37,7 @@ fn count_properties(
             HeaderProp::Name(_) => format!("{}:name", new_prefix),
             HeaderProp::QWord(_) => format!("{}:qword", new_prefix),
             HeaderProp::Str(_) => format!("{}:str", new_prefix),
+            HeaderProp::Unknown { kind, .. } => format!("{}:unknown({})", new_prefix, kind),
         };
 
         *counter.entry(k).or_default() += 1;
@@ -57,6 +58,7 @@ fn main() -> Result<(), Box<dyn error::Error>> {
         let replay = ParserBuilder::new(&buffer)
             .never_check_crc()
             .never_parse_network_data()
+            .retain_unknown_properties()
             .parse();
 
         let replay = if let Err(e) = replay {
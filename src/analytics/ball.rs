@@ -0,0 +1,188 @@
+//! # Ball modes
+//!
+//! Some game modes play with a ball that replicates mode specific state:
+//!
+//! - Heatseeker (`TAGame.Ball_God_TA`): the ball flies toward a goal at a target speed that
+//!   increases with each touch
+//! - Spooky (`TAGame.Ball_Haunted_TA`): touching the ball beams it toward a goal and goals can be
+//!   deactivated
+//! - Fire ball (`TAGame.Ball_Fire_TA`): the ball belongs to the team that last touched it
+//!
+//! This module reports every ball of the replay and the changes of their mode specific state.
+
+use super::{frames, Change, Tracker};
+use crate::models::Replay;
+use crate::network::attributes::Attribute;
+use crate::network::{is_instance_of, ActorId};
+use fnv::FnvHashMap;
+
+/// A summary of the ball mode specific network data of a replay
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BallReport {
+    /// Balls ordered by when they were created
+    pub balls: Vec<Ball>,
+
+    /// Changes of the balls' state in the order they happened
+    pub events: Vec<BallEvent>,
+}
+
+impl BallReport {
+    /// The events of the ball at the given index of `balls`
+    pub fn ball_events(&self, ball: usize) -> impl Iterator<Item = &BallEvent> + '_ {
+        self.events.iter().filter(move |x| x.ball == ball)
+    }
+}
+
+/// A ball actor. A new ball is typically created at each kickoff.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Ball {
+    pub actor_id: ActorId,
+
+    /// The archetype of the ball (eg: `Archetypes.Ball.Ball_God`)
+    pub object: String,
+    pub created_frame: usize,
+    pub created_time: f32,
+
+    /// When the ball was destroyed, if it was destroyed before the replay ended
+    pub destroyed_frame: Option<usize>,
+    pub destroyed_time: Option<f32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub enum BallEventKind {
+    /// Heatseeker: the speed the ball accelerates to changed
+    TargetSpeed { speed: f32 },
+
+    /// Spooky: the ball started or stopped being beamed toward a goal
+    Beamed { beamed: bool },
+
+    /// Spooky: the number of beams acting on the ball changed
+    ActiveBeams { beams: u8 },
+
+    /// Spooky: a beam was broken. The value is reported as replicated.
+    BeamBroken { value: u8 },
+
+    /// Spooky: the goal at the index was deactivated or, when `None`, the goals were reactivated
+    GoalDeactivated { goal: Option<u8> },
+
+    /// Fire ball: the ball changed team
+    FireTeam {
+        /// The team the ball now belongs to: 0 for blue and 1 for orange
+        team: Option<u8>,
+
+        /// The server time of the change, which differs from the frame's time
+        timestamp: f32,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BallEvent {
+    pub frame: usize,
+    pub time: f32,
+
+    /// Index into the report's balls
+    pub ball: usize,
+    pub kind: BallEventKind,
+}
+
+/// Summarizes the ball modes of the replay. Only the balls are reported for replays of other game
+/// modes, and nothing is reported when the network data was not parsed.
+pub fn analyze(replay: &Replay) -> BallReport {
+    let mut tracker = Tracker::new(replay);
+    let mut balls: Vec<Ball> = Vec::new();
+    let mut events: Vec<BallEvent> = Vec::new();
+    let mut live: FnvHashMap<ActorId, usize> = FnvHashMap::default();
+    let mut teams: FnvHashMap<ActorId, u8> = FnvHashMap::default();
+
+    for (index, frame) in frames(replay).iter().enumerate() {
+        let time = frame.time;
+        let first_event = events.len();
+        for change in tracker.apply(frame) {
+            let update = match change {
+                Change::Updated {
+                    update,
+                    changed: true,
+                } => update,
+                Change::Created { actor_id } => {
+                    let Some(object) = tracker.actor_object(actor_id) else {
+                        continue;
+                    };
+
+                    if is_instance_of(object, &["TAGame.Ball_TA"]) {
+                        live.insert(actor_id, balls.len());
+                        teams.remove(&actor_id);
+                        balls.push(Ball {
+                            actor_id,
+                            object: String::from(object),
+                            created_frame: index,
+                            created_time: time,
+                            destroyed_frame: None,
+                            destroyed_time: None,
+                        });
+                    }
+                    continue;
+                }
+                Change::Deleted { actor_id } => {
+                    if let Some(ball) = live.remove(&actor_id) {
+                        balls[ball].destroyed_frame = Some(index);
+                        balls[ball].destroyed_time = Some(time);
+                    }
+                    continue;
+                }
+                _ => continue,
+            };
+
+            let Some(&ball) = live.get(&update.actor_id) else {
+                continue;
+            };
+
+            let kind = match (tracker.object(update.object_id), &update.attribute) {
+                ("TAGame.Ball_TA:HitTeamNum", &Attribute::Byte(team)) => {
+                    teams.insert(update.actor_id, team);
+                    continue;
+                }
+                ("TAGame.Ball_God_TA:TargetSpeed", &Attribute::Float(speed)) => {
+                    BallEventKind::TargetSpeed { speed }
+                }
+                ("TAGame.Ball_Haunted_TA:bIsBallBeamed", &Attribute::Boolean(beamed)) => {
+                    BallEventKind::Beamed { beamed }
+                }
+                ("TAGame.Ball_Haunted_TA:TotalActiveBeams", &Attribute::Byte(beams)) => {
+                    BallEventKind::ActiveBeams { beams }
+                }
+                ("TAGame.Ball_Haunted_TA:ReplicatedBeamBrokenValue", &Attribute::Byte(value)) => {
+                    BallEventKind::BeamBroken { value }
+                }
+                ("TAGame.Ball_Haunted_TA:DeactivatedGoalIndex", &Attribute::Byte(goal)) => {
+                    // 255 denotes that no goal is deactivated
+                    BallEventKind::GoalDeactivated {
+                        goal: Some(goal).filter(|&x| x != 255),
+                    }
+                }
+                ("TAGame.Ball_Fire_TA:TeamNumChangeTimestamp", &Attribute::Float(timestamp)) => {
+                    BallEventKind::FireTeam {
+                        team: None,
+                        timestamp,
+                    }
+                }
+                _ => continue,
+            };
+
+            events.push(BallEvent {
+                frame: index,
+                time,
+                ball,
+                kind,
+            });
+        }
+
+        // The ball's team may be replicated after the timestamp of the change within a frame
+        for event in &mut events[first_event..] {
+            if let BallEventKind::FireTeam { ref mut team, .. } = event.kind {
+                *team = teams.get(&balls[event.ball].actor_id).copied();
+            }
+        }
+    }
+
+    BallReport { balls, events }
+}
@@ -0,0 +1,344 @@
+//! # Dropshot
+//!
+//! The Dropshot floor is made of hexagonal tiles (`TAGame.BreakOutActor_Platform_TA` actors)
+//! that are damaged and then destroyed when the ball lands on them. Each tile replicates its
+//! state and who damaged it through `TAGame.BreakOutActor_Platform_TA:DamageState`, while the ball
+//! replicates the damage it applied through `TAGame.Ball_Breakout_TA:AppliedDamage`. This module
+//! maps the tiles to stable indices and reports how their states changed over time and the damage
+//! each player caused.
+
+use super::{frames, Change, Player, Tracker};
+use crate::models::Replay;
+use crate::network::attributes::Attribute;
+use crate::network::{ActorId, Vector3f};
+use fnv::FnvHashMap;
+
+/// A summary of the Dropshot floor over the course of a replay
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DropshotReport {
+    pub players: Vec<Player>,
+
+    /// The floor tiles ordered by their index
+    pub tiles: Vec<Tile>,
+
+    /// Every change of a tile's state in the order they happened
+    pub changes: Vec<TileChange>,
+
+    /// The damage caused by each ball impact in the order they happened
+    pub damages: Vec<DamageEvent>,
+
+    /// The number of network frames in the replay
+    pub frames: usize,
+}
+
+impl DropshotReport {
+    /// The state of every tile, indexed the same as `tiles`, at the end of the given frame
+    pub fn states_at(&self, frame: usize) -> Vec<TileState> {
+        let mut states = vec![TileState::Undamaged; self.tiles.len()];
+        for change in self.changes.iter().take_while(|x| x.frame <= frame) {
+            states[change.tile] = change.state;
+        }
+        states
+    }
+
+    /// The state of every tile for each frame of the replay
+    pub fn grid(&self) -> TileGrid<'_> {
+        TileGrid {
+            report: self,
+            states: vec![TileState::Undamaged; self.tiles.len()],
+            frame: 0,
+            change: 0,
+        }
+    }
+
+    /// The total number of tile state changes caused by the player at the given index of
+    /// `players`
+    pub fn player_damage(&self, player: usize) -> usize {
+        self.damages
+            .iter()
+            .filter(|x| x.player == Some(player))
+            .map(|x| x.tiles.len())
+            .sum()
+    }
+}
+
+/// A tile on the Dropshot floor
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Tile {
+    /// The level object of the tile (eg: `ShatterShot_VFX.TheWorld:PersistentLevel.BreakOutActor_Platform_TA_33`)
+    pub object: String,
+
+    /// The actor of the tile, if it was seen in the network data
+    pub actor_id: Option<ActorId>,
+
+    /// The team that defends the tile: 0 for blue and 1 for orange. A tile's side is only known
+    /// once it has been damaged.
+    pub side: Option<u8>,
+}
+
+/// The damage state of a tile as replicated by `TAGame.BreakOutActor_Platform_TA:DamageState`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum TileState {
+    Undamaged,
+    Damaged,
+    Destroyed,
+
+    /// A state that is not known to this crate
+    Unknown(u8),
+}
+
+impl From<u8> for TileState {
+    fn from(x: u8) -> Self {
+        match x {
+            0 => TileState::Undamaged,
+            1 => TileState::Damaged,
+            2 => TileState::Destroyed,
+            x => TileState::Unknown(x),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TileChange {
+    pub frame: usize,
+    pub time: f32,
+
+    /// Index into the report's tiles
+    pub tile: usize,
+    pub state: TileState,
+}
+
+/// The tiles damaged by a single ball impact
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DamageEvent {
+    pub frame: usize,
+    pub time: f32,
+
+    /// Index into the report's players of the player credited with the damage
+    pub player: Option<usize>,
+
+    /// Indices into the report's tiles that were damaged or destroyed
+    pub tiles: Vec<usize>,
+
+    /// Index into the report's tiles of the tile the ball landed on
+    pub direct_hit: Option<usize>,
+
+    /// Position of the ball at the time of the damage
+    pub ball_position: Vector3f,
+
+    /// How charged the ball was (0 is uncharged), when the ball replicated the damage
+    pub damage_index: Option<i32>,
+}
+
+/// Iterator over the state of every tile at each frame
+#[derive(Debug, Clone)]
+pub struct TileGrid<'a> {
+    report: &'a DropshotReport,
+    states: Vec<TileState>,
+    frame: usize,
+    change: usize,
+}
+
+impl Iterator for TileGrid<'_> {
+    type Item = Vec<TileState>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.frame >= self.report.frames {
+            return None;
+        }
+
+        let changes = &self.report.changes[self.change..];
+        let count = changes.iter().take_while(|x| x.frame == self.frame).count();
+        for change in &changes[..count] {
+            self.states[change.tile] = change.state;
+        }
+
+        self.change += count;
+        self.frame += 1;
+        Some(self.states.clone())
+    }
+}
+
+/// Summarizes the Dropshot floor of the replay. Nothing other than the players is reported for
+/// replays of other game modes, and only the tiles are reported when the network data was not
+/// parsed.
+pub fn analyze(replay: &Replay) -> DropshotReport {
+    let mut tracker = Tracker::new(replay);
+    let (mut tiles, indices) = floor(&replay.objects);
+    let mut changes = Vec::new();
+    let mut damages: Vec<DamageEvent> = Vec::new();
+    let mut states: Vec<TileState> = vec![TileState::Undamaged; tiles.len()];
+
+    let frames = frames(replay);
+    for (index, frame) in frames.iter().enumerate() {
+        let time = frame.time;
+        let mut applied = None;
+        for change in tracker.apply(frame) {
+            let Change::Updated {
+                update,
+                changed: true,
+            } = change
+            else {
+                continue;
+            };
+
+            let state = match &update.attribute {
+                Attribute::DamageState(state) => state,
+                Attribute::AppliedDamage(damage) => {
+                    applied = Some(*damage);
+                    continue;
+                }
+                _ => continue,
+            };
+
+            let object = tracker.actor_object(update.actor_id);
+            let Some(&tile) = object.and_then(|x| indices.get(x)) else {
+                continue;
+            };
+
+            tiles[tile].actor_id = Some(update.actor_id);
+            let tile_state = TileState::from(state.tile_state);
+            if states[tile] != tile_state {
+                states[tile] = tile_state;
+                changes.push(TileChange {
+                    frame: index,
+                    time,
+                    tile,
+                    state: tile_state,
+                });
+            }
+
+            if !state.damaged || tile_state == TileState::Undamaged {
+                continue;
+            }
+
+            let player = tracker.pri_player(state.offender);
+            let team = player.and_then(|x| tracker.players()[x].team);
+            let side = team.map(|x| 1 - x).or_else(|| side_of(state.ball_position));
+            tiles[tile].side = tiles[tile].side.or(side);
+
+            // The damage of an impact spreads over a few frames while the ball's position stays
+            // the same, so the tiles are grouped with the latest damage at that position
+            let existing = damages
+                .iter()
+                .rev()
+                .take_while(|x| x.ball_position == state.ball_position)
+                .position(|x| x.player == player);
+
+            let damage = match existing {
+                Some(position) => {
+                    let index = damages.len() - 1 - position;
+                    &mut damages[index]
+                }
+                None => {
+                    damages.push(DamageEvent {
+                        frame: index,
+                        time,
+                        player,
+                        tiles: Vec::new(),
+                        direct_hit: None,
+                        ball_position: state.ball_position,
+                        damage_index: None,
+                    });
+                    let last = damages.len() - 1;
+                    &mut damages[last]
+                }
+            };
+
+            damage.tiles.push(tile);
+            if state.direct_hit {
+                damage.direct_hit = Some(tile);
+            }
+        }
+
+        if let Some(applied) = applied {
+            let matching = damages
+                .iter_mut()
+                .rev()
+                .take_while(|x| x.ball_position == applied.position);
+            for damage in matching {
+                damage.damage_index = Some(applied.damage_index);
+            }
+        }
+    }
+
+    DropshotReport {
+        players: tracker.into_players(),
+        tiles,
+        changes,
+        damages,
+        frames: frames.len(),
+    }
+}
+
+/// The tiles of the level ordered by the number that suffixes their object name, and a lookup
+/// from the object name to the tile's index
+fn floor(objects: &[String]) -> (Vec<Tile>, FnvHashMap<&str, usize>) {
+    const PLATFORM: &str = "TheWorld:PersistentLevel.BreakOutActor_Platform_TA_";
+    let mut platforms: Vec<(u32, usize)> = objects
+        .iter()
+        .enumerate()
+        .filter_map(|(i, object)| {
+            let (_, suffix) = object.split_once(PLATFORM)?;
+            Some((suffix.parse().ok()?, i))
+        })
+        .collect();
+    platforms.sort_unstable();
+
+    let tiles = platforms
+        .iter()
+        .map(|&(_, i)| Tile {
+            object: objects[i].clone(),
+            actor_id: None,
+            side: None,
+        })
+        .collect();
+
+    let indices = platforms
+        .iter()
+        .enumerate()
+        .map(|(tile, &(_, i))| (objects[i].as_str(), tile))
+        .collect();
+
+    (tiles, indices)
+}
+
+/// The blue team defends the half of the field with a negative y coordinate
+fn side_of(position: Vector3f) -> Option<u8> {
+    if position.y < 0.0 {
+        Some(0)
+    } else if position.y > 0.0 {
+        Some(1)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_floor_order() {
+        let objects = [
+            "ShatterShot_VFX.TheWorld:PersistentLevel.BreakOutActor_Platform_TA_10",
+            "TAGame.BreakOutActor_Platform_TA:DamageState",
+            "ShatterShot_VFX.TheWorld:PersistentLevel.BreakOutActor_Platform_TA_2",
+            "ShatterShot_VFX.TheWorld:PersistentLevel.BreakOutActor_Platform_TA_0",
+        ]
+        .map(String::from);
+
+        let (tiles, indices) = floor(&objects);
+        let names: Vec<_> = tiles.iter().map(|x| x.object.as_str()).collect();
+        assert_eq!(names, vec![&objects[3], &objects[2], &objects[0]]);
+        assert_eq!(indices.get(objects[0].as_str()), Some(&2));
+        assert_eq!(indices.get(objects[1].as_str()), None);
+    }
+
+    #[test]
+    fn test_tile_state() {
+        assert_eq!(TileState::from(0), TileState::Undamaged);
+        assert_eq!(TileState::from(2), TileState::Destroyed);
+        assert_eq!(TileState::from(3), TileState::Unknown(3));
+    }
+}
@@ -0,0 +1,510 @@
+//! # Knockout
+//!
+//! Knockout Bash is a free for all where players attack, grab, and block to knock each other out
+//! of the arena. The game replicates the mode through `TAGame.PRI_KnockOut_TA` stats on each
+//! player, `TAGame.Car_KnockOut_TA` state on each car, and a `TAGame.Stunlock_TA` actor attached
+//! to each car. This module summarizes them into:
+//!
+//! - a timeline of knockouts and eliminations
+//! - the attacks, grabs, and blocks of each player
+//! - the impulses applied to each player's car and their direction
+//! - the periods each player was stunned
+//! - the final stats of each player
+
+use super::{as_i32, frames, Change, Player, Tracker};
+use crate::models::Replay;
+use crate::network::attributes::Attribute;
+use crate::network::{ActorId, Vector3f};
+use fnv::FnvHashMap;
+
+/// A summary of the knockout specific network data of a replay
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct KnockoutReport {
+    pub players: Vec<Player>,
+
+    /// Knockouts and eliminations in the order they happened
+    pub timeline: Vec<KnockoutEvent>,
+
+    /// Attacks, grabs, and blocks in the order they happened
+    pub actions: Vec<KnockoutAction>,
+
+    /// Impulses applied to cars in the order they happened
+    pub impulses: Vec<KnockoutImpulse>,
+
+    /// Periods that cars were stunned ordered by when the stun started
+    pub stuns: Vec<StunPeriod>,
+
+    /// The last seen stats of each player, indexed the same as `players`
+    pub stats: Vec<KnockoutStats>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum KnockoutEventKind {
+    /// The player knocked out another player
+    Knockout,
+
+    /// The player was knocked out
+    KnockedOut,
+
+    /// The player was eliminated from the match
+    Eliminated,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct KnockoutEvent {
+    pub frame: usize,
+    pub time: f32,
+
+    /// Index into the report's players
+    pub player: usize,
+    pub kind: KnockoutEventKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum KnockoutActionKind {
+    Attack,
+    Grab,
+    Block,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct KnockoutAction {
+    pub frame: usize,
+    pub time: f32,
+
+    /// Index into the report's players
+    pub player: usize,
+    pub kind: KnockoutActionKind,
+
+    /// The car component last used to attack (eg: `Dodge` or `DoubleJump`)
+    pub component: Option<String>,
+}
+
+/// An impulse that the game applied to a player's car, such as the knockback from being hit
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct KnockoutImpulse {
+    pub frame: usize,
+    pub time: f32,
+
+    /// Index into the report's players
+    pub player: usize,
+
+    /// Unit vector of the impulse's direction
+    pub direction: Vector3f,
+    pub speed: f32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct StunPeriod {
+    /// Index into the report's players
+    pub player: usize,
+    pub start_frame: usize,
+    pub start_time: f32,
+
+    /// When the stun ended, if it ended before the replay did
+    pub end_frame: Option<usize>,
+    pub end_time: Option<f32>,
+
+    /// The number of seconds the stun would last if not mashed out of
+    pub max_stun_time: f32,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct KnockoutStats {
+    pub knockouts: i32,
+    pub knockout_deaths: i32,
+    pub hits: i32,
+    pub grabs: i32,
+    pub blocks: i32,
+    pub damage_caused: i32,
+    pub elimination_order: Option<i32>,
+    pub eliminated: bool,
+}
+
+/// Summarizes the knockout network data of the replay. Only the players are reported for replays
+/// of other game modes, and nothing is reported when the network data was not parsed.
+pub fn analyze(replay: &Replay) -> KnockoutReport {
+    let mut tracker = Tracker::new(replay);
+    let mut report = KnockoutReport {
+        players: Vec::new(),
+        timeline: Vec::new(),
+        actions: Vec::new(),
+        impulses: Vec::new(),
+        stuns: Vec::new(),
+        stats: Vec::new(),
+    };
+
+    let mut components: FnvHashMap<ActorId, ActorId> = FnvHashMap::default();
+    let mut stunlocks: FnvHashMap<ActorId, ActorId> = FnvHashMap::default();
+    let mut open_stuns: FnvHashMap<ActorId, usize> = FnvHashMap::default();
+
+    for (index, frame) in frames(replay).iter().enumerate() {
+        let time = frame.time;
+        for change in tracker.apply(frame) {
+            let update = match change {
+                Change::Updated {
+                    update,
+                    changed: true,
+                } => update,
+                Change::Linked { actor_id, values } => {
+                    // Stats replicated before the player's name
+                    let Some(player) = tracker.pri_player(actor_id) else {
+                        continue;
+                    };
+
+                    for (object_id, attribute) in &values {
+                        let stat = tracker
+                            .object(*object_id)
+                            .strip_prefix("TAGame.PRI_KnockOut_TA:");
+                        if let (Some(stat), Some(value)) = (stat, as_i32(attribute)) {
+                            record_stat(&mut report, player, stat, value, (index, time));
+                        }
+                    }
+                    continue;
+                }
+                Change::Deleted { actor_id } => {
+                    if let Some(stun) = open_stuns.remove(&actor_id) {
+                        report.stuns[stun].end_frame = Some(index);
+                        report.stuns[stun].end_time = Some(time);
+                    }
+                    components.remove(&actor_id);
+                    stunlocks.remove(&actor_id);
+                    continue;
+                }
+                _ => continue,
+            };
+
+            let actor_id = update.actor_id;
+            let attribute = tracker.object(update.object_id);
+            let Some(class_attribute) = attribute.split_once(':') else {
+                continue;
+            };
+
+            match class_attribute {
+                ("TAGame.PRI_KnockOut_TA", stat) => {
+                    let player = tracker.pri_player(actor_id);
+                    if let (Some(player), Some(value)) = (player, as_i32(&update.attribute)) {
+                        record_stat(&mut report, player, stat, value, (index, time));
+                    }
+                }
+                ("TAGame.Car_KnockOut_TA", "UsedAttackComponent") => {
+                    let Attribute::ActiveActor(component) = update.attribute else {
+                        continue;
+                    };
+
+                    if !component.active {
+                        components.remove(&actor_id);
+                        continue;
+                    }
+
+                    components.insert(actor_id, component.actor);
+                    if let Some(player) = tracker.car_player(actor_id) {
+                        push_action(&mut report.actions, index, time, player, || {
+                            component_name(&tracker, component.actor)
+                        });
+                    }
+                }
+                ("TAGame.Car_KnockOut_TA", "ReplicatedStateName") => {
+                    let Some(player) = tracker.car_player(actor_id) else {
+                        continue;
+                    };
+
+                    let state = as_i32(&update.attribute)
+                        .and_then(|x| usize::try_from(x).ok())
+                        .and_then(|x| replay.names.get(x))
+                        .map(|x| x.as_str());
+
+                    let kind = match state {
+                        Some("Attack") | Some("GrabAttack") => KnockoutActionKind::Attack,
+                        Some("Grabbing") => KnockoutActionKind::Grab,
+                        Some("Blocking") => KnockoutActionKind::Block,
+                        _ => continue,
+                    };
+
+                    let component = components
+                        .get(&actor_id)
+                        .and_then(|&x| component_name(&tracker, x));
+
+                    if kind == KnockoutActionKind::Attack {
+                        push_action(&mut report.actions, index, time, player, || component);
+                    } else {
+                        report.actions.push(KnockoutAction {
+                            frame: index,
+                            time,
+                            player,
+                            kind,
+                            component,
+                        });
+                    }
+                }
+                ("TAGame.Car_KnockOut_TA", "ReplicatedImpulse") => {
+                    let Attribute::Impulse(ref impulse) = update.attribute else {
+                        continue;
+                    };
+
+                    if let Some(player) = tracker.car_player(actor_id) {
+                        report.impulses.push(KnockoutImpulse {
+                            frame: index,
+                            time,
+                            player,
+                            direction: impulse.direction(),
+                            speed: impulse.speed,
+                        });
+                    }
+                }
+                ("TAGame.Stunlock_TA", "Car") => {
+                    if let Attribute::ActiveActor(car) = update.attribute {
+                        stunlocks.insert(actor_id, car.actor);
+                    }
+                }
+                ("TAGame.Stunlock_TA", "MaxStunTime") => {
+                    let Attribute::Float(max_stun_time) = update.attribute else {
+                        continue;
+                    };
+
+                    if let Some(stun) = open_stuns.remove(&actor_id) {
+                        report.stuns[stun].end_frame = Some(index);
+                        report.stuns[stun].end_time = Some(time);
+                    }
+
+                    let player = stunlocks
+                        .get(&actor_id)
+                        .and_then(|&car| tracker.car_player(car));
+
+                    if let Some(player) = player.filter(|_| max_stun_time > 0.0) {
+                        open_stuns.insert(actor_id, report.stuns.len());
+                        report.stuns.push(StunPeriod {
+                            player,
+                            start_frame: index,
+                            start_time: time,
+                            end_frame: None,
+                            end_time: None,
+                            max_stun_time,
+                        });
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    report.players = tracker.into_players();
+    report
+        .stats
+        .resize(report.players.len(), KnockoutStats::default());
+    report
+}
+
+/// Records the player's latest value of the stat, with an event in the timeline for each
+/// knockout, knockout death, and elimination the value increased by
+fn record_stat(
+    report: &mut KnockoutReport,
+    player: usize,
+    stat: &str,
+    value: i32,
+    (frame, time): (usize, f32),
+) {
+    if report.stats.len() <= player {
+        report.stats.resize(player + 1, KnockoutStats::default());
+    }
+
+    let stats = &mut report.stats[player];
+    let (previous, kind) = match stat {
+        "Knockouts" => (
+            std::mem::replace(&mut stats.knockouts, value),
+            KnockoutEventKind::Knockout,
+        ),
+        "KnockoutDeaths" => (
+            std::mem::replace(&mut stats.knockout_deaths, value),
+            KnockoutEventKind::KnockedOut,
+        ),
+        "bIsEliminated" => (
+            i32::from(std::mem::replace(&mut stats.eliminated, value != 0)),
+            KnockoutEventKind::Eliminated,
+        ),
+        _ => {
+            match stat {
+                "Hits" => stats.hits = value,
+                "Grabs" => stats.grabs = value,
+                "Blocks" => stats.blocks = value,
+                "DamageCaused" => stats.damage_caused = value,
+                "EliminationOrder" => stats.elimination_order = Some(value),
+                _ => {}
+            }
+            return;
+        }
+    };
+
+    for _ in previous..value {
+        report.timeline.push(KnockoutEvent {
+            frame,
+            time,
+            player,
+            kind,
+        });
+    }
+}
+
+/// Records an attack unless the player was already recorded attacking in the same frame, as an
+/// attack is replicated as both the attack component used and the car's state.
+fn push_action<F>(actions: &mut Vec<KnockoutAction>, frame: usize, time: f32, player: usize, f: F)
+where
+    F: FnOnce() -> Option<String>,
+{
+    let duplicate = actions
+        .iter()
+        .rev()
+        .take_while(|x| x.frame == frame)
+        .any(|x| x.player == player && x.kind == KnockoutActionKind::Attack);
+
+    if !duplicate {
+        actions.push(KnockoutAction {
+            frame,
+            time,
+            player,
+            kind: KnockoutActionKind::Attack,
+            component: f(),
+        });
+    }
+}
+
+/// The name of the car component from its archetype (eg: `...:CarArchetype.Dodge` is `Dodge`)
+fn component_name(tracker: &Tracker<'_>, actor_id: ActorId) -> Option<String> {
+    tracker
+        .actor_object(actor_id)
+        .and_then(|x| x.rsplit_once('.'))
+        .map(|(_, name)| String::from(name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::NetworkFrames;
+    use crate::network::{Frame, NewActor, ObjectId, StreamId, Trajectory, UpdatedAttribute};
+    use crate::ParserBuilder;
+
+    #[test]
+    fn test_stat_replicated_with_player_name() {
+        let data = include_bytes!("../../assets/replays/good/f770.replay");
+        let mut replay = ParserBuilder::new(&data[..])
+            .never_parse_network_data()
+            .parse()
+            .unwrap();
+
+        let object = |name: &str| {
+            let position = replay.objects.iter().position(|x| x == name).unwrap();
+            ObjectId(position as i32)
+        };
+
+        let pri = ActorId(5);
+        let update = |name: &str, attribute| UpdatedAttribute {
+            actor_id: pri,
+            stream_id: StreamId(0),
+            object_id: object(name),
+            attribute,
+        };
+
+        // A player that joins mid match has their stats replicated in the same frame as their
+        // name, and the stats may come first
+        let frame = Frame {
+            time: 10.0,
+            delta: 0.03,
+            new_actors: vec![NewActor {
+                actor_id: pri,
+                name_id: None,
+                object_id: object("TAGame.PRI_KnockOut_TA"),
+                initial_trajectory: Trajectory {
+                    location: None,
+                    rotation: None,
+                },
+            }],
+            deleted_actors: Vec::new(),
+            updated_actors: vec![
+                update("TAGame.PRI_KnockOut_TA:Knockouts", Attribute::Int(2)),
+                update("TAGame.PRI_KnockOut_TA:Hits", Attribute::Int(7)),
+                update(
+                    "Engine.PlayerReplicationInfo:PlayerName",
+                    Attribute::String(String::from("joiner")),
+                ),
+            ],
+        };
+
+        replay.network_frames = Some(NetworkFrames {
+            frames: vec![frame.clone(), frame],
+        });
+
+        let report = analyze(&replay);
+        assert_eq!(report.players.len(), 1);
+        assert_eq!(report.stats[0].knockouts, 2);
+        assert_eq!(report.stats[0].hits, 7);
+        assert_eq!(report.timeline.len(), 2);
+    }
+
+    #[test]
+    fn test_stat_replicated_before_player_name() {
+        let data = include_bytes!("../../assets/replays/good/f770.replay");
+        let mut replay = ParserBuilder::new(&data[..])
+            .never_parse_network_data()
+            .parse()
+            .unwrap();
+
+        let object = |name: &str| {
+            let position = replay.objects.iter().position(|x| x == name).unwrap();
+            ObjectId(position as i32)
+        };
+
+        let pri = ActorId(5);
+        let update = |name: &str, attribute| UpdatedAttribute {
+            actor_id: pri,
+            stream_id: StreamId(0),
+            object_id: object(name),
+            attribute,
+        };
+
+        // The stats are replicated a frame before the name, and the name's frame resends one of
+        // them with the same value
+        let stats = Frame {
+            time: 10.0,
+            delta: 0.03,
+            new_actors: vec![NewActor {
+                actor_id: pri,
+                name_id: None,
+                object_id: object("TAGame.PRI_KnockOut_TA"),
+                initial_trajectory: Trajectory {
+                    location: None,
+                    rotation: None,
+                },
+            }],
+            deleted_actors: Vec::new(),
+            updated_actors: vec![
+                update("TAGame.PRI_KnockOut_TA:Knockouts", Attribute::Int(2)),
+                update("TAGame.PRI_KnockOut_TA:Hits", Attribute::Int(7)),
+            ],
+        };
+
+        let name = Frame {
+            time: 10.03,
+            delta: 0.03,
+            new_actors: Vec::new(),
+            deleted_actors: Vec::new(),
+            updated_actors: vec![
+                update("TAGame.PRI_KnockOut_TA:Knockouts", Attribute::Int(2)),
+                update(
+                    "Engine.PlayerReplicationInfo:PlayerName",
+                    Attribute::String(String::from("joiner")),
+                ),
+            ],
+        };
+
+        replay.network_frames = Some(NetworkFrames {
+            frames: vec![stats, name],
+        });
+
+        let report = analyze(&replay);
+        assert_eq!(report.players.len(), 1);
+        assert_eq!(report.stats[0].knockouts, 2);
+        assert_eq!(report.stats[0].hits, 7);
+        assert_eq!(report.timeline.len(), 2);
+        assert!(report.timeline.iter().all(|x| x.frame == 1));
+    }
+}
@@ -0,0 +1,363 @@
+//! # Loadouts
+//!
+//! A player's cosmetics are replicated on their PRI actor as product ids (`Loadout`) along with
+//! the attributes of those products (`LoadoutOnline`) such as their paint or title. Most replays
+//! replicate a loadout per team (`ClientLoadouts` and `ClientLoadoutsOnline`) while older replays
+//! replicate a single loadout. The colors of the car are replicated separately on the car as a
+//! `TeamPaint`. This module combines them into a loadout per player per team.
+
+use super::{frames, Change, Player, Tracker};
+use crate::models::Replay;
+use crate::network::attributes::{Attribute, Loadout, Product, ProductValue, TeamPaint};
+use fnv::FnvHashMap;
+
+/// The loadouts of the players in a replay
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LoadoutReport {
+    pub players: Vec<Player>,
+
+    /// Loadouts ordered by player and then team
+    pub loadouts: Vec<PlayerLoadout>,
+}
+
+impl LoadoutReport {
+    /// The loadouts of the player at the given index of `players`
+    pub fn player_loadouts(&self, player: usize) -> impl Iterator<Item = &PlayerLoadout> + '_ {
+        self.loadouts.iter().filter(move |x| x.player == player)
+    }
+}
+
+/// The last loadout replicated for a player
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PlayerLoadout {
+    /// Index into the report's players
+    pub player: usize,
+
+    /// The team the loadout is for: 0 for blue and 1 for orange. `None` when the replay
+    /// replicates a single loadout for either team.
+    pub team: Option<u8>,
+
+    /// The equipped items ordered by slot
+    pub items: Vec<LoadoutItem>,
+
+    /// The colors of the player's car while on the team
+    pub paint: Option<TeamPaint>,
+
+    /// The player's title (eg: `Season10GrandChampion`)
+    pub title: Option<String>,
+}
+
+impl PlayerLoadout {
+    /// The item equipped in the slot
+    pub fn item(&self, slot: LoadoutSlot) -> Option<&LoadoutItem> {
+        self.items.iter().find(|x| x.slot == slot)
+    }
+}
+
+/// Where an item is equipped, numbered as the game does
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub enum LoadoutSlot {
+    Body,
+    Decal,
+    Wheels,
+    Boost,
+    Antenna,
+    Topper,
+    PaintFinish,
+    AccentFinish,
+    EngineAudio,
+    Trail,
+    GoalExplosion,
+    PlayerBanner,
+    GoalStinger,
+    PlayerAvatar,
+    AvatarBorder,
+    Title,
+    EsportsTeam,
+    Other(u8),
+}
+
+impl From<u8> for LoadoutSlot {
+    fn from(x: u8) -> Self {
+        match x {
+            0 => LoadoutSlot::Body,
+            1 => LoadoutSlot::Decal,
+            2 => LoadoutSlot::Wheels,
+            3 => LoadoutSlot::Boost,
+            4 => LoadoutSlot::Antenna,
+            5 => LoadoutSlot::Topper,
+            7 => LoadoutSlot::PaintFinish,
+            12 => LoadoutSlot::AccentFinish,
+            13 => LoadoutSlot::EngineAudio,
+            14 => LoadoutSlot::Trail,
+            15 => LoadoutSlot::GoalExplosion,
+            16 => LoadoutSlot::PlayerBanner,
+            18 => LoadoutSlot::GoalStinger,
+            19 => LoadoutSlot::PlayerAvatar,
+            20 => LoadoutSlot::AvatarBorder,
+            21 => LoadoutSlot::Title,
+            22 => LoadoutSlot::EsportsTeam,
+            x => LoadoutSlot::Other(x),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LoadoutItem {
+    pub slot: LoadoutSlot,
+
+    /// The product id of the item, if the slot's item is replicated in the `Loadout`
+    pub product_id: Option<u32>,
+
+    /// The attributes of the item in the order they were replicated
+    pub attributes: Vec<ItemAttribute>,
+}
+
+impl LoadoutItem {
+    /// The paint id of a painted item
+    pub fn painted(&self) -> Option<u32> {
+        self.attributes.iter().find_map(|x| match x.value {
+            ProductValue::OldPaint(paint) | ProductValue::NewPaint(paint) => Some(paint),
+            _ => None,
+        })
+    }
+
+    /// The special edition id of a special edition item
+    pub fn special_edition(&self) -> Option<u32> {
+        self.attributes.iter().find_map(|x| match x.value {
+            ProductValue::SpecialEdition(edition) => Some(edition),
+            _ => None,
+        })
+    }
+
+    /// The team id of a team edition item
+    pub fn team_edition(&self) -> Option<u32> {
+        self.attributes.iter().find_map(|x| match x.value {
+            ProductValue::OldTeamEdition(team) | ProductValue::NewTeamEdition(team) => Some(team),
+            _ => None,
+        })
+    }
+
+    /// Whether the item has a certification attribute. Replays do not replicate which
+    /// certification it is.
+    pub fn certified(&self) -> bool {
+        self.attributes
+            .iter()
+            .any(|x| x.name == "TAGame.ProductAttribute_Certified_TA")
+    }
+}
+
+/// An attribute of an item, such as its paint
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ItemAttribute {
+    /// The object name of the attribute (eg: `TAGame.ProductAttribute_Painted_TA`)
+    pub name: String,
+    pub value: ProductValue,
+}
+
+/// The loadout attributes last replicated for a player
+#[derive(Debug, Default)]
+struct Replicated {
+    single: Option<Loadout>,
+    single_online: Option<Vec<Vec<Product>>>,
+    teams: Option<[Loadout; 2]>,
+    teams_online: Option<[Vec<Vec<Product>>; 2]>,
+    paints: [Option<TeamPaint>; 2],
+    last_paint: Option<TeamPaint>,
+}
+
+impl Replicated {
+    fn replicate(&mut self, attribute: &Attribute) {
+        match attribute {
+            Attribute::Loadout(loadout) => self.single = Some(**loadout),
+            Attribute::LoadoutOnline(online) => self.single_online = Some(online.clone()),
+            Attribute::TeamLoadout(loadouts) => {
+                self.teams = Some([loadouts.blue, loadouts.orange]);
+            }
+            Attribute::LoadoutsOnline(online) => {
+                self.teams_online = Some([online.blue.clone(), online.orange.clone()]);
+            }
+            &Attribute::TeamPaint(paint) => {
+                if let Some(x) = self.paints.get_mut(usize::from(paint.team)) {
+                    *x = Some(paint);
+                }
+                self.last_paint = Some(paint);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Resolves the loadouts of the players in the replay. Only the players are reported when the
+/// network data was not parsed.
+pub fn analyze(replay: &Replay) -> LoadoutReport {
+    let mut tracker = Tracker::new(replay);
+    let mut replicated: FnvHashMap<usize, Replicated> = FnvHashMap::default();
+
+    for frame in frames(replay) {
+        for change in tracker.apply(frame) {
+            let update = match change {
+                Change::Updated {
+                    update,
+                    changed: true,
+                } => update,
+                Change::Linked { actor_id, values } => {
+                    // Loadouts replicated before the player's name
+                    if let Some(player) = tracker.pri_player(actor_id) {
+                        let entry = replicated.entry(player).or_default();
+                        for (_, attribute) in &values {
+                            entry.replicate(attribute);
+                        }
+                    }
+                    continue;
+                }
+                _ => continue,
+            };
+
+            let actor_id = update.actor_id;
+            let player = match update.attribute {
+                Attribute::TeamPaint(_) => tracker.car_player(actor_id),
+                Attribute::Loadout(_)
+                | Attribute::LoadoutOnline(_)
+                | Attribute::TeamLoadout(_)
+                | Attribute::LoadoutsOnline(_) => tracker.pri_player(actor_id),
+                _ => continue,
+            };
+
+            if let Some(player) = player {
+                replicated
+                    .entry(player)
+                    .or_default()
+                    .replicate(&update.attribute);
+            }
+        }
+    }
+
+    let mut players: Vec<_> = replicated.into_iter().collect();
+    players.sort_unstable_by_key(|&(player, _)| player);
+
+    let mut loadouts = Vec::new();
+    for (player, replicated) in players {
+        if let Some(teams) = replicated.teams {
+            let online = replicated.teams_online.as_ref();
+            for (team, loadout) in teams.iter().enumerate() {
+                loadouts.push(player_loadout(
+                    &tracker,
+                    player,
+                    Some(team as u8),
+                    Some(loadout),
+                    online.map(|x| x[team].as_slice()),
+                    replicated.paints[team],
+                ));
+            }
+        } else if replicated.single.is_some() || replicated.single_online.is_some() {
+            loadouts.push(player_loadout(
+                &tracker,
+                player,
+                None,
+                replicated.single.as_ref(),
+                replicated.single_online.as_deref(),
+                replicated.last_paint,
+            ));
+        }
+    }
+
+    LoadoutReport {
+        players: tracker.into_players(),
+        loadouts,
+    }
+}
+
+fn player_loadout(
+    tracker: &Tracker<'_>,
+    player: usize,
+    team: Option<u8>,
+    loadout: Option<&Loadout>,
+    online: Option<&[Vec<Product>]>,
+    paint: Option<TeamPaint>,
+) -> PlayerLoadout {
+    let mut items: Vec<LoadoutItem> = loadout
+        .map(item_ids)
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|&(_, id)| id != 0)
+        .map(|(slot, id)| LoadoutItem {
+            slot,
+            product_id: Some(id),
+            attributes: Vec::new(),
+        })
+        .collect();
+
+    let online = online.unwrap_or_default();
+    for (slot, products) in online.iter().enumerate() {
+        if products.is_empty() {
+            continue;
+        }
+
+        let slot = LoadoutSlot::from(slot as u8);
+        let attributes = products.iter().map(|x| ItemAttribute {
+            name: String::from(tracker.object(x.object_ind)),
+            value: x.value.clone(),
+        });
+
+        match items.iter_mut().find(|x| x.slot == slot) {
+            Some(item) => item.attributes.extend(attributes),
+            None => items.push(LoadoutItem {
+                slot,
+                product_id: None,
+                attributes: attributes.collect(),
+            }),
+        }
+    }
+
+    items.sort_by_key(|x| x.slot);
+    let title = items
+        .iter()
+        .flat_map(|x| x.attributes.iter())
+        .find_map(|x| match &x.value {
+            ProductValue::Title(title) => Some(title.clone()),
+            _ => None,
+        });
+
+    PlayerLoadout {
+        player,
+        team,
+        items,
+        paint,
+        title,
+    }
+}
+
+/// The product ids of the loadout and the slots they are equipped in. Products missing from older
+/// loadouts are reported as 0.
+fn item_ids(loadout: &Loadout) -> Vec<(LoadoutSlot, u32)> {
+    vec![
+        (LoadoutSlot::Body, loadout.body),
+        (LoadoutSlot::Decal, loadout.decal),
+        (LoadoutSlot::Wheels, loadout.wheels),
+        (LoadoutSlot::Boost, loadout.rocket_trail),
+        (LoadoutSlot::Antenna, loadout.antenna),
+        (LoadoutSlot::Topper, loadout.topper),
+        (LoadoutSlot::EngineAudio, loadout.engine_audio.unwrap_or(0)),
+        (LoadoutSlot::Trail, loadout.trail.unwrap_or(0)),
+        (
+            LoadoutSlot::GoalExplosion,
+            loadout.goal_explosion.unwrap_or(0),
+        ),
+        (LoadoutSlot::PlayerBanner, loadout.banner.unwrap_or(0)),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_loadout_slot_order() {
+        assert_eq!(LoadoutSlot::from(0), LoadoutSlot::Body);
+        assert_eq!(LoadoutSlot::from(21), LoadoutSlot::Title);
+        assert_eq!(LoadoutSlot::from(6), LoadoutSlot::Other(6));
+        assert!(LoadoutSlot::Boost < LoadoutSlot::Trail);
+        assert!(LoadoutSlot::Title < LoadoutSlot::Other(0));
+    }
+}
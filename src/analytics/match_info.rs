@@ -0,0 +1,147 @@
+//! # Match info
+//!
+//! Server side details of a match, such as the playlist and the server's region, are only
+//! replicated in the network data by the game's replication info (GRI) and game event actors.
+//! Most are replicated when the actors are created at the start of the replay, so only the first
+//! frames need to be decoded to collect them. The server's address is the exception, see
+//! [`MatchInfo::server_ip`].
+//!
+//! ```
+//! let data = include_bytes!("../../assets/replays/good/181d6.replay");
+//! let info = boxcars::analytics::match_info::parse(&data[..]).unwrap();
+//! assert_eq!(info.server_region.as_deref(), Some("USE"));
+//! assert_eq!(info.bot_match, Some(true));
+//! ```
+
+use super::frames;
+use crate::errors::ParseError;
+use crate::models::Replay;
+use crate::network::attributes::Attribute;
+use crate::parser::ParserBuilder;
+
+/// How many seconds of the network data [`parse`] decodes
+pub const FIRST_SECONDS: f32 = 5.0;
+
+const ATTRIBUTES: [&str; 12] = [
+    "ProjectX.GRI_X:GameServerID",
+    "ProjectX.GRI_X:MatchGUID",
+    "ProjectX.GRI_X:MatchGuid",
+    "ProjectX.GRI_X:ReplicatedGameMutatorIndex",
+    "ProjectX.GRI_X:ReplicatedGamePlaylist",
+    "ProjectX.GRI_X:ReplicatedServerRegion",
+    "TAGame.GameEvent_Soccar_TA:MaxScore",
+    "TAGame.GameEvent_Soccar_TA:SeriesLength",
+    "TAGame.GameEvent_TA:BotSkill",
+    "TAGame.GameEvent_TA:MatchStartEpoch",
+    "TAGame.GameEvent_TA:bIsBotMatch",
+    "TAGame.GRI_TA:NewDedicatedServerIP",
+];
+
+/// Details of the match replicated in the network data. Which details are replicated depends on
+/// the game version and the match, so each may be missing. [`parse`] only collects the details
+/// replicated in the first [`FIRST_SECONDS`], which excludes `server_ip`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct MatchInfo {
+    /// The match's guid (eg: `3102849011EE7A893CDBA1B11737A86E`), which has been replicated under
+    /// both the `MatchGUID` and `MatchGuid` attributes
+    pub match_guid: Option<String>,
+
+    /// The id of the game server, which older replays replicate as a number
+    pub game_server_id: Option<String>,
+
+    /// The region of the game server (eg: `USE`)
+    pub server_region: Option<String>,
+
+    /// The playlist id of the match (eg: 3 for ranked standard)
+    pub playlist: Option<i32>,
+
+    /// The index of the mutator preset for private matches, or -1 when there is none
+    pub mutator_index: Option<i32>,
+
+    /// The address of the game server (eg: `45.63.50.200:7854`). It is replicated near the end of
+    /// the match rather than at the start, so [`parse`] does not collect it. Use [`analyze`] on a
+    /// replay with all of its network data decoded instead.
+    pub server_ip: Option<String>,
+
+    /// When the match started as seconds since the unix epoch
+    pub match_start_epoch: Option<i64>,
+    pub bot_match: Option<bool>,
+
+    /// The skill of the bots as the raw replicated integer, which is replicated even when there
+    /// are no bots. How the skill is encoded in the integer is not known for every game version,
+    /// so it is not converted.
+    pub bot_skill: Option<i32>,
+
+    /// The score that ends the match for modes that play to a score
+    pub max_score: Option<i32>,
+
+    /// The number of games in a series (eg: 3 for a best of three)
+    pub series_length: Option<i32>,
+}
+
+/// Decodes only the first [`FIRST_SECONDS`] of the network data to collect the match info, which
+/// is much cheaper than decoding the whole replay. Details that are replicated later, such as
+/// [`MatchInfo::server_ip`], are missing. Decoding does not continue until every detail is seen,
+/// as details that a replay never replicates would always require decoding the whole replay.
+pub fn parse(data: &[u8]) -> Result<MatchInfo, ParseError> {
+    let replay = ParserBuilder::new(data)
+        .must_parse_network_data()
+        .only_attributes(ATTRIBUTES)
+        .stop_after_time(FIRST_SECONDS)
+        .parse()?;
+    Ok(analyze(&replay))
+}
+
+/// Collects the match info from the replay. The first value replicated for each detail is
+/// reported, and nothing is reported when the network data was not parsed.
+pub fn analyze(replay: &Replay) -> MatchInfo {
+    let mut info = MatchInfo::default();
+    let updates = frames(replay).iter().flat_map(|x| x.updated_actors.iter());
+    for update in updates {
+        let Some(name) = replay.objects.get(usize::from(update.object_id)) else {
+            continue;
+        };
+
+        match (name.as_str(), &update.attribute) {
+            ("ProjectX.GRI_X:MatchGUID" | "ProjectX.GRI_X:MatchGuid", Attribute::String(x)) => {
+                info.match_guid.get_or_insert_with(|| x.clone());
+            }
+            ("ProjectX.GRI_X:GameServerID", Attribute::String(x)) => {
+                info.game_server_id.get_or_insert_with(|| x.clone());
+            }
+            ("ProjectX.GRI_X:GameServerID", Attribute::QWord(x)) => {
+                info.game_server_id.get_or_insert_with(|| x.to_string());
+            }
+            ("ProjectX.GRI_X:ReplicatedServerRegion", Attribute::String(x)) => {
+                info.server_region.get_or_insert_with(|| x.clone());
+            }
+            ("ProjectX.GRI_X:ReplicatedGamePlaylist", &Attribute::Int(x)) => {
+                info.playlist.get_or_insert(x);
+            }
+            ("ProjectX.GRI_X:ReplicatedGameMutatorIndex", &Attribute::Int(x)) => {
+                info.mutator_index.get_or_insert(x);
+            }
+            ("TAGame.GRI_TA:NewDedicatedServerIP", Attribute::String(x)) => {
+                info.server_ip.get_or_insert_with(|| x.clone());
+            }
+            ("TAGame.GameEvent_TA:MatchStartEpoch", &Attribute::Int64(x)) => {
+                info.match_start_epoch.get_or_insert(x);
+            }
+            ("TAGame.GameEvent_TA:bIsBotMatch", &Attribute::Boolean(x)) => {
+                info.bot_match.get_or_insert(x);
+            }
+            ("TAGame.GameEvent_TA:BotSkill", &Attribute::Int(x)) => {
+                info.bot_skill.get_or_insert(x);
+            }
+            ("TAGame.GameEvent_Soccar_TA:MaxScore", &Attribute::Int(x)) => {
+                info.max_score.get_or_insert(x);
+            }
+            ("TAGame.GameEvent_Soccar_TA:SeriesLength", &Attribute::Int(x)) => {
+                info.series_length.get_or_insert(x);
+            }
+            _ => {}
+        }
+    }
+
+    info
+}
@@ -0,0 +1,359 @@
+//! # Analytics
+//!
+//! The network data is a low level stream of actors being created, updated, and destroyed. The
+//! game mode specific modules here replay that stream and summarize it into events that can be
+//! consumed without knowing which classes and attributes the game uses to replicate them.
+//!
+//! Rocket League periodically resends the state of every actor (at each keyframe), so the
+//! analyses only react to attribute values that changed rather than every update.
+//!
+//! ```
+//! let data = include_bytes!("../../assets/replays/good/f770.replay");
+//! let replay = boxcars::ParserBuilder::new(&data[..])
+//!     .must_parse_network_data()
+//!     .parse()
+//!     .unwrap();
+//!
+//! let report = boxcars::analytics::knockout::analyze(&replay);
+//! for event in &report.timeline {
+//!     let player = &report.players[event.player];
+//!     println!("{:.2}: {} {:?}", event.time, player.name, event.kind);
+//! }
+//! ```
+
+use crate::identity::PlayerIdentity;
+use crate::models::Replay;
+use crate::network::attributes::Attribute;
+use crate::network::{ActorId, Frame, ObjectId, UpdatedAttribute};
+use fnv::FnvHashMap;
+
+pub mod ball;
+pub mod dropshot;
+pub mod knockout;
+pub mod loadout;
+pub mod match_info;
+pub mod rumble;
+
+/// A player seen in the network data. Players are identified by the unique id replicated on their
+/// player replication info (PRI) actor, so a player that leaves and rejoins is reported once.
+/// Bots do not have an identity and are identified by their name instead.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Player {
+    /// The last name replicated for the player
+    pub name: String,
+
+    /// The identity of the player, or `None` for bots
+    pub identity: Option<PlayerIdentity>,
+
+    /// The team the player was last on: 0 for blue and 1 for orange
+    pub team: Option<u8>,
+
+    /// The PRI actor last associated with the player
+    pub actor_id: ActorId,
+}
+
+/// What happened to an actor during a frame
+#[derive(Debug)]
+pub(crate) enum Change<'f> {
+    /// An actor's attribute was updated. `changed` is false when the value is the same as the
+    /// last value seen for the attribute.
+    Updated {
+        update: &'f UpdatedAttribute,
+        changed: bool,
+    },
+
+    /// An actor was created that is not a keyframe's recreation of a live actor
+    Created { actor_id: ActorId },
+
+    /// An actor was destroyed
+    Deleted { actor_id: ActorId },
+
+    /// A PRI was linked to a player for the first time. The values are the PRI's attributes
+    /// that were replicated in earlier frames, before the player was known, so they can be
+    /// attributed to the player now.
+    Linked {
+        actor_id: ActorId,
+        values: Vec<(ObjectId, Attribute)>,
+    },
+}
+
+/// Replays the network frames while tracking the actors that are alive, the last value of their
+/// attributes, and which player each car and PRI belongs to.
+#[derive(Debug)]
+pub(crate) struct Tracker<'a> {
+    objects: &'a [String],
+    actors: FnvHashMap<ActorId, ObjectId>,
+    values: FnvHashMap<ActorId, FnvHashMap<ObjectId, Attribute>>,
+    cars: FnvHashMap<ActorId, ActorId>,
+    pris: FnvHashMap<ActorId, usize>,
+    teams: FnvHashMap<ActorId, u8>,
+    names: FnvHashMap<ActorId, String>,
+    identities: FnvHashMap<ActorId, PlayerIdentity>,
+    unlinked: Vec<ActorId>,
+    players: Vec<Player>,
+}
+
+impl<'a> Tracker<'a> {
+    pub fn new(replay: &'a Replay) -> Self {
+        Tracker {
+            objects: &replay.objects,
+            actors: FnvHashMap::default(),
+            values: FnvHashMap::default(),
+            cars: FnvHashMap::default(),
+            pris: FnvHashMap::default(),
+            teams: FnvHashMap::default(),
+            names: FnvHashMap::default(),
+            identities: FnvHashMap::default(),
+            unlinked: Vec::new(),
+            players: Vec::new(),
+        }
+    }
+
+    /// Applies the frame and returns the changes in the order: deletions, creations, updates, then
+    /// links. Actors recreated with the same object (as happens at keyframes) keep their attribute
+    /// values and are not reported as created. PRIs are linked to their players before the changes
+    /// are returned, so the player of an update can be resolved even when the PRI's name is
+    /// replicated after the update in the same frame.
+    pub fn apply<'f>(&mut self, frame: &'f Frame) -> Vec<Change<'f>> {
+        let mut changes = Vec::with_capacity(frame.updated_actors.len());
+        for &actor_id in &frame.deleted_actors {
+            self.actors.remove(&actor_id);
+            self.forget(actor_id);
+            changes.push(Change::Deleted { actor_id });
+        }
+
+        for actor in &frame.new_actors {
+            let previous = self.actors.insert(actor.actor_id, actor.object_id);
+            if previous != Some(actor.object_id) {
+                self.forget(actor.actor_id);
+                changes.push(Change::Created {
+                    actor_id: actor.actor_id,
+                });
+            }
+        }
+
+        for update in &frame.updated_actors {
+            let values = self.values.entry(update.actor_id).or_default();
+            let changed = values.get(&update.object_id) != Some(&update.attribute);
+            if changed {
+                values.insert(update.object_id, update.attribute.clone());
+                self.link(update);
+            }
+
+            changes.push(Change::Updated { update, changed });
+        }
+
+        // The name and unique id of a PRI are replicated in any order, so the PRI is linked to
+        // its player once all of the frame's updates are seen
+        for actor_id in std::mem::take(&mut self.unlinked) {
+            let first = !self.pris.contains_key(&actor_id);
+            self.link_player(actor_id);
+            if first && self.pris.contains_key(&actor_id) {
+                changes.push(Change::Linked {
+                    actor_id,
+                    values: self.earlier_values(&changes, actor_id),
+                });
+            }
+        }
+
+        changes
+    }
+
+    /// The name of the object (or attribute) with the given id
+    pub fn object(&self, object_id: ObjectId) -> &'a str {
+        self.objects
+            .get(usize::from(object_id))
+            .map(|x| x.as_str())
+            .unwrap_or_default()
+    }
+
+    /// The name of the object that a live actor is an instance of
+    pub fn actor_object(&self, actor_id: ActorId) -> Option<&'a str> {
+        self.actors.get(&actor_id).map(|&x| self.object(x))
+    }
+
+    /// The player that owns the PRI actor
+    pub fn pri_player(&self, actor_id: ActorId) -> Option<usize> {
+        self.pris.get(&actor_id).copied()
+    }
+
+    /// The player driving the car actor
+    pub fn car_player(&self, actor_id: ActorId) -> Option<usize> {
+        self.cars.get(&actor_id).and_then(|x| self.pri_player(*x))
+    }
+
+    pub fn players(&self) -> &[Player] {
+        &self.players
+    }
+
+    pub fn into_players(self) -> Vec<Player> {
+        self.players
+    }
+
+    /// The last values of the actor's attributes, except for those already reported as changed
+    fn earlier_values(&self, changes: &[Change], actor_id: ActorId) -> Vec<(ObjectId, Attribute)> {
+        let reported = |object_id: ObjectId| {
+            changes.iter().any(|x| match x {
+                Change::Updated {
+                    update,
+                    changed: true,
+                } => update.actor_id == actor_id && update.object_id == object_id,
+                _ => false,
+            })
+        };
+
+        let values = self.values.get(&actor_id).into_iter().flatten();
+        let mut values: Vec<_> = values
+            .filter(|&(&object_id, _)| !reported(object_id))
+            .map(|(&object_id, attribute)| (object_id, attribute.clone()))
+            .collect();
+
+        // The values are kept in a hash map, so they are ordered by attribute
+        values.sort_unstable_by_key(|&(object_id, _)| object_id);
+        values
+    }
+
+    fn forget(&mut self, actor_id: ActorId) {
+        self.values.remove(&actor_id);
+        self.cars.remove(&actor_id);
+        self.pris.remove(&actor_id);
+        self.teams.remove(&actor_id);
+        self.names.remove(&actor_id);
+        self.identities.remove(&actor_id);
+    }
+
+    fn link(&mut self, update: &UpdatedAttribute) {
+        match (self.object(update.object_id), &update.attribute) {
+            ("Engine.Pawn:PlayerReplicationInfo", Attribute::ActiveActor(pri)) => {
+                if pri.active {
+                    self.cars.insert(update.actor_id, pri.actor);
+                } else {
+                    self.cars.remove(&update.actor_id);
+                }
+            }
+            ("Engine.PlayerReplicationInfo:PlayerName", Attribute::String(name)) => {
+                self.names.insert(update.actor_id, name.clone());
+                self.unlinked.push(update.actor_id);
+            }
+            ("Engine.PlayerReplicationInfo:UniqueId", Attribute::UniqueId(unique_id)) => {
+                match PlayerIdentity::from_unique_id(unique_id) {
+                    Some(identity) => self.identities.insert(update.actor_id, identity),
+                    None => self.identities.remove(&update.actor_id),
+                };
+                self.unlinked.push(update.actor_id);
+            }
+            ("Engine.PlayerReplicationInfo:Team", Attribute::ActiveActor(team)) => {
+                let team = match team.active {
+                    true => self.actor_object(team.actor).and_then(team_number),
+                    false => None,
+                };
+
+                // Players leave their team when they are eliminated or leave the match, so the
+                // last team is kept
+                let Some(team) = team else {
+                    return;
+                };
+
+                self.teams.insert(update.actor_id, team);
+                if let Some(index) = self.pri_player(update.actor_id) {
+                    self.players[index].team = Some(team);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Associates the PRI with the player of the same identity, or of the same name for bots.
+    /// A PRI is not associated with a player until its name is known.
+    fn link_player(&mut self, actor_id: ActorId) {
+        let Some(name) = self.names.get(&actor_id) else {
+            return;
+        };
+
+        let identity = self.identities.get(&actor_id);
+        let existing = match identity {
+            Some(identity) => self
+                .players
+                .iter()
+                .position(|x| x.identity.as_ref() == Some(identity)),
+            None => self
+                .players
+                .iter()
+                .position(|x| x.identity.is_none() && &x.name == name),
+        };
+
+        // A PRI whose unique id is replicated after its name already has a player without an
+        // identity, which the identity is given to
+        let existing = existing.or_else(|| {
+            self.pri_player(actor_id)
+                .filter(|&x| self.players[x].identity.is_none())
+        });
+
+        let index = match existing {
+            Some(index) => index,
+            None => {
+                self.players.push(Player {
+                    name: name.clone(),
+                    identity: None,
+                    team: None,
+                    actor_id,
+                });
+                self.players.len() - 1
+            }
+        };
+
+        let player = &mut self.players[index];
+        player.name.clone_from(name);
+        if let Some(identity) = identity {
+            player.identity = Some(identity.clone());
+        }
+        player.actor_id = actor_id;
+        if let Some(&team) = self.teams.get(&actor_id) {
+            player.team = Some(team);
+        }
+        self.pris.insert(actor_id, index);
+    }
+}
+
+/// The frames of the network data or nothing if the network data was not parsed
+pub(crate) fn frames(replay: &Replay) -> &[Frame] {
+    replay
+        .network_frames
+        .as_ref()
+        .map(|x| x.frames.as_slice())
+        .unwrap_or_default()
+}
+
+/// The number of the team from its archetype (eg: `Archetypes.Teams.Team1`)
+fn team_number(object: &str) -> Option<u8> {
+    if object.ends_with("Team0") {
+        Some(0)
+    } else if object.ends_with("Team1") {
+        Some(1)
+    } else {
+        None
+    }
+}
+
+/// The numeric value of an integer or boolean attribute
+pub(crate) fn as_i32(attribute: &Attribute) -> Option<i32> {
+    match *attribute {
+        Attribute::Int(x) => Some(x),
+        Attribute::Byte(x) => Some(i32::from(x)),
+        Attribute::Boolean(x) => Some(i32::from(x)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_team_number() {
+        assert_eq!(team_number("Archetypes.Teams.Team0"), Some(0));
+        assert_eq!(team_number("Archetypes.Teams.Team1"), Some(1));
+        assert_eq!(team_number("Archetypes.Teams.TeamWhite0"), None);
+        assert_eq!(team_number(""), None);
+    }
+}
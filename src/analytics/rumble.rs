@@ -0,0 +1,370 @@
+//! # Rumble
+//!
+//! In rumble, players are periodically granted an item (a power-up) that they can activate. Each
+//! item is a `TAGame.SpecialPickup_TA` car component actor. The items a car holds are replicated
+//! by the `TAGame.RumblePickups_TA:PickupInfo` of the car's `TAGame.Car_TA:RumblePickups` actor,
+//! while older replays replicate the held item with `TAGame.Car_TA:AttachedPickup`. An item is
+//! activated through `TAGame.CarComponent_TA:ReplicatedActive`. Targeted items replicate what they
+//! were used on and some items replicate whether they hit. This module reports the lifetime of
+//! every item, including the items that were granted but never used.
+//!
+//! Each player has a `TAGame.PickupTimer_TA` that counts down the seconds until their next item
+//! in `TAGame.PickupTimer_TA:TimeTillItem`.
+
+use super::{frames, Change, Player, Tracker};
+use crate::data::PARENT_CLASSES;
+use crate::models::Replay;
+use crate::network::attributes::Attribute;
+use crate::network::{is_instance_of, normalize_object, ActorId};
+use fnv::FnvHashMap;
+
+/// A summary of the rumble items used in a replay
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RumbleReport {
+    pub players: Vec<Player>,
+
+    /// Items ordered by when they were granted
+    pub items: Vec<RumbleItem>,
+
+    /// When pickup timers ran out, ordered by frame
+    pub timer_expiries: Vec<RumbleTimerExpiry>,
+}
+
+impl RumbleReport {
+    /// The items granted to the player at the given index of `players`
+    pub fn player_items(&self, player: usize) -> impl Iterator<Item = &RumbleItem> + '_ {
+        self.items.iter().filter(move |x| x.player == player)
+    }
+}
+
+/// What a targeted item was used on
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub enum RumbleTarget {
+    Ball,
+
+    /// Index into the report's players
+    Player(usize),
+
+    /// The object name of any other actor
+    Other(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RumbleItem {
+    /// Index into the report's players
+    pub player: usize,
+
+    /// The name of the item (eg: `BallFreeze`, `BallVelcro`, `GravityWell`)
+    pub item: String,
+    pub granted_frame: usize,
+    pub granted_time: f32,
+    pub activated_frame: Option<usize>,
+    pub activated_time: Option<f32>,
+
+    /// When the car stopped holding the item (it was used up, or lost to a demolition or the end
+    /// of a round), if it was removed before the replay ended
+    pub removed_frame: Option<usize>,
+    pub removed_time: Option<f32>,
+    pub target: Option<RumbleTarget>,
+
+    /// The item connected with its target: the velcro stuck to the ball, the ball was frozen, or
+    /// the ball was welded to the car
+    pub hit: bool,
+
+    /// The ball broke free of the velcro
+    pub broken: bool,
+}
+
+/// One or more pickup timers running out, after which their players are granted an item once
+/// they have none. The timers are not replicated with the car or player they belong to, so only
+/// the number of timers is known.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RumbleTimerExpiry {
+    pub frame: usize,
+    pub time: f32,
+    pub timers: usize,
+}
+
+/// The items granted so far and which cars hold them
+#[derive(Debug, Default)]
+struct Items {
+    items: Vec<RumbleItem>,
+
+    /// Item actors that are alive, which keep replicating their target and hits after they are
+    /// removed from the car
+    live: FnvHashMap<ActorId, usize>,
+
+    /// The item actors held by each car
+    held: FnvHashMap<ActorId, Vec<ActorId>>,
+}
+
+impl Items {
+    /// Records that the car holds the item actor. The same item may be replicated as held more
+    /// than once (eg: by both the pickup info and the item's vehicle).
+    fn grant(&mut self, tracker: &Tracker<'_>, car: ActorId, actor_id: ActorId, at: (usize, f32)) {
+        if let Some(&item) = self.live.get(&actor_id) {
+            let held = self.held.entry(car).or_default();
+            if !held.contains(&actor_id) {
+                held.push(actor_id);
+                self.items[item].removed_frame = None;
+                self.items[item].removed_time = None;
+            }
+            return;
+        }
+
+        let Some(object) = tracker.actor_object(actor_id) else {
+            return;
+        };
+
+        if !is_instance_of(object, &["TAGame.SpecialPickup_TA"]) {
+            return;
+        }
+
+        let Some(player) = tracker.car_player(car) else {
+            return;
+        };
+
+        self.live.insert(actor_id, self.items.len());
+        self.held.entry(car).or_default().push(actor_id);
+        self.items.push(RumbleItem {
+            player,
+            item: item_name(object),
+            granted_frame: at.0,
+            granted_time: at.1,
+            activated_frame: None,
+            activated_time: None,
+            removed_frame: None,
+            removed_time: None,
+            target: None,
+            hit: false,
+            broken: false,
+        });
+    }
+
+    /// Removes the items held by the car other than those kept
+    fn release(&mut self, car: ActorId, keep: &[ActorId], at: (usize, f32)) {
+        let Some(held) = self.held.get_mut(&car) else {
+            return;
+        };
+
+        for actor_id in held.iter().filter(|x| !keep.contains(x)) {
+            if let Some(&item) = self.live.get(actor_id) {
+                self.items[item].removed_frame.get_or_insert(at.0);
+                self.items[item].removed_time.get_or_insert(at.1);
+            }
+        }
+
+        held.retain(|x| keep.contains(x));
+    }
+
+    /// The item of the live item actor
+    fn get_mut(&mut self, actor_id: ActorId) -> Option<&mut RumbleItem> {
+        let item = *self.live.get(&actor_id)?;
+        self.items.get_mut(item)
+    }
+
+    fn delete(&mut self, actor_id: ActorId, at: (usize, f32)) {
+        // A car's items are lost when it is destroyed
+        self.release(actor_id, &[], at);
+        self.held.remove(&actor_id);
+
+        if let Some(item) = self.live.remove(&actor_id) {
+            self.items[item].removed_frame.get_or_insert(at.0);
+            self.items[item].removed_time.get_or_insert(at.1);
+            for held in self.held.values_mut() {
+                held.retain(|&x| x != actor_id);
+            }
+        }
+    }
+}
+
+/// Summarizes the rumble items of the replay. Nothing is reported for replays of other game
+/// modes other than the players, nor when the network data was not parsed.
+pub fn analyze(replay: &Replay) -> RumbleReport {
+    let mut tracker = Tracker::new(replay);
+    let mut items = Items::default();
+    let mut timer_expiries: Vec<RumbleTimerExpiry> = Vec::new();
+
+    // The car of each `TAGame.RumblePickups_TA` actor and the last seconds of each pickup timer
+    let mut pickups: FnvHashMap<ActorId, ActorId> = FnvHashMap::default();
+    let mut timers: FnvHashMap<ActorId, i32> = FnvHashMap::default();
+
+    for (index, frame) in frames(replay).iter().enumerate() {
+        let at = (index, frame.time);
+        for change in tracker.apply(frame) {
+            let update = match change {
+                Change::Updated {
+                    update,
+                    changed: true,
+                } => update,
+                Change::Deleted { actor_id } => {
+                    items.delete(actor_id, at);
+                    pickups.remove(&actor_id);
+                    timers.remove(&actor_id);
+                    continue;
+                }
+                _ => continue,
+            };
+
+            let actor_id = update.actor_id;
+            match (tracker.object(update.object_id), &update.attribute) {
+                ("TAGame.Car_TA:RumblePickups", Attribute::ActiveActor(pickup))
+                    if pickup.active =>
+                {
+                    pickups.insert(pickup.actor, actor_id);
+                }
+                ("TAGame.RumblePickups_TA:PickupInfo", Attribute::PickupInfo(info)) => {
+                    let Some(&car) = pickups.get(&actor_id) else {
+                        continue;
+                    };
+
+                    // Preview items are shown before they are granted
+                    if info.items_are_preview {
+                        continue;
+                    }
+
+                    let available: Vec<_> = info
+                        .available_pickups
+                        .iter()
+                        .filter(|x| x.active)
+                        .map(|x| x.actor)
+                        .collect();
+
+                    items.release(car, &available, at);
+                    for &item in &available {
+                        items.grant(&tracker, car, item, at);
+                    }
+                }
+                ("TAGame.RumblePickups_TA:AttachedPickup", Attribute::ActiveActor(item)) => {
+                    if let Some(&car) = pickups.get(&actor_id).filter(|_| item.active) {
+                        items.grant(&tracker, car, item.actor, at);
+                    }
+                }
+                ("TAGame.Car_TA:AttachedPickup", Attribute::ActiveActor(item)) => {
+                    if item.active {
+                        items.release(actor_id, &[item.actor], at);
+                        items.grant(&tracker, actor_id, item.actor, at);
+                    } else {
+                        items.release(actor_id, &[], at);
+                    }
+                }
+                ("TAGame.CarComponent_TA:Vehicle", Attribute::ActiveActor(car)) if car.active => {
+                    // Items are attached to the car they are granted to, which is the only record
+                    // of the grant in replays that replicate neither the pickup info nor the
+                    // attached pickup
+                    items.grant(&tracker, car.actor, actor_id, at);
+                }
+                ("TAGame.PickupTimer_TA:TimeTillItem", &Attribute::Int(seconds)) => {
+                    let previous = timers.insert(actor_id, seconds);
+                    if seconds > 0 || previous.is_some_and(|x| x <= 0) {
+                        continue;
+                    }
+
+                    match timer_expiries.last_mut() {
+                        Some(expiry) if expiry.frame == index => expiry.timers += 1,
+                        _ => timer_expiries.push(RumbleTimerExpiry {
+                            frame: index,
+                            time: frame.time,
+                            timers: 1,
+                        }),
+                    }
+                }
+                ("TAGame.CarComponent_TA:ReplicatedActive", Attribute::Byte(active)) => {
+                    let Some(item) = items.get_mut(actor_id) else {
+                        continue;
+                    };
+
+                    // Odd values denote an active component
+                    if active % 2 == 1 && item.activated_frame.is_none() {
+                        item.activated_frame = Some(index);
+                        item.activated_time = Some(frame.time);
+                    }
+                }
+                ("TAGame.SpecialPickup_Targeted_TA:Targeted", Attribute::ActiveActor(target)) => {
+                    let target = match target.active {
+                        true => Some(rumble_target(&tracker, target.actor)),
+                        false => None,
+                    };
+
+                    if let Some(item) = items.get_mut(actor_id) {
+                        item.target = target;
+                    }
+                }
+                ("TAGame.SpecialPickup_BallVelcro_TA:bHit", &Attribute::Boolean(hit))
+                | ("TAGame.SpecialPickup_Rugby_TA:bBallWelded", &Attribute::Boolean(hit)) => {
+                    if let Some(item) = items.get_mut(actor_id) {
+                        item.hit |= hit;
+                    }
+                }
+                ("TAGame.SpecialPickup_BallVelcro_TA:bBroken", &Attribute::Boolean(broken)) => {
+                    if let Some(item) = items.get_mut(actor_id) {
+                        item.broken |= broken;
+                    }
+                }
+                ("TAGame.SpecialPickup_BallFreeze_TA:RepOrigSpeed", _)
+                | ("TAGame.SpecialPickup_Football_TA:WeldedBall", _) => {
+                    if let Some(item) = items.get_mut(actor_id) {
+                        item.hit = true;
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    RumbleReport {
+        players: tracker.into_players(),
+        items: items.items,
+        timer_expiries,
+    }
+}
+
+fn rumble_target(tracker: &Tracker<'_>, actor_id: ActorId) -> RumbleTarget {
+    if let Some(player) = tracker.car_player(actor_id) {
+        return RumbleTarget::Player(player);
+    }
+
+    match tracker.actor_object(actor_id) {
+        Some(object) if is_instance_of(object, &["TAGame.Ball_TA"]) => RumbleTarget::Ball,
+        Some(object) => RumbleTarget::Other(String::from(object)),
+        None => RumbleTarget::Other(String::new()),
+    }
+}
+
+/// The item name from the archetype (`Archetypes.SpecialPickups.SpecialPickup_BallFreeze` is
+/// `BallFreeze`) or else from the class (`TAGame.SpecialPickup_BallFreeze_TA` is `BallFreeze`)
+fn item_name(object: &str) -> String {
+    let name = |x: &str| {
+        let last = x.rsplit('.').next().unwrap_or(x);
+        last.strip_prefix("SpecialPickup_")
+            .map(|x| String::from(x.strip_suffix("_TA").unwrap_or(x)))
+    };
+
+    name(object)
+        .or_else(|| {
+            PARENT_CLASSES
+                .get(normalize_object(object))
+                .and_then(|x| name(x))
+        })
+        .unwrap_or_else(|| String::from(object))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_item_name() {
+        assert_eq!(
+            item_name("Archetypes.SpecialPickups.SpecialPickup_BallFreeze"),
+            "BallFreeze"
+        );
+        assert_eq!(
+            item_name(
+                "Archetypes.Mutators.SubRules.ItemsMode_RPS:DispenserArchetype.ItemPool.Obj_2"
+            ),
+            "BallFreeze"
+        );
+        assert_eq!(item_name("TAGame.SpecialPickup_Tornado_TA"), "Tornado");
+    }
+}
@@ -0,0 +1,56 @@
+//! Parse many replays in parallel with rayon.
+
+use crate::context::ParserContext;
+use crate::errors::{FileError, ParseError};
+use crate::models::Replay;
+use crate::parser::ParserBuilder;
+use rayon::prelude::*;
+use std::fs;
+use std::path::Path;
+
+/// Parses the replays concurrently with the configuration of the given builder (the builder's
+/// own data is ignored). The results are in the same order as the inputs. Each worker reuses a
+/// `ParserContext`, so replays from the same patch share their network setup.
+///
+/// ```
+/// let rumble = std::fs::read("assets/replays/good/rumble.replay").unwrap();
+/// let bad = vec![0u8; 10];
+/// let config = boxcars::ParserBuilder::new(&[]).never_parse_network_data();
+///
+/// let results = boxcars::parse_many(&[rumble, bad], &config);
+/// assert!(results[0].is_ok());
+/// assert!(results[1].is_err());
+/// ```
+pub fn parse_many<T>(inputs: &[T], builder: &ParserBuilder<'_>) -> Vec<Result<Replay, ParseError>>
+where
+    T: AsRef<[u8]> + Sync,
+{
+    inputs
+        .par_iter()
+        .map_init(ParserContext::new, |context, data| {
+            context.parse(builder.with_data(data.as_ref()))
+        })
+        .collect()
+}
+
+/// Reads and parses the replay files concurrently with the configuration of the given builder
+/// (the builder's own data is ignored). The results are in the same order as the paths. Like
+/// `parse_many`, each worker reuses a `ParserContext`.
+pub fn parse_many_files<P>(
+    paths: &[P],
+    builder: &ParserBuilder<'_>,
+) -> Vec<Result<Replay, FileError>>
+where
+    P: AsRef<Path> + Sync,
+{
+    paths
+        .par_iter()
+        .map_init(ParserContext::new, |context, path| {
+            let path = path.as_ref();
+            let data = fs::read(path).map_err(|e| FileError::Io(path.to_path_buf(), e))?;
+            context
+                .parse(builder.with_data(&data))
+                .map_err(|e| FileError::Parse(path.to_path_buf(), e))
+        })
+        .collect()
+}
@@ -0,0 +1,314 @@
+//! Command line interface to inspect Rocket League replays with boxcars.
+//!
+//! ```bash
+//! boxcars json --header-only my.replay
+//! boxcars frames --from 100 --to 200 my.replay
+//! cat my.replay | boxcars verify -
+//! boxcars scan replays/
+//! ```
+use boxcars::corpus::Scanner;
+use boxcars::{crc::calc_crc, HeaderProp, ParserBuilder, Replay};
+use std::collections::HashMap;
+use std::error;
+use std::fs;
+use std::io::{self, BufWriter, Read, Write};
+use std::process;
+
+const USAGE: &str = "\
+Usage: boxcars <COMMAND> [OPTIONS] <REPLAY>
+
+Reads the replay from stdin when <REPLAY> is '-'. Prints this message with -h or --help.
+
+Commands:
+  json [--header-only]      Output the replay as JSON
+  header                    Print a table of the header properties
+  frames [--from N] [--to N]
+                            Output the network frames in the range [from, to) as JSON
+  objects                   Print the objects, names, and class indices
+  verify                    Check the header and body sections for corruption
+  stats                     Print frame, actor, and attribute counts
+  scan [--json] <DIR>       Parse the replays under a directory and classify failures
+";
+
+type Result<T> = std::result::Result<T, Box<dyn error::Error>>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Command {
+    Replay(ReplayCommand),
+    Scan { json: bool },
+}
+
+/// The commands that read a single replay
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ReplayCommand {
+    Json { header_only: bool },
+    Header,
+    Frames { from: usize, to: Option<usize> },
+    Objects,
+    Verify,
+    Stats,
+}
+
+#[derive(Debug)]
+struct Args {
+    command: Command,
+    path: String,
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Args> {
+    let command = args.next().ok_or("expected a command")?;
+    let mut command = match command.as_str() {
+        "json" => Command::Replay(ReplayCommand::Json { header_only: false }),
+        "header" => Command::Replay(ReplayCommand::Header),
+        "frames" => Command::Replay(ReplayCommand::Frames { from: 0, to: None }),
+        "objects" => Command::Replay(ReplayCommand::Objects),
+        "verify" => Command::Replay(ReplayCommand::Verify),
+        "stats" => Command::Replay(ReplayCommand::Stats),
+        "scan" => Command::Scan { json: false },
+        x => return Err(format!("unrecognized command: {}", x).into()),
+    };
+
+    let mut path = None;
+    while let Some(arg) = args.next() {
+        match (&mut command, arg.as_str()) {
+            (Command::Replay(ReplayCommand::Json { header_only }), "--header-only") => {
+                *header_only = true
+            }
+            (Command::Scan { json }, "--json") => *json = true,
+            (Command::Replay(ReplayCommand::Frames { from, .. }), "--from") => {
+                *from = args.next().ok_or("expected a value for --from")?.parse()?
+            }
+            (Command::Replay(ReplayCommand::Frames { to, .. }), "--to") => {
+                *to = Some(args.next().ok_or("expected a value for --to")?.parse()?)
+            }
+            (_, x) if x.starts_with("--") => {
+                return Err(format!("unrecognized option: {}", x).into())
+            }
+            (_, _) if path.is_none() => path = Some(arg),
+            (_, x) => return Err(format!("unexpected argument: {}", x).into()),
+        }
+    }
+
+    let path = path.ok_or("expected a replay file")?;
+    Ok(Args { command, path })
+}
+
+fn read_replay(path: &str) -> Result<Vec<u8>> {
+    if path == "-" {
+        let mut data = Vec::new();
+        io::stdin().read_to_end(&mut data)?;
+        Ok(data)
+    } else {
+        fs::read(path).map_err(|e| format!("unable to read {}: {}", path, e).into())
+    }
+}
+
+fn parse_replay(data: &[u8], network: bool) -> Result<Replay> {
+    let builder = ParserBuilder::new(data).on_error_check_crc();
+    let builder = if network {
+        builder.must_parse_network_data()
+    } else {
+        builder.never_parse_network_data()
+    };
+
+    Ok(builder.parse()?)
+}
+
+fn print_properties(
+    out: &mut impl Write,
+    props: &[(String, HeaderProp)],
+    prefix: &str,
+) -> io::Result<()> {
+    for (key, prop) in props {
+        let path = if prefix.is_empty() {
+            key.clone()
+        } else {
+            format!("{}.{}", prefix, key)
+        };
+
+        match prop {
+            HeaderProp::Array(elems) => {
+                for (i, elem) in elems.iter().enumerate() {
+                    print_properties(out, elem, &format!("{}[{}]", path, i))?;
+                }
+            }
+            HeaderProp::Struct { fields, .. } => print_properties(out, fields, &path)?,
+            HeaderProp::Bool(x) => writeln!(out, "{:<40} {}", path, x)?,
+            HeaderProp::Byte { kind, value } => match value {
+                Some(value) => writeln!(out, "{:<40} {}", path, value)?,
+                None => writeln!(out, "{:<40} {}", path, kind)?,
+            },
+            HeaderProp::Float(x) => writeln!(out, "{:<40} {}", path, x)?,
+            HeaderProp::Int(x) => writeln!(out, "{:<40} {}", path, x)?,
+            HeaderProp::QWord(x) => writeln!(out, "{:<40} {}", path, x)?,
+            HeaderProp::Name(x) | HeaderProp::Str(x) => writeln!(out, "{:<40} {}", path, x)?,
+            HeaderProp::Unknown { kind, raw } => {
+                writeln!(out, "{:<40} <{}: {} bytes>", path, kind, raw.len())?
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Computes the crc of a section that starts at the given offset, returning the section's end,
+/// the expected crc, and the actual crc
+fn section_crc(data: &[u8], offset: usize) -> Result<(usize, u32, u32)> {
+    const SIZE_ERR: &str = "section size exceeds replay size";
+    let start = offset
+        .checked_add(8)
+        .ok_or("not enough data for section size and crc")?;
+    let prefix = data
+        .get(offset..start)
+        .ok_or("not enough data for section size and crc")?;
+    let size = u32::from_le_bytes([prefix[0], prefix[1], prefix[2], prefix[3]]) as usize;
+    let expected = u32::from_le_bytes([prefix[4], prefix[5], prefix[6], prefix[7]]);
+    let end = start.checked_add(size).ok_or(SIZE_ERR)?;
+    let section = data.get(start..end).ok_or(SIZE_ERR)?;
+    Ok((end, expected, calc_crc(section)))
+}
+
+fn verify(out: &mut impl Write, data: &[u8]) -> Result<bool> {
+    let mut offset = 0;
+    let mut valid = true;
+    for section in ["header", "body"] {
+        let (end, expected, actual) = section_crc(data, offset)?;
+        let status = if expected == actual { "ok" } else { "CORRUPT" };
+        writeln!(
+            out,
+            "{:<8} expected: {:#010x} actual: {:#010x} {}",
+            section, expected, actual, status
+        )?;
+        valid &= expected == actual;
+        offset = end;
+    }
+
+    Ok(valid)
+}
+
+fn stats(out: &mut impl Write, replay: &Replay) -> io::Result<()> {
+    let frames = replay
+        .network_frames
+        .as_ref()
+        .map(|x| x.frames.as_slice())
+        .unwrap_or_default();
+
+    let new_actors: usize = frames.iter().map(|x| x.new_actors.len()).sum();
+    let deleted_actors: usize = frames.iter().map(|x| x.deleted_actors.len()).sum();
+    let updates: usize = frames.iter().map(|x| x.updated_actors.len()).sum();
+
+    let mut histogram: HashMap<usize, usize> = HashMap::new();
+    for update in frames.iter().flat_map(|x| x.updated_actors.iter()) {
+        *histogram.entry(usize::from(update.object_id)).or_default() += 1;
+    }
+
+    let mut histogram = histogram.into_iter().collect::<Vec<_>>();
+    histogram
+        .sort_by(|(a_obj, a_count), (b_obj, b_count)| b_count.cmp(a_count).then(a_obj.cmp(b_obj)));
+
+    writeln!(out, "frames:         {}", frames.len())?;
+    writeln!(out, "new actors:     {}", new_actors)?;
+    writeln!(out, "deleted actors: {}", deleted_actors)?;
+    writeln!(out, "updates:        {}", updates)?;
+    writeln!(out)?;
+    writeln!(out, "attributes:")?;
+    for (object_id, count) in histogram {
+        let name = replay
+            .objects
+            .get(object_id)
+            .map(|x| x.as_str())
+            .unwrap_or("<unknown>");
+        writeln!(out, "{:>10} {}", count, name)?;
+    }
+
+    Ok(())
+}
+
+fn run(args: Args) -> Result<bool> {
+    let stdout = io::stdout();
+    let mut out = BufWriter::new(stdout.lock());
+    let valid = match args.command {
+        Command::Replay(command) => run_replay(&mut out, command, &args.path)?,
+        Command::Scan { json } => {
+            let report = Scanner::new().scan_dir(&args.path)?;
+            if json {
+                serde_json::to_writer(&mut out, &report)?;
+            } else {
+                write!(out, "{}", report)?;
+            }
+            report.failures.is_empty()
+        }
+    };
+
+    out.flush()?;
+    Ok(valid)
+}
+
+fn run_replay(out: &mut impl Write, command: ReplayCommand, path: &str) -> Result<bool> {
+    let data = read_replay(path)?;
+    match command {
+        ReplayCommand::Json { header_only } => {
+            let replay = parse_replay(&data, !header_only)?;
+            serde_json::to_writer(&mut *out, &replay)?;
+        }
+        ReplayCommand::Header => {
+            let replay = parse_replay(&data, false)?;
+            print_properties(out, &replay.properties, "")?;
+        }
+        ReplayCommand::Frames { from, to } => {
+            let replay = parse_replay(&data, true)?;
+            let frames = replay.network_frames.map(|x| x.frames).unwrap_or_default();
+            let to = to.unwrap_or(frames.len()).min(frames.len());
+            let frames = frames.get(from.min(to)..to).unwrap_or_default();
+            serde_json::to_writer(&mut *out, frames)?;
+        }
+        ReplayCommand::Objects => {
+            let replay = parse_replay(&data, false)?;
+            writeln!(out, "objects:")?;
+            for (i, object) in replay.objects.iter().enumerate() {
+                writeln!(out, "{:>6} {}", i, object)?;
+            }
+
+            writeln!(out, "\nnames:")?;
+            for (i, name) in replay.names.iter().enumerate() {
+                writeln!(out, "{:>6} {}", i, name)?;
+            }
+
+            writeln!(out, "\nclass indices:")?;
+            for class in replay.class_indices.iter() {
+                writeln!(out, "{:>6} {}", class.index, class.class)?;
+            }
+        }
+        ReplayCommand::Verify => return verify(out, &data),
+        ReplayCommand::Stats => {
+            let replay = parse_replay(&data, true)?;
+            stats(out, &replay)?;
+        }
+    }
+
+    Ok(true)
+}
+
+fn main() {
+    if std::env::args().skip(1).any(|x| x == "-h" || x == "--help") {
+        print!("{}", USAGE);
+        return;
+    }
+
+    let args = match parse_args(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, USAGE);
+            process::exit(2);
+        }
+    };
+
+    match run(args) {
+        Ok(true) => {}
+        Ok(false) => process::exit(1),
+        Err(e) => {
+            eprintln!("An error occurred: {}", e);
+            process::exit(1);
+        }
+    }
+}
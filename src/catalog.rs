@@ -0,0 +1,217 @@
+//! # Catalog
+//!
+//! Replays reference the items of a loadout by product id (eg: `Loadout::body` and
+//! `Loadout::wheels`) and paints by id (eg: `ProductValue::NewPaint`). The catalog is an embedded
+//! table that resolves these ids to their names and slots, and car bodies to their hitbox, so
+//! that a loadout can be displayed as "Octane, Hitbox: Octane, Titanium White" instead of
+//! numbers. The catalog is only available with the `catalog` feature.
+//!
+//! Product ids are unique across slots, so [`product`] resolves the item of any slot: bodies,
+//! decals, wheels, boosts (`Loadout::rocket_trail`), antennas, toppers, goal explosions and
+//! banners. Certifications are resolved by [`certification`]. Replays do not replicate which
+//! certification an item has, so the certification id comes from outside of the replay.
+//!
+//! The tables are a snapshot and are not exhaustive: only products whose id has been verified
+//! are listed, and any other product resolves to `None`. The [`VERSION`] is incremented whenever
+//! entries are added or corrected.
+//!
+//! ```
+//! use boxcars::analytics::loadout::LoadoutSlot;
+//! use boxcars::catalog::{self, Hitbox};
+//!
+//! let octane = catalog::body(23).unwrap();
+//! assert_eq!(octane.name, "Octane");
+//! assert_eq!(octane.hitbox, Some(Hitbox::Octane));
+//! assert_eq!(catalog::paint(12), Some("Titanium White"));
+//!
+//! let wheels = catalog::product(376).unwrap();
+//! assert_eq!((wheels.name, wheels.slot), ("OEM", LoadoutSlot::Wheels));
+//! ```
+
+use crate::analytics::loadout::LoadoutSlot;
+use crate::hitbox::BODIES;
+use crate::network::attributes::{Loadout, ProductValue};
+
+pub use crate::hitbox::Hitbox;
+
+/// The revision of the embedded tables
+pub const VERSION: u32 = 2;
+
+/// A car body
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Body {
+    /// The product id of the body (ie: `Loadout::body`)
+    pub id: u32,
+    pub name: &'static str,
+
+    /// The hitbox preset of the body, if known
+    pub hitbox: Option<Hitbox>,
+}
+
+/// An item that can be equipped in a loadout
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Product {
+    pub id: u32,
+    pub name: &'static str,
+
+    /// The slot the item is equipped in
+    pub slot: LoadoutSlot,
+}
+
+/// The car body with the given product id
+pub fn body(id: u32) -> Option<Body> {
+    BODIES
+        .get(&id)
+        .map(|&(name, hitbox)| Body { id, name, hitbox })
+}
+
+/// The item of any slot with the given product id
+pub fn product(id: u32) -> Option<Product> {
+    let (name, slot) = match BODIES.get(&id) {
+        Some(&(name, _)) => (name, LoadoutSlot::Body),
+        None => PRODUCTS.get(&id).copied()?,
+    };
+
+    Some(Product { id, name, slot })
+}
+
+/// The items of the loadout that are in the catalog, in the order of their slots. Empty slots
+/// (ie: a product id of 0) and products that are not in the catalog are skipped.
+pub fn loadout(loadout: &Loadout) -> Vec<Product> {
+    let ids = [
+        loadout.body,
+        loadout.decal,
+        loadout.wheels,
+        loadout.rocket_trail,
+        loadout.antenna,
+        loadout.topper,
+        loadout.goal_explosion.unwrap_or(0),
+        loadout.banner.unwrap_or(0),
+    ];
+
+    ids.into_iter()
+        .filter(|&id| id != 0)
+        .filter_map(product)
+        .collect()
+}
+
+/// The name of the paint with the given id (eg: 12 is `Titanium White`)
+pub fn paint(id: u32) -> Option<&'static str> {
+    PAINTS.get(&id).copied()
+}
+
+/// The name of the certification with the given id (eg: 8 is `Scorer`)
+pub fn certification(id: u32) -> Option<&'static str> {
+    CERTIFICATIONS.get(&id).copied()
+}
+
+/// The name of a product attribute's value for attributes that reference a table, which is the
+/// paint of a painted item
+pub fn product_value(value: &ProductValue) -> Option<&'static str> {
+    match *value {
+        ProductValue::OldPaint(id) | ProductValue::NewPaint(id) => paint(id),
+        _ => None,
+    }
+}
+
+// The items of the slots other than the body, which are listed with the hitbox of the body in
+// `BODIES`.
+//
+// Please keep entries sorted by key (ascending)
+static PRODUCTS: phf::Map<u32, (&'static str, LoadoutSlot)> = phf::phf_map! {
+    376u32 => ("OEM", LoadoutSlot::Wheels),
+    1903u32 => ("Classic", LoadoutSlot::GoalExplosion),
+};
+
+// Please keep entries sorted by key (ascending)
+static PAINTS: phf::Map<u32, &'static str> = phf::phf_map! {
+    1u32 => "Crimson",
+    2u32 => "Lime",
+    3u32 => "Black",
+    4u32 => "Sky Blue",
+    5u32 => "Cobalt",
+    6u32 => "Burnt Sienna",
+    7u32 => "Forest Green",
+    8u32 => "Purple",
+    9u32 => "Pink",
+    10u32 => "Orange",
+    11u32 => "Grey",
+    12u32 => "Titanium White",
+    13u32 => "Saffron",
+    14u32 => "Gold",
+    15u32 => "Rose Gold",
+    16u32 => "White Gold",
+    17u32 => "Onyx",
+    18u32 => "Platinum",
+};
+
+// Please keep entries sorted by key (ascending)
+static CERTIFICATIONS: phf::Map<u32, &'static str> = phf::phf_map! {
+    1u32 => "Aviator",
+    2u32 => "Playmaker",
+    3u32 => "Show-Off",
+    4u32 => "Acrobat",
+    5u32 => "Tactician",
+    6u32 => "Sweeper",
+    7u32 => "Guardian",
+    8u32 => "Scorer",
+    9u32 => "Juggler",
+    10u32 => "Sniper",
+    11u32 => "Paragon",
+    12u32 => "Goalkeeper",
+    13u32 => "Striker",
+    14u32 => "Turtle",
+    15u32 => "Victor",
+};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_body() {
+        let dominus = body(403).unwrap();
+        assert_eq!(dominus.name, "Dominus");
+        assert_eq!(dominus.hitbox, Some(Hitbox::Dominus));
+        assert_eq!(body(27).and_then(|x| x.hitbox), None);
+        assert_eq!(body(0), None);
+    }
+
+    #[test]
+    fn test_product_value() {
+        assert_eq!(product_value(&ProductValue::NewPaint(5)), Some("Cobalt"));
+        assert_eq!(product_value(&ProductValue::OldPaint(1)), Some("Crimson"));
+        assert_eq!(product_value(&ProductValue::NewPaint(0)), None);
+        assert_eq!(product_value(&ProductValue::Absent), None);
+        assert_eq!(certification(8), Some("Scorer"));
+        assert_eq!(certification(0), None);
+    }
+
+    #[test]
+    fn test_product() {
+        let octane = product(23).unwrap();
+        assert_eq!((octane.name, octane.slot), ("Octane", LoadoutSlot::Body));
+        let explosion = product(1903).unwrap();
+        assert_eq!(explosion.slot, LoadoutSlot::GoalExplosion);
+        assert_eq!(product(0), None);
+
+        let items = loadout(&Loadout {
+            version: 22,
+            body: 23,
+            decal: 0,
+            wheels: 376,
+            rocket_trail: 1,
+            antenna: 0,
+            topper: 0,
+            unknown1: 0,
+            unknown2: None,
+            engine_audio: None,
+            trail: None,
+            goal_explosion: Some(1903),
+            banner: None,
+            product_id: None,
+        });
+        let names: Vec<_> = items.iter().map(|x| x.name).collect();
+        assert_eq!(names, ["Octane", "OEM", "Classic"]);
+    }
+}
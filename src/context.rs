@@ -0,0 +1,272 @@
+use crate::errors::{NetworkError, ParseError};
+use crate::models::{ClassNetCache, Replay, ReplayRef};
+use crate::network::{FrameBuffers, NetworkSetup, VersionTriplet};
+use crate::parser::{ParserBuilder, ReplayBody};
+use fnv::{FnvHashMap, FnvHasher};
+use std::borrow::Cow;
+use std::hash::{Hash, Hasher};
+
+/// Amortizes the setup of decoding network data across many replays.
+///
+/// Before the network data can be decoded, lookup tables (the object hierarchy, how new actors
+/// spawn, and every object's attributes) are derived from the replay's objects and net cache.
+/// Replays from the same patch share identical objects and net cache, so a context caches these
+/// tables and reuses them for subsequent replays that match. A cache hit is verified against the
+/// full objects and net cache, so a hash collision can't result in decoding with the wrong tables.
+///
+/// At most `ParserContext::DEFAULT_MAX_SETUPS` setups are cached by default (see
+/// `with_max_setups`). When the cache is full, the least recently used setup is dropped.
+///
+/// The context also keeps the buffers that the actors of each frame are decoded into, so they are
+/// allocated once and reused for every frame of every replay. Each decoded frame copies its
+/// actors out of the buffers into vectors of their exact size, as the frames are returned to the
+/// caller.
+///
+/// ```
+/// let mut context = boxcars::ParserContext::new();
+/// for file in ["assets/replays/good/rumble.replay", "assets/replays/good/3381.replay"] {
+///     let data = std::fs::read(file).unwrap();
+///     let builder = boxcars::ParserBuilder::new(&data).must_parse_network_data();
+///     let replay = context.parse(builder).unwrap();
+///     assert!(replay.network_frames.is_some());
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct ParserContext {
+    setups: FnvHashMap<u64, Vec<CachedSetup>>,
+    max_setups: usize,
+    buffers: FrameBuffers,
+
+    /// Incremented on every lookup to track which setups were used least recently
+    tick: u64,
+}
+
+#[derive(Debug, Clone)]
+struct CachedSetup {
+    version: VersionTriplet,
+    objects: Vec<String>,
+    net_cache: Vec<ClassNetCache>,
+    setup: NetworkSetup,
+    last_used: u64,
+}
+
+impl Default for ParserContext {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ParserContext {
+    /// The number of network setups cached by default
+    pub const DEFAULT_MAX_SETUPS: usize = 16;
+
+    pub fn new() -> Self {
+        Self::with_max_setups(Self::DEFAULT_MAX_SETUPS)
+    }
+
+    /// A context that caches at most the given number of network setups (and at least one)
+    pub fn with_max_setups(max_setups: usize) -> Self {
+        ParserContext {
+            setups: FnvHashMap::default(),
+            max_setups: max_setups.max(1),
+            buffers: FrameBuffers::default(),
+            tick: 0,
+        }
+    }
+
+    /// Parses the replay configured by the builder, reusing the network setup from previously
+    /// parsed replays when possible
+    pub fn parse(&mut self, builder: ParserBuilder<'_>) -> Result<Replay, ParseError> {
+        builder.parse_with(Some(self)).map(ReplayRef::into_owned)
+    }
+
+    /// The number of distinct network setups cached
+    pub fn len(&self) -> usize {
+        self.setups.values().map(|x| x.len()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Drops all cached network setups and the frame buffers
+    pub fn clear(&mut self) {
+        self.setups.clear();
+        self.buffers = FrameBuffers::default();
+    }
+
+    pub(crate) fn network_setup(
+        &mut self,
+        version: VersionTriplet,
+        body: &ReplayBody<'_>,
+    ) -> Result<(&NetworkSetup, &mut FrameBuffers), NetworkError> {
+        let key = setup_hash(version, &body.objects, &body.net_cache);
+        let position = self.setups.get(&key).and_then(|entries| {
+            entries.iter().position(|x| {
+                x.version == version
+                    && x.objects.iter().eq(body.objects.iter())
+                    && x.net_cache == body.net_cache
+            })
+        });
+
+        // The setup is built before anything is inserted so that a failure leaves the cache as
+        // it was
+        let (entries, ind) = match position {
+            Some(ind) => (self.setups.entry(key).or_default(), ind),
+            None => {
+                let setup = NetworkSetup::new(version, &body.objects, &body.net_cache)?;
+                if self.len() >= self.max_setups {
+                    self.evict_least_recent();
+                }
+
+                let entries = self.setups.entry(key).or_default();
+                entries.push(CachedSetup {
+                    version,
+                    objects: body.objects.iter().map(|x| x.to_string()).collect(),
+                    net_cache: body.net_cache.clone(),
+                    setup,
+                    last_used: 0,
+                });
+                let ind = entries.len() - 1;
+                (entries, ind)
+            }
+        };
+
+        self.tick += 1;
+        let entry = &mut entries[ind];
+        entry.last_used = self.tick;
+        Ok((&entry.setup, &mut self.buffers))
+    }
+
+    fn evict_least_recent(&mut self) {
+        let oldest = self
+            .setups
+            .iter()
+            .flat_map(|(key, entries)| entries.iter().enumerate().map(move |(i, x)| (x, *key, i)))
+            .min_by_key(|(x, _, _)| x.last_used)
+            .map(|(_, key, i)| (key, i));
+
+        if let Some((key, i)) = oldest {
+            if let Some(entries) = self.setups.get_mut(&key) {
+                entries.remove(i);
+                if entries.is_empty() {
+                    self.setups.remove(&key);
+                }
+            }
+        }
+    }
+}
+
+fn setup_hash(
+    version: VersionTriplet,
+    objects: &[Cow<'_, str>],
+    net_cache: &[ClassNetCache],
+) -> u64 {
+    let mut hasher = FnvHasher::default();
+    version.hash(&mut hasher);
+    objects.hash(&mut hasher);
+    net_cache.len().hash(&mut hasher);
+    for cache in net_cache {
+        cache.object_ind.hash(&mut hasher);
+        cache.parent_id.hash(&mut hasher);
+        cache.cache_id.hash(&mut hasher);
+        cache.properties.len().hash(&mut hasher);
+        for prop in &cache.properties {
+            prop.object_ind.hash(&mut hasher);
+            prop.stream_id.hash(&mut hasher);
+        }
+    }
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn context_reuses_setup() {
+        let rumble = include_bytes!("../assets/replays/good/rumble.replay");
+        let other = include_bytes!("../assets/replays/good/3381.replay");
+        let mut context = ParserContext::new();
+        assert!(context.is_empty());
+
+        for data in [&rumble[..], &other[..], &rumble[..]] {
+            let builder = ParserBuilder::new(data).must_parse_network_data();
+            let expected = builder.clone().parse().unwrap();
+            let actual = context.parse(builder).unwrap();
+            assert_eq!(actual, expected);
+        }
+
+        assert_eq!(context.len(), 2);
+        context.clear();
+        assert!(context.is_empty());
+    }
+
+    #[test]
+    fn context_reuses_frame_buffers() {
+        let rumble = include_bytes!("../assets/replays/good/rumble.replay");
+        let mut context = ParserContext::new();
+        let mut allocation = None;
+        for builder in [
+            ParserBuilder::new(&rumble[..]).must_parse_network_data(),
+            ParserBuilder::new(&rumble[..])
+                .must_parse_network_data()
+                .columnar_frames(),
+            ParserBuilder::new(&rumble[..]).must_parse_network_data(),
+        ] {
+            let expected = builder.clone().parse().unwrap();
+            let actual = context.parse(builder).unwrap();
+            assert_eq!(actual, expected);
+
+            // Decoding the same replay again needs no more room, so the buffer is not reallocated
+            let updated = &context.buffers.updated_actors;
+            assert!(updated.capacity() > 0);
+            let current = (updated.as_ptr(), updated.capacity());
+            assert_eq!(*allocation.get_or_insert(current), current);
+        }
+    }
+
+    #[test]
+    fn context_evicts_least_recent_setup() {
+        let rumble = include_bytes!("../assets/replays/good/rumble.replay");
+        let other = include_bytes!("../assets/replays/good/3381.replay");
+        let mut context = ParserContext::with_max_setups(1);
+        for data in [&rumble[..], &other[..], &rumble[..]] {
+            let builder = ParserBuilder::new(data).must_parse_network_data();
+            context.parse(builder).unwrap();
+            assert_eq!(context.len(), 1);
+        }
+    }
+
+    #[test]
+    fn context_unchanged_on_setup_error() {
+        // The property refers to an object that doesn't exist, which fails the setup
+        let body = ReplayBody {
+            levels: Vec::new(),
+            keyframes: Vec::new(),
+            debug_info: Vec::new(),
+            tick_marks: Vec::new(),
+            packages: Vec::new(),
+            objects: vec![Cow::Borrowed("Core.Object")],
+            names: Vec::new(),
+            class_indices: Vec::new(),
+            net_cache: vec![ClassNetCache {
+                object_ind: 0,
+                parent_id: 0,
+                cache_id: 1,
+                properties: vec![crate::models::CacheProp {
+                    object_ind: 5,
+                    stream_id: 1,
+                }],
+            }],
+            network_data: &[],
+        };
+
+        let mut context = ParserContext::new();
+        let version = VersionTriplet(868, 0, 0);
+        assert!(context.network_setup(version, &body).is_err());
+        assert!(context.is_empty());
+        assert_eq!(context.len(), 0);
+        assert!(context.setups.is_empty());
+    }
+}
@@ -1,5 +1,7 @@
-use crate::errors::ParseError;
+use crate::errors::{LimitError, ParseError};
 use crate::parsing_utils::{decode_str, decode_utf16, decode_windows1252};
+use crate::text::{RlString, TextEncoding};
+use std::borrow::Cow;
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct CoreParser<'a> {
@@ -7,11 +9,46 @@ pub struct CoreParser<'a> {
 
     /// Current offset in regards to the whole view of the replay
     col: i32,
+
+    /// When recording, the text (and its encoding) decoded by `parse_text` in the order parsed
+    texts: Option<Vec<RlString>>,
+
+    /// The maximum size in bytes of a string
+    max_string_size: Option<usize>,
 }
 
 impl<'a> CoreParser<'a> {
     pub fn new(data: &'a [u8]) -> Self {
-        CoreParser { data, col: 0 }
+        CoreParser {
+            data,
+            col: 0,
+            texts: None,
+            max_string_size: None,
+        }
+    }
+
+    /// Error on any subsequently parsed string that is larger than the given number of bytes
+    pub fn limit_string_size(&mut self, size: Option<usize>) {
+        self.max_string_size = size;
+    }
+
+    fn check_string_size(&self, size: usize) -> Result<(), ParseError> {
+        match self.max_string_size {
+            Some(limit) if size > limit => {
+                Err(ParseError::LimitExceeded(LimitError::StringSize(limit)))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Start recording the encoding of all subsequently parsed text
+    pub fn record_texts(&mut self) {
+        self.texts.get_or_insert_with(Vec::new);
+    }
+
+    /// Returns the text recorded since the last call, if recording
+    pub fn take_texts(&mut self) -> Option<Vec<RlString>> {
+        self.texts.as_mut().map(std::mem::take)
     }
 
     pub fn bytes_read(&self) -> i32 {
@@ -102,18 +139,37 @@ impl<'a> CoreParser<'a> {
         CoreParser::repeat(size as usize, || f(self))
     }
 
-    pub fn text_list(&mut self) -> Result<Vec<String>, ParseError> {
+    pub fn text_list(&mut self) -> Result<Vec<Cow<'a, str>>, ParseError> {
         self.list_of(CoreParser::parse_text)
     }
 
     /// Parses UTF-8 string from replay
     pub fn parse_str(&mut self) -> Result<&'a str, ParseError> {
         let size = self.take::<4>().map(i32::from_le_bytes)?;
+
+        // A negative size is corrupt, so it is rejected before it is compared to the limit
+        if size < 0 {
+            return Err(ParseError::TextTooLarge(size));
+        }
+
+        self.check_string_size(size as usize)?;
         self.take_data(size as usize).and_then(decode_str)
     }
 
-    /// Parses either UTF-16 or Windows-1252 encoded strings
-    pub fn parse_text(&mut self) -> Result<String, ParseError> {
+    /// Parses either UTF-16 or Windows-1252 encoded strings. The string is borrowed from the
+    /// replay when it doesn't need to be decoded.
+    pub fn parse_text(&mut self) -> Result<Cow<'a, str>, ParseError> {
+        let (text, encoding) = self.parse_encoded_text()?;
+        if let Some(texts) = self.texts.as_mut() {
+            texts.push(RlString {
+                value: String::from(text.as_ref()),
+                encoding,
+            });
+        }
+        Ok(text)
+    }
+
+    fn parse_encoded_text(&mut self) -> Result<(Cow<'a, str>, TextEncoding), ParseError> {
         // The number of bytes that the string is composed of. If negative, the string is UTF-16,
         // else the string is windows 1252 encoded.
         let characters = self.take::<4>().map(i32::from_le_bytes)?;
@@ -126,10 +182,15 @@ impl<'a> CoreParser<'a> {
             // multiply the size by 2. The last two bytes included in the count are
             // null terminators
             let size = characters * -2;
-            self.take_data(size as usize).and_then(decode_utf16)
+            self.check_string_size(size as usize)?;
+            self.take_data(size as usize)
+                .and_then(decode_utf16)
+                .map(|x| (Cow::Owned(x), TextEncoding::Utf16))
         } else {
+            self.check_string_size(characters as usize)?;
             self.take_data(characters as usize)
                 .and_then(decode_windows1252)
+                .map(|x| (x, TextEncoding::Windows1252))
         }
     }
 }
@@ -137,7 +198,7 @@ impl<'a> CoreParser<'a> {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::errors::ParseError;
+    use crate::errors::{LimitError, ParseError};
 
     #[test]
     fn parse_text_encoding() {
@@ -169,7 +230,7 @@ mod tests {
     fn parse_text_zero_size2() {
         let mut parser = CoreParser::new(&[0, 0, 0, 0, 0]);
         let res = parser.parse_text();
-        assert_eq!(res, Ok(String::new()));
+        assert_eq!(res, Ok(Cow::Borrowed("")));
     }
 
     #[test]
@@ -181,6 +242,28 @@ mod tests {
         assert_eq!(error, ParseError::TextTooLarge(-858993460));
     }
 
+    #[test]
+    fn parse_str_negative_size() {
+        let mut parser = CoreParser::new(&[0xfe, 0xff, 0xff, 0xff, 0x00]);
+        parser.limit_string_size(Some(10));
+        assert_eq!(parser.parse_str(), Err(ParseError::TextTooLarge(-2)));
+    }
+
+    #[test]
+    fn parse_text_limit() {
+        let data = include_bytes!("../assets/replays/partial/text.replay");
+        let mut parser = CoreParser::new(&data[..]);
+        parser.limit_string_size(Some(10));
+        assert_eq!(
+            parser.parse_text(),
+            Err(ParseError::LimitExceeded(LimitError::StringSize(10)))
+        );
+
+        let mut parser = CoreParser::new(&data[..]);
+        parser.limit_string_size(Some(24));
+        assert_eq!(parser.parse_str(), Ok("TAGame.Replay_Soccar_TA"));
+    }
+
     #[test]
     fn parse_text_encoding_bad_2() {
         // Test for when there is not enough data to decode text length
@@ -218,4 +301,29 @@ mod tests {
         let res = parser.parse_text().unwrap();
         assert_eq!(res, "�\u{0}");
     }
+
+    #[test]
+    fn record_text_encodings() {
+        let utf16 = include_bytes!("../assets/replays/partial/utf-16-text.replay");
+        let windows = include_bytes!("../assets/replays/partial/windows_1252.replay");
+        let windows = &windows[0x1ad..0x1c4];
+        let mut data = utf16.to_vec();
+        data.extend_from_slice(windows);
+
+        let mut parser = CoreParser::new(&data[..]);
+        parser.parse_text().unwrap();
+        assert_eq!(parser.take_texts(), None);
+
+        let mut parser = CoreParser::new(&data[..]);
+        parser.record_texts();
+        parser.parse_text().unwrap();
+        parser.parse_text().unwrap();
+        let texts = parser.take_texts().unwrap();
+        assert_eq!(texts[0].encoding, TextEncoding::Utf16);
+        assert_eq!(texts[0].encode(), &utf16[..]);
+        assert_eq!(texts[1].encoding, TextEncoding::Windows1252);
+        assert_eq!(texts[1].value, "caudillman6000\u{b3}(2)");
+        assert_eq!(texts[1].encode(), windows);
+        assert_eq!(parser.take_texts(), Some(Vec::new()));
+    }
 }
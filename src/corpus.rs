@@ -0,0 +1,490 @@
+//! Scan a corpus of replays and classify the failures.
+//!
+//! When a Rocket League patch lands, the first question is which replays no longer parse and why.
+//! The `Scanner` parses every replay under a directory (network data included, in parallel when
+//! the `rayon` feature is enabled) and produces a `ScanReport` that groups failures by the kind
+//! of error, the object or attribute that was missing from boxcars' knowledge, and the build
+//! version of the replay.
+//!
+//! ```no_run
+//! let report = boxcars::corpus::Scanner::new()
+//!     .scan_dir("assets/replays")
+//!     .unwrap();
+//!
+//! for (kind, failures) in report.by_kind() {
+//!     println!("{}: {}", kind, failures.len());
+//! }
+//! ```
+
+use crate::context::ParserContext;
+use crate::errors::{FrameContext, FrameError, NetworkError, ParseError};
+use crate::parser::{CrcCheck, ParserBuilder};
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+use std::any::Any;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+
+/// The category of error that caused a replay to fail to parse. Wrapping errors that only
+/// record where the failure happened (`ParseError::ParseError`, `ParseError::NetworkError`, and
+/// `NetworkError::FrameError`) are looked through so that the underlying cause is reported.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize)]
+pub enum FailureKind {
+    /// The replay could not be read from disk
+    Io,
+
+    /// The parser panicked, which is always a bug in boxcars
+    Panic,
+    Parse(&'static str),
+    Network(&'static str),
+    Frame(&'static str),
+}
+
+impl fmt::Display for FailureKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FailureKind::Io => write!(f, "io"),
+            FailureKind::Panic => write!(f, "panic"),
+            FailureKind::Parse(x) => write!(f, "ParseError::{}", x),
+            FailureKind::Network(x) => write!(f, "NetworkError::{}", x),
+            FailureKind::Frame(x) => write!(f, "FrameError::{}", x),
+        }
+    }
+}
+
+/// A replay that failed to parse
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ScanFailure {
+    pub path: PathBuf,
+    pub kind: FailureKind,
+
+    /// The name of the object or attribute that boxcars did not know how to decode, if the
+    /// error is attributable to one
+    pub missing: Option<String>,
+
+    /// The build version from the header, if the header could be parsed
+    pub build_version: Option<String>,
+
+    /// The display of the error
+    pub message: String,
+}
+
+/// The outcome of scanning a corpus of replays
+#[derive(Debug, Clone, PartialEq, Default, Serialize)]
+pub struct ScanReport {
+    /// The number of replays scanned
+    pub scanned: usize,
+
+    /// The replays that failed to parse, sorted by path
+    pub failures: Vec<ScanFailure>,
+}
+
+impl ScanReport {
+    /// The number of replays that parsed successfully
+    pub fn successes(&self) -> usize {
+        self.scanned - self.failures.len()
+    }
+
+    /// Failures grouped by their error kind
+    pub fn by_kind(&self) -> BTreeMap<FailureKind, Vec<&ScanFailure>> {
+        group(&self.failures, |x| Some(x.kind))
+    }
+
+    /// Failures grouped by the object or attribute that was missing. Failures without a missing
+    /// name are omitted.
+    pub fn by_missing(&self) -> BTreeMap<&str, Vec<&ScanFailure>> {
+        group(&self.failures, |x| x.missing.as_deref())
+    }
+
+    /// Failures grouped by the build version of the replay
+    pub fn by_build_version(&self) -> BTreeMap<Option<&str>, Vec<&ScanFailure>> {
+        group(&self.failures, |x| Some(x.build_version.as_deref()))
+    }
+}
+
+fn group<'a, K, F>(failures: &'a [ScanFailure], mut key: F) -> BTreeMap<K, Vec<&'a ScanFailure>>
+where
+    K: Ord,
+    F: FnMut(&'a ScanFailure) -> Option<K>,
+{
+    let mut result: BTreeMap<K, Vec<&ScanFailure>> = BTreeMap::new();
+    for failure in failures {
+        if let Some(k) = key(failure) {
+            result.entry(k).or_default().push(failure);
+        }
+    }
+    result
+}
+
+impl fmt::Display for ScanReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "scanned: {} succeeded: {} failed: {}",
+            self.scanned,
+            self.successes(),
+            self.failures.len()
+        )?;
+
+        if self.failures.is_empty() {
+            return Ok(());
+        }
+
+        writeln!(f, "\nby error kind:")?;
+        for (kind, failures) in self.by_kind() {
+            writeln!(f, "{:>8} {}", failures.len(), kind)?;
+        }
+
+        let missing = self.by_missing();
+        if !missing.is_empty() {
+            writeln!(f, "\nby missing object or attribute:")?;
+            for (name, failures) in missing {
+                writeln!(f, "{:>8} {}", failures.len(), name)?;
+            }
+        }
+
+        writeln!(f, "\nby build version:")?;
+        for (version, failures) in self.by_build_version() {
+            writeln!(
+                f,
+                "{:>8} {}",
+                failures.len(),
+                version.unwrap_or("<unknown>")
+            )?;
+        }
+
+        writeln!(f, "\nfailures:")?;
+        for failure in &self.failures {
+            writeln!(f, "{} ({})", failure.path.display(), failure.kind)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns the kind of error and, if applicable, the name of the object or attribute that was
+/// missing
+pub fn classify(error: &ParseError) -> (FailureKind, Option<String>) {
+    match error {
+        ParseError::ParseError(_, _, inner) => classify(inner),
+        ParseError::NetworkError(inner) => classify_network(inner),
+        ParseError::Utf8Error(_) => (FailureKind::Parse("Utf8Error"), None),
+        ParseError::TextTooLarge(_) => (FailureKind::Parse("TextTooLarge"), None),
+        ParseError::InsufficientData(_, _) => (FailureKind::Parse("InsufficientData"), None),
+        ParseError::UnexpectedProperty(x) => {
+            (FailureKind::Parse("UnexpectedProperty"), Some(x.clone()))
+        }
+        ParseError::CrcMismatch(_, _) => (FailureKind::Parse("CrcMismatch"), None),
+        ParseError::CorruptReplay(_, _) => (FailureKind::Parse("CorruptReplay"), None),
+        ParseError::ListTooLarge(_) => (FailureKind::Parse("ListTooLarge"), None),
+        ParseError::LimitExceeded(_) => (FailureKind::Parse("LimitExceeded"), None),
+    }
+}
+
+fn classify_network(error: &NetworkError) -> (FailureKind, Option<String>) {
+    match error {
+        NetworkError::FrameError(inner, context) => classify_frame(inner, context),
+        NetworkError::NotEnoughDataFor(_) => (FailureKind::Network("NotEnoughDataFor"), None),
+        NetworkError::ObjectIdOutOfRange(_) => (FailureKind::Network("ObjectIdOutOfRange"), None),
+        NetworkError::StreamTooLargeIndex(_, _) => {
+            (FailureKind::Network("StreamTooLargeIndex"), None)
+        }
+        NetworkError::MissingParentClass(obj, _) => (
+            FailureKind::Network("MissingParentClass"),
+            Some(obj.clone()),
+        ),
+        NetworkError::ParentHasNoAttributes(_, _) => {
+            (FailureKind::Network("ParentHasNoAttributes"), None)
+        }
+        NetworkError::TooManyFrames(_) => (FailureKind::Network("TooManyFrames"), None),
+        NetworkError::LimitExceeded(_) => (FailureKind::Network("LimitExceeded"), None),
+        NetworkError::Cancelled => (FailureKind::Network("Cancelled"), None),
+    }
+}
+
+fn classify_frame(error: &FrameError, context: &FrameContext) -> (FailureKind, Option<String>) {
+    let object_name = |id| context.objects.get(usize::from(id)).cloned();
+    match *error {
+        FrameError::NotEnoughDataFor(_) => (FailureKind::Frame("NotEnoughDataFor"), None),
+        FrameError::TimeOutOfRange { .. } => (FailureKind::Frame("TimeOutOfRange"), None),
+        FrameError::DeltaOutOfRange { .. } => (FailureKind::Frame("DeltaOutOfRange"), None),
+        FrameError::ObjectIdOutOfRange { .. } => (FailureKind::Frame("ObjectIdOutOfRange"), None),
+        FrameError::MissingActor { .. } => (FailureKind::Frame("MissingActor"), None),
+        FrameError::MissingCache { actor_object, .. } => (
+            FailureKind::Frame("MissingCache"),
+            object_name(actor_object),
+        ),
+        FrameError::MissingAttribute {
+            actor_object,
+            attribute_stream,
+            ..
+        }
+        | FrameError::AttributeError {
+            actor_object,
+            attribute_stream,
+            ..
+        } => {
+            let kind = if matches!(error, FrameError::MissingAttribute { .. }) {
+                FailureKind::Frame("MissingAttribute")
+            } else {
+                FailureKind::Frame("AttributeError")
+            };
+
+            // Prefer the name of the attribute, but when the stream id isn't known for the
+            // actor's object, the actor's object is the best we can do
+            let attribute = context
+                .object_attributes
+                .get(usize::from(actor_object))
+                .and_then(|x| x.as_ref())
+                .and_then(|x| x.attributes.get(attribute_stream))
+                .and_then(|x| object_name(x.object_id));
+
+            (kind, attribute.or_else(|| object_name(actor_object)))
+        }
+    }
+}
+
+/// Parses replays and collects the failures. With the `rayon` feature, replays are parsed in
+/// parallel on the current rayon thread pool, else they are parsed one after another.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Scanner {
+    crc_check: CrcCheck,
+}
+
+impl Default for Scanner {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Scanner {
+    pub fn new() -> Self {
+        Scanner {
+            crc_check: CrcCheck::OnError,
+        }
+    }
+
+    pub fn with_crc_check(mut self, check: CrcCheck) -> Self {
+        self.crc_check = check;
+        self
+    }
+
+    /// Scans all files with a `.replay` extension under the directory (recursively)
+    pub fn scan_dir<P: AsRef<Path>>(&self, dir: P) -> io::Result<ScanReport> {
+        let mut paths = Vec::new();
+        find_replays(dir.as_ref(), &mut paths)?;
+        Ok(self.scan_paths(&paths))
+    }
+
+    /// Scans the given replay files
+    pub fn scan_paths<P: AsRef<Path> + Sync>(&self, paths: &[P]) -> ScanReport {
+        #[cfg(feature = "rayon")]
+        let mut failures = paths
+            .par_iter()
+            .map_init(ParserContext::new, |context, path| {
+                self.scan_file(context, path.as_ref())
+            })
+            .flatten()
+            .collect::<Vec<_>>();
+
+        #[cfg(not(feature = "rayon"))]
+        let mut failures = {
+            let mut context = ParserContext::new();
+            paths
+                .iter()
+                .filter_map(|path| self.scan_file(&mut context, path.as_ref()))
+                .collect::<Vec<_>>()
+        };
+
+        failures.sort_by(|a, b| a.path.cmp(&b.path));
+        ScanReport {
+            scanned: paths.len(),
+            failures,
+        }
+    }
+
+    fn scan_file(&self, context: &mut ParserContext, path: &Path) -> Option<ScanFailure> {
+        let data = match fs::read(path) {
+            Ok(data) => data,
+            Err(e) => {
+                return Some(ScanFailure {
+                    path: path.to_path_buf(),
+                    kind: FailureKind::Io,
+                    missing: None,
+                    build_version: None,
+                    message: e.to_string(),
+                })
+            }
+        };
+
+        let result = catch_panic(path, || self.scan_data(context, path, &data));
+        if matches!(
+            result,
+            Some(ScanFailure {
+                kind: FailureKind::Panic,
+                ..
+            })
+        ) {
+            // The panic may have left the cached network setups half updated
+            context.clear();
+        }
+
+        result
+    }
+
+    fn scan_data(
+        &self,
+        context: &mut ParserContext,
+        path: &Path,
+        data: &[u8],
+    ) -> Option<ScanFailure> {
+        let builder = ParserBuilder::new(data)
+            .with_crc_check(self.crc_check)
+            .must_parse_network_data();
+        let error = context.parse(builder).err()?;
+
+        let build_version = ParserBuilder::new(data)
+            .never_check_crc()
+            .never_parse_network_data()
+            .parse()
+            .ok()
+            .and_then(|x| x.header().build_version().map(String::from));
+
+        let (kind, missing) = classify(&error);
+        Some(ScanFailure {
+            path: path.to_path_buf(),
+            kind,
+            missing,
+            build_version,
+            message: error.to_string(),
+        })
+    }
+}
+
+/// Runs the scan of a single replay, reporting a panic as a failure so that one replay can't
+/// abort the whole scan
+fn catch_panic<F>(path: &Path, f: F) -> Option<ScanFailure>
+where
+    F: FnOnce() -> Option<ScanFailure>,
+{
+    panic::catch_unwind(AssertUnwindSafe(f)).unwrap_or_else(|payload| {
+        Some(ScanFailure {
+            path: path.to_path_buf(),
+            kind: FailureKind::Panic,
+            missing: None,
+            build_version: None,
+            message: panic_message(payload.as_ref()),
+        })
+    })
+}
+
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    payload
+        .downcast_ref::<&str>()
+        .map(|x| String::from(*x))
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| String::from("unknown panic"))
+}
+
+fn find_replays(dir: &Path, paths: &mut Vec<PathBuf>) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            find_replays(&path, paths)?;
+        } else if path
+            .extension()
+            .is_some_and(|x| x.eq_ignore_ascii_case("replay"))
+        {
+            paths.push(path);
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classify_looks_through_wrappers() {
+        let error = ParseError::ParseError(
+            "header properties",
+            10,
+            Box::new(ParseError::UnexpectedProperty(String::from("BiteProperty"))),
+        );
+
+        assert_eq!(
+            classify(&error),
+            (
+                FailureKind::Parse("UnexpectedProperty"),
+                Some(String::from("BiteProperty"))
+            )
+        );
+
+        let error = ParseError::NetworkError(Box::new(NetworkError::TooManyFrames(10)));
+        assert_eq!(
+            classify(&error),
+            (FailureKind::Network("TooManyFrames"), None)
+        );
+        assert_eq!(
+            FailureKind::Network("TooManyFrames").to_string(),
+            "NetworkError::TooManyFrames"
+        );
+    }
+
+    #[test]
+    fn panics_are_failures() {
+        let path = Path::new("a.replay");
+        assert_eq!(catch_panic(path, || None), None);
+
+        let failure = catch_panic(path, || panic!("bad {}", "replay")).unwrap();
+        assert_eq!(failure.kind, FailureKind::Panic);
+        assert_eq!(failure.message, "bad replay");
+        assert_eq!(failure.path, path);
+    }
+
+    #[test]
+    fn report_groups() {
+        let failure =
+            |path: &str, kind, missing: Option<&str>, version: Option<&str>| ScanFailure {
+                path: PathBuf::from(path),
+                kind,
+                missing: missing.map(String::from),
+                build_version: version.map(String::from),
+                message: String::new(),
+            };
+
+        let report = ScanReport {
+            scanned: 5,
+            failures: vec![
+                failure(
+                    "a",
+                    FailureKind::Frame("MissingAttribute"),
+                    Some("x"),
+                    Some("1"),
+                ),
+                failure(
+                    "b",
+                    FailureKind::Frame("MissingAttribute"),
+                    Some("y"),
+                    Some("1"),
+                ),
+                failure("c", FailureKind::Parse("CorruptReplay"), None, None),
+            ],
+        };
+
+        assert_eq!(report.successes(), 2);
+        assert_eq!(
+            report.by_kind()[&FailureKind::Frame("MissingAttribute")].len(),
+            2
+        );
+        assert_eq!(report.by_missing().len(), 2);
+        assert_eq!(report.by_build_version()[&Some("1")].len(), 2);
+        assert_eq!(report.by_build_version()[&None].len(), 1);
+    }
+}
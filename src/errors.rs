@@ -1,11 +1,13 @@
 use crate::data::ATTRIBUTES;
-use crate::network::{ActorId, Frame, NewActor, ObjectId, StreamId, UpdatedAttribute};
+use crate::network::{ActorId, ColumnKind, Frame, NewActor, ObjectId, StreamId, UpdatedAttribute};
 use crate::{AttributeTag, CacheInfo};
 use fnv::FnvHashMap;
 use std::error::Error;
 use std::fmt;
 use std::fmt::{Display, Formatter};
+use std::io;
 use std::ops::Deref;
+use std::path::PathBuf;
 use std::str;
 
 #[derive(PartialEq, Debug, Clone)]
@@ -18,6 +20,7 @@ pub enum ParseError {
     CrcMismatch(u32, u32),
     CorruptReplay(String, Box<ParseError>),
     ListTooLarge(usize),
+    LimitExceeded(LimitError),
     NetworkError(Box<NetworkError>),
 }
 
@@ -47,6 +50,7 @@ impl Display for ParseError {
                 section
             ),
             ParseError::ListTooLarge(size) => write!(f, "list of size {} is too large", size),
+            ParseError::LimitExceeded(limit) => write!(f, "{}", limit),
             ParseError::ParseError(section, bytes_read, parse_error) => write!(
                 f,
                 "Could not decode replay {} at offset ({}): {}",
@@ -64,6 +68,7 @@ impl Error for ParseError {
             ParseError::CorruptReplay(_, error) => Some(error),
             ParseError::ParseError(_, _, error) => Some(error),
             ParseError::NetworkError(error) => Some(error),
+            ParseError::LimitExceeded(error) => Some(error),
             _ => None,
         }
     }
@@ -75,17 +80,184 @@ impl From<str::Utf8Error> for ParseError {
     }
 }
 
+/// A replay exceeded one of the configured `Limits`. Each variant contains the limit that was
+/// exceeded.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum LimitError {
+    Frames(usize),
+    LiveActors(usize),
+    Updates(usize),
+    StringSize(usize),
+    OutputBytes(usize),
+}
+
+impl Error for LimitError {}
+
+impl Display for LimitError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            LimitError::Frames(limit) => write!(f, "replay exceeds the limit of {} frames", limit),
+            LimitError::LiveActors(limit) => {
+                write!(f, "replay exceeds the limit of {} live actors", limit)
+            }
+            LimitError::Updates(limit) => {
+                write!(f, "replay exceeds the limit of {} attribute updates", limit)
+            }
+            LimitError::StringSize(limit) => {
+                write!(f, "replay contains a string larger than {} bytes", limit)
+            }
+            LimitError::OutputBytes(limit) => write!(
+                f,
+                "decoded network data exceeds the limit of {} bytes",
+                limit
+            ),
+        }
+    }
+}
+
+/// An error parsing a replay read from a file
+#[derive(Debug)]
+pub enum FileError {
+    Io(PathBuf, io::Error),
+    Parse(PathBuf, ParseError),
+}
+
+impl FileError {
+    /// The path of the replay that failed
+    pub fn path(&self) -> &PathBuf {
+        match self {
+            FileError::Io(path, _) | FileError::Parse(path, _) => path,
+        }
+    }
+}
+
+impl Error for FileError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            FileError::Io(_, error) => Some(error),
+            FileError::Parse(_, error) => Some(error),
+        }
+    }
+}
+
+impl Display for FileError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            FileError::Io(path, error) => {
+                write!(f, "Unable to read {}: {}", path.display(), error)
+            }
+            FileError::Parse(path, error) => {
+                write!(f, "Unable to parse {}: {}", path.display(), error)
+            }
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub enum QueryError {
+    Empty,
+    UnexpectedCharacter(char, usize),
+    UnterminatedIndex(usize),
+    InvalidIndex(String),
+}
+
+impl Error for QueryError {}
+
+impl Display for QueryError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            QueryError::Empty => write!(f, "Query does not contain a path"),
+            QueryError::UnexpectedCharacter(c, pos) => {
+                write!(
+                    f,
+                    "Unexpected character '{}' in query at position {}",
+                    c, pos
+                )
+            }
+            QueryError::UnterminatedIndex(pos) => {
+                write!(f, "Index starting at position {} is not terminated", pos)
+            }
+            QueryError::InvalidIndex(index) => {
+                write!(f, "Index of {} is not a number or wildcard", index)
+            }
+        }
+    }
+}
+
+/// An inconsistency in `ColumnarFrames` that prevents reconstructing the frames
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub enum ColumnarError {
+    /// The frame times and deltas have different lengths
+    FrameLength(usize, usize),
+
+    /// The offsets of the named list are not one more than the number of frames, are not
+    /// ascending, or do not end at the list's length
+    InvalidOffsets(&'static str),
+
+    /// The column's vectors have different lengths or the column's length doesn't match the
+    /// number of updates of its kind
+    ColumnLength(ColumnKind),
+}
+
+impl Error for ColumnarError {}
+
+impl Display for ColumnarError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            ColumnarError::FrameLength(times, deltas) => {
+                write!(f, "Frames have {} times but {} deltas", times, deltas)
+            }
+            ColumnarError::InvalidOffsets(list) => {
+                write!(f, "Offsets of the {} are invalid", list)
+            }
+            ColumnarError::ColumnLength(kind) => {
+                write!(f, "Column of {:?} updates has inconsistent lengths", kind)
+            }
+        }
+    }
+}
+
+/// An error parsing the canonical string form of a `PlayerIdentity`
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub enum IdentityError {
+    MissingPlatform,
+    UnknownPlatform(String),
+    InvalidId(String),
+    InvalidLocalId(String),
+}
+
+impl Error for IdentityError {}
+
+impl Display for IdentityError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            IdentityError::MissingPlatform => {
+                write!(f, "Identity is not prefixed with a platform")
+            }
+            IdentityError::UnknownPlatform(platform) => {
+                write!(f, "Unknown identity platform of {}", platform)
+            }
+            IdentityError::InvalidId(id) => write!(f, "Invalid identity id of {}", id),
+            IdentityError::InvalidLocalId(id) => write!(f, "Invalid identity local id of {}", id),
+        }
+    }
+}
+
 #[derive(PartialEq, Debug, Clone)]
 pub enum AttributeError {
     NotEnoughDataFor(&'static str),
     UnrecognizedRemoteId(u8),
     Unimplemented,
     TooBigString(i32),
+    LimitExceeded(LimitError),
 }
 
 impl Error for AttributeError {
     fn source(&self) -> Option<&(dyn Error + 'static)> {
-        None
+        match self {
+            AttributeError::LimitExceeded(limit) => Some(limit),
+            _ => None,
+        }
     }
 }
 
@@ -100,6 +272,7 @@ impl Display for AttributeError {
             }
             AttributeError::Unimplemented => write!(f, "Does not have an attribute implementation"),
             AttributeError::TooBigString(size) => write!(f, "Unexpected size for string: {}", size),
+            AttributeError::LimitExceeded(limit) => write!(f, "{}", limit),
         }
     }
 }
@@ -395,12 +568,15 @@ pub enum NetworkError {
     ParentHasNoAttributes(ObjectId, ObjectId),
     FrameError(FrameError, Box<FrameContext>),
     TooManyFrames(i32),
+    LimitExceeded(LimitError),
+    Cancelled,
 }
 
 impl Error for NetworkError {
     fn source(&self) -> Option<&(dyn Error + 'static)> {
         match self {
             NetworkError::FrameError(err, _) => Some(err),
+            NetworkError::LimitExceeded(err) => Some(err),
             _ => None,
         }
     }
@@ -429,6 +605,8 @@ impl Display for NetworkError {
                 parent_id, object_id
             ),
             NetworkError::TooManyFrames(size) => write!(f, "Too many frames to decode: {}", size),
+            NetworkError::LimitExceeded(limit) => write!(f, "{}", limit),
+            NetworkError::Cancelled => write!(f, "Network data decoding was cancelled"),
             NetworkError::FrameError(err, context) => {
                 write!(f, "Error decoding frame: {}", err)?;
                 if !matches!(err, FrameError::MissingAttribute { .. }) {
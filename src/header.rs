@@ -1,48 +1,44 @@
 use crate::core_parser::CoreParser;
 use crate::errors::ParseError;
-use crate::models::HeaderProp;
+use crate::models::{find_prop_ref, HeaderPropRef};
+use std::borrow::Cow;
 
 /// Intermediate parsing structure for the header
 #[derive(Debug, PartialEq)]
-pub struct Header {
+pub struct Header<'a> {
     pub major_version: i32,
     pub minor_version: i32,
     pub net_version: Option<i32>,
-    pub game_type: String,
-    pub properties: Vec<(String, HeaderProp)>,
+    pub game_type: Cow<'a, str>,
+    pub properties: Vec<(Cow<'a, str>, HeaderPropRef<'a>)>,
 }
 
-impl Header {
+impl Header<'_> {
+    fn get(&self, key: &str) -> Option<&HeaderPropRef<'_>> {
+        find_prop_ref(&self.properties, key)
+    }
+
     pub fn num_frames(&self) -> Option<i32> {
-        self.properties
-            .iter()
-            .find(|&(key, _)| key == "NumFrames")
-            .and_then(|(_, ref prop)| prop.as_i32())
+        self.get("NumFrames").and_then(HeaderPropRef::as_i32)
     }
 
     pub fn max_channels(&self) -> Option<i32> {
-        self.properties
-            .iter()
-            .find(|&(key, _)| key == "MaxChannels")
-            .and_then(|(_, ref prop)| prop.as_i32())
+        self.get("MaxChannels").and_then(HeaderPropRef::as_i32)
     }
 
     pub fn match_type(&self) -> Option<&str> {
-        self.properties
-            .iter()
-            .find(|&(key, _)| key == "MatchType")
-            .and_then(|(_, ref prop)| prop.as_string())
+        self.get("MatchType").and_then(HeaderPropRef::as_string)
     }
 
     pub fn build_version(&self) -> Option<&str> {
-        self.properties
-            .iter()
-            .find(|&(key, _)| key == "BuildVersion")
-            .and_then(|(_, ref prop)| prop.as_string())
+        self.get("BuildVersion").and_then(HeaderPropRef::as_string)
     }
 }
 
-pub fn parse_header(rlp: &mut CoreParser) -> Result<Header, ParseError> {
+pub fn parse_header<'a>(
+    rlp: &mut CoreParser<'a>,
+    retain_unknown: bool,
+) -> Result<Header<'a>, ParseError> {
     let major_version = rlp.take_i32("major version")?;
     let minor_version = rlp.take_i32("minor version")?;
     let net_version = if major_version > 865 && minor_version > 17 {
@@ -56,11 +52,17 @@ pub fn parse_header(rlp: &mut CoreParser) -> Result<Header, ParseError> {
         _ => ParserMode::Standard,
     };
 
+    let unknown = if retain_unknown {
+        UnknownProperty::Retain
+    } else {
+        UnknownProperty::Error
+    };
+
     let game_type = rlp
         .parse_text()
         .map_err(|e| ParseError::ParseError("game type", rlp.bytes_read(), Box::new(e)))?;
 
-    let properties = parse_rdict(rlp, mode)
+    let properties = parse_rdict(rlp, mode, unknown)
         .map_err(|e| ParseError::ParseError("header properties", rlp.bytes_read(), Box::new(e)))?;
 
     Ok(Header {
@@ -78,10 +80,18 @@ enum ParserMode {
     Quirks,
 }
 
-fn parse_rdict(
-    rlp: &mut CoreParser,
+/// How to handle a property type that isn't recognized
+#[derive(Clone, Copy)]
+enum UnknownProperty {
+    Error,
+    Retain,
+}
+
+fn parse_rdict<'a>(
+    rlp: &mut CoreParser<'a>,
     mode: ParserMode,
-) -> Result<Vec<(String, HeaderProp)>, ParseError> {
+    unknown: UnknownProperty,
+) -> Result<Vec<(Cow<'a, str>, HeaderPropRef<'a>)>, ParseError> {
     // The return type of this function is a key value vector because since there is no format
     // specification, we can't rule out duplicate keys.
     let mut res: Vec<_> = Vec::new();
@@ -93,8 +103,9 @@ fn parse_rdict(
 
         let kind = rlp.parse_str()?;
         // This size is normally the number of bytes a property takes up,
-        // but it can't be trusted, so we're ignoring it.
-        let _size = rlp.take_u32("property size")? as usize;
+        // but it can't be trusted, so it is only used to skip over
+        // properties that aren't recognized.
+        let size = rlp.take_u32("property size")? as usize;
         let _ignored = rlp.take_data(4)?;
         let val = match kind {
             "BoolProperty" => match mode {
@@ -112,55 +123,65 @@ fn parse_rdict(
 
                     // kind SHOULD equal "OnlinePlatform"
                     let value = rlp.parse_str().map(Some)?;
-                    Ok(HeaderProp::Byte {
-                        kind: String::from(kind),
-                        value: value.map(String::from),
+                    Ok(HeaderPropRef::Byte {
+                        kind: Cow::Borrowed(kind),
+                        value: value.map(Cow::Borrowed),
                     })
                 }
                 ParserMode::Quirks => rlp
                     .parse_text()
-                    .map(|kind| HeaderProp::Byte { kind, value: None }),
+                    .map(|kind| HeaderPropRef::Byte { kind, value: None }),
             },
-            "ArrayProperty" => array_property(rlp, mode),
+            "ArrayProperty" => array_property(rlp, mode, unknown),
             "FloatProperty" => rlp
                 .take_bytes::<4>()
                 .map(f32::from_le_bytes)
-                .map(HeaderProp::Float),
+                .map(HeaderPropRef::Float),
             "IntProperty" => rlp
                 .take_bytes::<4>()
                 .map(i32::from_le_bytes)
-                .map(HeaderProp::Int),
+                .map(HeaderPropRef::Int),
             "QWordProperty" => rlp
                 .take_bytes::<8>()
                 .map(u64::from_le_bytes)
-                .map(HeaderProp::QWord),
-            "NameProperty" => rlp.parse_text().map(HeaderProp::Name),
-            "StrProperty" => rlp.parse_text().map(HeaderProp::Str),
+                .map(HeaderPropRef::QWord),
+            "NameProperty" => rlp.parse_text().map(HeaderPropRef::Name),
+            "StrProperty" => rlp.parse_text().map(HeaderPropRef::Str),
             "StructProperty" => {
                 let name = rlp.parse_str()?;
-                let fields = parse_rdict(rlp, mode)?;
-                Ok(HeaderProp::Struct {
-                    name: String::from(name),
+                let fields = parse_rdict(rlp, mode, unknown)?;
+                Ok(HeaderPropRef::Struct {
+                    name: Cow::Borrowed(name),
                     fields,
                 })
             }
-            x => Err(ParseError::UnexpectedProperty(String::from(x))),
+            x => match unknown {
+                UnknownProperty::Error => Err(ParseError::UnexpectedProperty(String::from(x))),
+                UnknownProperty::Retain => rlp.take_data(size).map(|raw| HeaderPropRef::Unknown {
+                    kind: Cow::Borrowed(x),
+                    raw: Cow::Borrowed(raw),
+                }),
+            },
         }?;
 
-        res.push((String::from(key), val));
+        res.push((Cow::Borrowed(key), val));
     }
 
     Ok(res)
 }
 
-fn bool_prop(data: &[u8]) -> HeaderProp {
-    HeaderProp::Bool(data[0] == 1)
+fn bool_prop(data: &[u8]) -> HeaderPropRef<'static> {
+    HeaderPropRef::Bool(data[0] == 1)
 }
 
-fn array_property(rlp: &mut CoreParser, mode: ParserMode) -> Result<HeaderProp, ParseError> {
+fn array_property<'a>(
+    rlp: &mut CoreParser<'a>,
+    mode: ParserMode,
+    unknown: UnknownProperty,
+) -> Result<HeaderPropRef<'a>, ParseError> {
     let size = rlp.take_i32("array property size")?;
-    let arr = CoreParser::repeat(size as usize, || parse_rdict(rlp, mode))?;
-    Ok(HeaderProp::Array(arr))
+    let arr = CoreParser::repeat(size as usize, || parse_rdict(rlp, mode, unknown))?;
+    Ok(HeaderPropRef::Array(arr))
 }
 
 #[cfg(test)]
@@ -168,12 +189,21 @@ mod tests {
     use crate::core_parser::CoreParser;
 
     use super::*;
+    use crate::models::HeaderProp;
+
+    fn owned(props: Vec<(Cow<'_, str>, HeaderPropRef<'_>)>) -> Vec<(String, HeaderProp)> {
+        props
+            .into_iter()
+            .map(|(k, v)| (k.into_owned(), v.into_owned()))
+            .collect()
+    }
 
     #[test]
     fn rdict_no_elements() {
         let data = [0x05, 0x00, 0x00, 0x00, b'N', b'o', b'n', b'e', 0x00];
         let mut parser = CoreParser::new(&data[..]);
-        let res = parse_rdict(&mut parser, ParserMode::Standard).unwrap();
+        let res =
+            owned(parse_rdict(&mut parser, ParserMode::Standard, UnknownProperty::Error).unwrap());
         assert_eq!(res, Vec::new());
     }
 
@@ -182,7 +212,8 @@ mod tests {
         // dd skip=$((0x1269)) count=$((0x12a8 - 0x1269)) if=rumble.replay of=rdict_one.replay bs=1
         let data = include_bytes!("../assets/replays/partial/rdict_one.replay");
         let mut parser = CoreParser::new(&data[..]);
-        let res = parse_rdict(&mut parser, ParserMode::Standard).unwrap();
+        let res =
+            owned(parse_rdict(&mut parser, ParserMode::Standard, UnknownProperty::Error).unwrap());
         assert_eq!(
             res,
             vec![(
@@ -197,7 +228,8 @@ mod tests {
         // dd skip=$((0x250)) count=$((0x284 - 0x250)) if=rumble.replay of=rdict_int.replay bs=1
         let data = include_bytes!("../assets/replays/partial/rdict_int.replay");
         let mut parser = CoreParser::new(&data[..]);
-        let res = parse_rdict(&mut parser, ParserMode::Standard).unwrap();
+        let res =
+            owned(parse_rdict(&mut parser, ParserMode::Standard, UnknownProperty::Error).unwrap());
         assert_eq!(res, vec![(String::from("PlayerTeam"), HeaderProp::Int(0))]);
     }
 
@@ -206,7 +238,8 @@ mod tests {
         // dd skip=$((0xa0f)) count=$((0xa3b - 0xa0f)) if=rumble.replay of=rdict_bool.replay bs=1
         let data = include_bytes!("../assets/replays/partial/rdict_bool.replay");
         let mut parser = CoreParser::new(&data[..]);
-        let res = parse_rdict(&mut parser, ParserMode::Standard).unwrap();
+        let res =
+            owned(parse_rdict(&mut parser, ParserMode::Standard, UnknownProperty::Error).unwrap());
         assert_eq!(res, vec![(String::from("bBot"), HeaderProp::Bool(false))]);
     }
 
@@ -225,7 +258,8 @@ mod tests {
             "../assets/replays/partial/rdict_name.replay"
         ));
         let mut parser = CoreParser::new(&data[..]);
-        let res = parse_rdict(&mut parser, ParserMode::Standard).unwrap();
+        let res =
+            owned(parse_rdict(&mut parser, ParserMode::Standard, UnknownProperty::Error).unwrap());
         assert_eq!(
             res,
             vec![(
@@ -242,7 +276,8 @@ mod tests {
             "../assets/replays/partial/rdict_float.replay"
         ));
         let mut parser = CoreParser::new(&data[..]);
-        let res = parse_rdict(&mut parser, ParserMode::Standard).unwrap();
+        let res =
+            owned(parse_rdict(&mut parser, ParserMode::Standard, UnknownProperty::Error).unwrap());
         assert_eq!(
             res,
             vec![(String::from("RecordFPS"), HeaderProp::Float(30.0))]
@@ -256,7 +291,8 @@ mod tests {
             "../assets/replays/partial/rdict_qword.replay"
         ));
         let mut parser = CoreParser::new(&data[..]);
-        let res = parse_rdict(&mut parser, ParserMode::Standard).unwrap();
+        let res =
+            owned(parse_rdict(&mut parser, ParserMode::Standard, UnknownProperty::Error).unwrap());
         assert_eq!(
             res,
             vec![(
@@ -273,7 +309,8 @@ mod tests {
             "../assets/replays/partial/rdict_array.replay"
         ));
         let mut parser = CoreParser::new(&data[..]);
-        let res = parse_rdict(&mut parser, ParserMode::Standard).unwrap();
+        let res =
+            owned(parse_rdict(&mut parser, ParserMode::Standard, UnknownProperty::Error).unwrap());
         let expected = vec![
             vec![
                 (String::from("frame"), HeaderProp::Int(441)),
@@ -345,7 +382,8 @@ mod tests {
             "../assets/replays/partial/rdict_byte.replay"
         ));
         let mut parser = CoreParser::new(&data[..]);
-        let res = parse_rdict(&mut parser, ParserMode::Standard).unwrap();
+        let res =
+            owned(parse_rdict(&mut parser, ParserMode::Standard, UnknownProperty::Error).unwrap());
         assert_eq!(
             res,
             vec![(
@@ -365,10 +403,41 @@ mod tests {
             "../assets/replays/partial/rdict_unrecognized.replay"
         ));
         let mut parser = CoreParser::new(&data[..]);
-        let res = parse_rdict(&mut parser, ParserMode::Standard).unwrap_err();
+        let res =
+            parse_rdict(&mut parser, ParserMode::Standard, UnknownProperty::Error).unwrap_err();
         assert_eq!(
             res.to_string(),
             String::from("Did not expect a property of: BiteProperty")
         );
     }
+
+    #[test]
+    fn rdict_retain_unrecognized_property() {
+        let mut data = Vec::new();
+        data.extend_from_slice(&[0x07, 0x00, 0x00, 0x00]);
+        data.extend_from_slice(b"Custom\0");
+        data.extend_from_slice(&[0x0e, 0x00, 0x00, 0x00]);
+        data.extend_from_slice(b"FancyProperty\0");
+        data.extend_from_slice(&[0x03, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00]);
+        data.extend_from_slice(&[0xaa, 0xbb, 0xcc]);
+        let data = append_none(&data);
+
+        let mut parser = CoreParser::new(&data[..]);
+        let res =
+            owned(parse_rdict(&mut parser, ParserMode::Standard, UnknownProperty::Retain).unwrap());
+        assert_eq!(
+            res,
+            vec![(
+                String::from("Custom"),
+                HeaderProp::Unknown {
+                    kind: String::from("FancyProperty"),
+                    raw: vec![0xaa, 0xbb, 0xcc],
+                }
+            )]
+        );
+
+        let mut parser = CoreParser::new(&data[..]);
+        let res = parse_rdict(&mut parser, ParserMode::Standard, UnknownProperty::Error);
+        assert!(res.is_err());
+    }
 }
@@ -0,0 +1,328 @@
+//! # Hitbox
+//!
+//! Every car body uses one of six hitbox presets, regardless of how the car looks. A hitbox is an
+//! oriented box whose center is offset from the car's origin (the location replicated in its
+//! `RigidBody`). Knowing the hitbox of each car allows for contact and proximity calculations
+//! such as how close a car came to the ball.
+//!
+//! ```
+//! use boxcars::hitbox::Hitbox;
+//! use boxcars::{Quaternion, RigidBody, Vector3f};
+//!
+//! let hitbox = Hitbox::from_body(23).unwrap();
+//! assert_eq!(hitbox, Hitbox::Octane);
+//!
+//! let car = RigidBody {
+//!     sleeping: false,
+//!     location: Vector3f { x: 0.0, y: 0.0, z: 17.0 },
+//!     rotation: Quaternion { x: 0.0, y: 0.0, z: 0.0, w: 1.0 },
+//!     linear_velocity: None,
+//!     angular_velocity: None,
+//! };
+//!
+//! // The ball's center is in front of the car at the height of the hitbox's center. The replay's
+//! // net version (10) replicates the car's rotation as a quaternion.
+//! let ball = Vector3f { x: 200.0, y: 0.0, z: 40.0 };
+//! let distance = hitbox.dimensions().distance(&car, Some(10), ball).unwrap();
+//! assert!((distance - 127.12).abs() < 0.01);
+//! ```
+
+use crate::network::attributes::RigidBody;
+use crate::network::{Quaternion, Vector3f};
+
+/// The hitbox presets that every car body uses one of
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum Hitbox {
+    Octane,
+    Dominus,
+    Plank,
+    Breakout,
+    Hybrid,
+    Merc,
+}
+
+impl Hitbox {
+    /// The hitbox of the car body with the given product id (ie: `Loadout::body`). Bodies that
+    /// are not known to the embedded table, or whose hitbox is not known, are `None`.
+    pub fn from_body(body: u32) -> Option<Hitbox> {
+        BODIES.get(&body).and_then(|&(_, hitbox)| hitbox)
+    }
+
+    /// The size of the hitbox and where it is positioned relative to the car's origin, in
+    /// unreal units
+    pub fn dimensions(self) -> HitboxDimensions {
+        let (length, width, height, offset) = match self {
+            Hitbox::Octane => (118.0074, 84.19941, 36.15907, (13.87566, 20.75499)),
+            Hitbox::Dominus => (127.9268, 83.27995, 31.3, (9.0, 15.75)),
+            Hitbox::Plank => (128.8198, 84.67036, 29.3944, (9.008572, 12.0942)),
+            Hitbox::Breakout => (131.4924, 80.521, 30.3, (12.5, 11.75)),
+            Hitbox::Hybrid => (127.0192, 82.18787, 34.15907, (13.87566, 20.75499)),
+            Hitbox::Merc => (120.72, 76.71, 41.66, (11.37, 21.5)),
+        };
+
+        HitboxDimensions {
+            length,
+            width,
+            height,
+            offset: Vector3f {
+                x: offset.0,
+                y: 0.0,
+                z: offset.1,
+            },
+        }
+    }
+}
+
+/// The size and position of a hitbox in unreal units. The car's forward direction is the x axis
+/// and its up direction is the z axis.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct HitboxDimensions {
+    pub length: f32,
+    pub width: f32,
+    pub height: f32,
+
+    /// The center of the hitbox relative to the car's origin
+    pub offset: Vector3f,
+}
+
+impl HitboxDimensions {
+    /// The distance from the point to the surface of the hitbox of a car with the given rigid
+    /// body, or 0 if the point is inside the hitbox. The `net_version` is the replay's
+    /// `Replay::net_version`, which determines how the car's rotation was replicated. Replays
+    /// before net version 7 replicate a compressed rotation that is not a quaternion and is not
+    /// a usable orientation, so the distance is `None` for them.
+    pub fn distance(
+        &self,
+        car: &RigidBody,
+        net_version: Option<i32>,
+        point: Vector3f,
+    ) -> Option<f32> {
+        if !is_quaternion(net_version) {
+            return None;
+        }
+
+        let world = Vector3f {
+            x: point.x - car.location.x,
+            y: point.y - car.location.y,
+            z: point.z - car.location.z,
+        };
+
+        let local = rotate_inverse(car.rotation, world);
+        let dx = outside(local.x - self.offset.x, self.length / 2.0);
+        let dy = outside(local.y - self.offset.y, self.width / 2.0);
+        let dz = outside(local.z - self.offset.z, self.height / 2.0);
+        Some((dx * dx + dy * dy + dz * dz).sqrt())
+    }
+
+    /// Whether the point is inside the hitbox of a car with the given rigid body, or `None` when
+    /// the distance is not known (see [`HitboxDimensions::distance`])
+    pub fn contains(
+        &self,
+        car: &RigidBody,
+        net_version: Option<i32>,
+        point: Vector3f,
+    ) -> Option<bool> {
+        self.distance(car, net_version, point).map(|x| x == 0.0)
+    }
+}
+
+/// Whether rigid bodies of replays with the net version replicate their rotation as a quaternion
+fn is_quaternion(net_version: Option<i32>) -> bool {
+    net_version.unwrap_or(0) >= 7
+}
+
+/// How far the coordinate is outside of the extent centered at 0
+fn outside(x: f32, extent: f32) -> f32 {
+    (x.abs() - extent).max(0.0)
+}
+
+/// Rotates the vector by the inverse of the rotation, which transforms a vector from world space
+/// into the rotated object's space
+fn rotate_inverse(q: Quaternion, v: Vector3f) -> Vector3f {
+    let (x, y, z) = (-q.x, -q.y, -q.z);
+
+    // t = 2 * cross(q.xyz, v)
+    let tx = 2.0 * (y * v.z - z * v.y);
+    let ty = 2.0 * (z * v.x - x * v.z);
+    let tz = 2.0 * (x * v.y - y * v.x);
+
+    // v + w * t + cross(q.xyz, t)
+    Vector3f {
+        x: v.x + q.w * tx + (y * tz - z * ty),
+        y: v.y + q.w * ty + (z * tx - x * tz),
+        z: v.z + q.w * tz + (x * ty - y * tx),
+    }
+}
+
+// The car bodies by product id with their name and hitbox. This is the only table of bodies: the
+// catalog resolves body names from it.
+//
+// Please keep entries sorted by key (ascending)
+pub(crate) static BODIES: phf::Map<u32, (&'static str, Option<Hitbox>)> = phf::phf_map! {
+    21u32 => ("Backfire", Some(Hitbox::Octane)),
+    22u32 => ("Breakout", Some(Hitbox::Breakout)),
+    23u32 => ("Octane", Some(Hitbox::Octane)),
+    24u32 => ("Paladin", Some(Hitbox::Plank)),
+    25u32 => ("Road Hog", Some(Hitbox::Octane)),
+    26u32 => ("Gizmo", Some(Hitbox::Octane)),
+    27u32 => ("Sweet Tooth", None),
+    28u32 => ("X-Devil", Some(Hitbox::Hybrid)),
+    29u32 => ("Hotshot", Some(Hitbox::Dominus)),
+    30u32 => ("Merc", Some(Hitbox::Merc)),
+    31u32 => ("Venom", Some(Hitbox::Hybrid)),
+    402u32 => ("Takumi", Some(Hitbox::Octane)),
+    403u32 => ("Dominus", Some(Hitbox::Dominus)),
+    404u32 => ("Scarab", Some(Hitbox::Octane)),
+    523u32 => ("Zippy", Some(Hitbox::Octane)),
+    597u32 => ("DeLorean Time Machine", Some(Hitbox::Dominus)),
+    600u32 => ("Ripper", Some(Hitbox::Dominus)),
+    607u32 => ("Grog", Some(Hitbox::Octane)),
+    625u32 => ("Armadillo", Some(Hitbox::Octane)),
+    723u32 => ("Hogsticker", Some(Hitbox::Octane)),
+    803u32 => ("'16 Batmobile", Some(Hitbox::Plank)),
+    1018u32 => ("Dominus GT", Some(Hitbox::Dominus)),
+    1159u32 => ("X-Devil Mk2", Some(Hitbox::Hybrid)),
+    1171u32 => ("Masamune", Some(Hitbox::Dominus)),
+    1172u32 => ("Marauder", Some(Hitbox::Octane)),
+    1286u32 => ("Aftershock", Some(Hitbox::Dominus)),
+    1295u32 => ("Takumi RX-T", Some(Hitbox::Octane)),
+    1300u32 => ("Road Hog XL", Some(Hitbox::Octane)),
+    1317u32 => ("Esper", Some(Hitbox::Hybrid)),
+    1416u32 => ("Breakout Type-S", Some(Hitbox::Breakout)),
+    1475u32 => ("Proteus", None),
+    1478u32 => ("Triton", None),
+    1533u32 => ("Vulcan", Some(Hitbox::Octane)),
+    1568u32 => ("Octane ZSR", Some(Hitbox::Octane)),
+    1603u32 => ("Twinmill III", Some(Hitbox::Dominus)),
+    1623u32 => ("Bone Shaker", Some(Hitbox::Octane)),
+    1624u32 => ("Endo", Some(Hitbox::Hybrid)),
+    1675u32 => ("Ice Charger", Some(Hitbox::Dominus)),
+    1691u32 => ("Mantis", Some(Hitbox::Plank)),
+    1856u32 => ("Jäger 619 RS", Some(Hitbox::Hybrid)),
+    1883u32 => ("Imperator DT5", Some(Hitbox::Dominus)),
+    1919u32 => ("Centio V17", Some(Hitbox::Plank)),
+    1932u32 => ("Animus GP", Some(Hitbox::Breakout)),
+    2070u32 => ("Werewolf", Some(Hitbox::Dominus)),
+    2268u32 => ("Dingo", None),
+    2269u32 => ("Diestro", Some(Hitbox::Dominus)),
+    2298u32 => ("Samurai", Some(Hitbox::Breakout)),
+    2665u32 => ("The Dark Knight Rises Tumbler", None),
+    2666u32 => ("'89 Batmobile", Some(Hitbox::Dominus)),
+    2853u32 => ("Twinzer", Some(Hitbox::Octane)),
+    2919u32 => ("Jurassic Jeep Wrangler", Some(Hitbox::Octane)),
+    4284u32 => ("Fennec", Some(Hitbox::Octane)),
+};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NET_VERSION: Option<i32> = Some(10);
+
+    fn car(rotation: Quaternion) -> RigidBody {
+        RigidBody {
+            sleeping: false,
+            location: Vector3f {
+                x: 100.0,
+                y: 100.0,
+                z: 17.0,
+            },
+            rotation,
+            linear_velocity: None,
+            angular_velocity: None,
+        }
+    }
+
+    #[test]
+    fn test_distance_unrotated() {
+        let dims = Hitbox::Octane.dimensions();
+        let car = car(Quaternion {
+            x: 0.0,
+            y: 0.0,
+            z: 0.0,
+            w: 1.0,
+        });
+
+        let center = Vector3f {
+            x: 100.0 + dims.offset.x,
+            y: 100.0,
+            z: 17.0 + dims.offset.z,
+        };
+        assert_eq!(dims.contains(&car, NET_VERSION, center), Some(true));
+
+        let front = Vector3f {
+            x: center.x + dims.length / 2.0 + 10.0,
+            ..center
+        };
+        assert!((dims.distance(&car, NET_VERSION, front).unwrap() - 10.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_distance_rotated() {
+        // Yawed 90 degrees so that the car faces the positive y axis
+        let dims = Hitbox::Dominus.dimensions();
+        let half = std::f32::consts::FRAC_1_SQRT_2;
+        let car = car(Quaternion {
+            x: 0.0,
+            y: 0.0,
+            z: half,
+            w: half,
+        });
+
+        let front = Vector3f {
+            x: 100.0,
+            y: 100.0 + dims.offset.x + dims.length / 2.0 + 5.0,
+            z: 17.0 + dims.offset.z,
+        };
+        assert!((dims.distance(&car, NET_VERSION, front).unwrap() - 5.0).abs() < 1e-3);
+
+        let side = Vector3f {
+            x: 100.0 + dims.width / 2.0 - 1.0,
+            y: 100.0 + dims.offset.x,
+            z: 17.0 + dims.offset.z,
+        };
+        assert_eq!(dims.contains(&car, NET_VERSION, side), Some(true));
+    }
+
+    #[test]
+    fn test_distance_turned_around() {
+        // Yawed 180 degrees so that the car faces the negative x axis, which has a `w` of 0
+        let dims = Hitbox::Octane.dimensions();
+        let car = car(Quaternion {
+            x: 0.0,
+            y: 0.0,
+            z: 1.0,
+            w: 0.0,
+        });
+
+        let front = Vector3f {
+            x: 100.0 - dims.offset.x - dims.length / 2.0 - 10.0,
+            y: 100.0,
+            z: 17.0 + dims.offset.z,
+        };
+        assert!((dims.distance(&car, NET_VERSION, front).unwrap() - 10.0).abs() < 1e-3);
+
+        let center = Vector3f {
+            x: 100.0 - dims.offset.x,
+            ..front
+        };
+        assert_eq!(dims.contains(&car, NET_VERSION, center), Some(true));
+    }
+
+    #[test]
+    fn test_distance_compressed_rotation() {
+        // A compressed rotation of a car facing backwards, which has a length of 1 but is not a
+        // quaternion
+        let dims = Hitbox::Octane.dimensions();
+        let car = car(Quaternion {
+            x: 0.0,
+            y: 1.0,
+            z: 0.0,
+            w: 0.0,
+        });
+
+        assert_eq!(dims.distance(&car, Some(5), car.location), None);
+        assert_eq!(dims.contains(&car, Some(5), car.location), None);
+        assert_eq!(dims.distance(&car, None, car.location), None);
+    }
+}
@@ -0,0 +1,749 @@
+//! # Identity
+//!
+//! Players are identified in the network data by a `UniqueId` replicated on their PRI actor and
+//! in the header by the `Platform` and `OnlineID` (or `PlayerID`) of their `PlayerStats` entry.
+//! A [`PlayerIdentity`] normalizes both into one type with a canonical string form, such as
+//! `steam:76561198055442516` or `epic:62c2cffa806543e4add327f76cf9d7b6`. Players sharing a
+//! console in split screen have the same id and are distinguished by their local id, which is
+//! appended to the canonical form when it isn't zero (eg: `xbox:2535447016429069/1`).
+//!
+//! Identities also encode who queued together: each PRI replicates the identity of its party
+//! leader and the game replicates a reservation per player, which [`parties`] groups by.
+//!
+//! ```
+//! use boxcars::identity::{Platform, PlayerIdentity};
+//!
+//! let identity: PlayerIdentity = "steam:76561198055442516".parse().unwrap();
+//! assert_eq!(identity.platform, Platform::Steam);
+//! assert_eq!(identity.to_string(), "steam:76561198055442516");
+//! ```
+
+use crate::analytics::{frames, Change, Tracker};
+use crate::errors::IdentityError;
+use crate::models::Replay;
+use crate::network::attributes::{Attribute, RemoteId, UniqueId};
+use crate::network::ActorId;
+use crate::replay_header::PlayerStats;
+use fnv::FnvHashMap;
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+pub enum Platform {
+    Steam,
+    PlayStation,
+    Xbox,
+    Switch,
+    Epic,
+    PsyNet,
+    QQ,
+    SplitScreen,
+}
+
+impl Platform {
+    /// The prefix of the platform in the canonical string form
+    pub fn prefix(self) -> &'static str {
+        match self {
+            Platform::Steam => "steam",
+            Platform::PlayStation => "psn",
+            Platform::Xbox => "xbox",
+            Platform::Switch => "switch",
+            Platform::Epic => "epic",
+            Platform::PsyNet => "psynet",
+            Platform::QQ => "qq",
+            Platform::SplitScreen => "splitscreen",
+        }
+    }
+
+    fn from_prefix(prefix: &str) -> Option<Platform> {
+        match prefix {
+            "steam" => Some(Platform::Steam),
+            "psn" => Some(Platform::PlayStation),
+            "xbox" => Some(Platform::Xbox),
+            "switch" => Some(Platform::Switch),
+            "epic" => Some(Platform::Epic),
+            "psynet" => Some(Platform::PsyNet),
+            "qq" => Some(Platform::QQ),
+            "splitscreen" => Some(Platform::SplitScreen),
+            _ => None,
+        }
+    }
+
+    /// The platform from the header's `Platform` property (eg: `OnlinePlatform_Steam`). Xbox is
+    /// known internally as `Dingo` and the Switch as `NNX`.
+    pub fn from_header(platform: &str) -> Option<Platform> {
+        match platform.strip_prefix("OnlinePlatform_")? {
+            "Steam" => Some(Platform::Steam),
+            "PS3" | "PS4" => Some(Platform::PlayStation),
+            "Dingo" => Some(Platform::Xbox),
+            "NNX" => Some(Platform::Switch),
+            "Epic" => Some(Platform::Epic),
+            "PsyNet" => Some(Platform::PsyNet),
+            "QQ" => Some(Platform::QQ),
+            _ => None,
+        }
+    }
+}
+
+/// A platform agnostic identity of a player
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize)]
+pub struct PlayerIdentity {
+    pub platform: Platform,
+
+    /// The id of the player on the platform: a number for every platform except Epic
+    pub id: String,
+
+    /// Distinguishes the players playing split screen on the same console. The header does not
+    /// record the local id, so identities from the header have a local id of 0.
+    pub local_id: u8,
+}
+
+impl PlayerIdentity {
+    /// The identity from a `UniqueId` replicated in the network data. An id of zero, as used by
+    /// bots, or an empty Epic id does not identify a player.
+    pub fn from_unique_id(unique_id: &UniqueId) -> Option<PlayerIdentity> {
+        let (platform, id) = match &unique_id.remote_id {
+            RemoteId::SplitScreen(x) => (Platform::SplitScreen, x.to_string()),
+            RemoteId::Steam(x) => (Platform::Steam, x.to_string()),
+            RemoteId::PlayStation(x) => (Platform::PlayStation, x.online_id.to_string()),
+            RemoteId::Xbox(x) => (Platform::Xbox, x.to_string()),
+            RemoteId::Switch(x) => (Platform::Switch, x.online_id.to_string()),
+            RemoteId::PsyNet(x) => (Platform::PsyNet, x.online_id.to_string()),
+            RemoteId::QQ(x) => (Platform::QQ, x.to_string()),
+            RemoteId::Epic(x) => (Platform::Epic, x.clone()),
+        };
+
+        if id.is_empty() || id == "0" {
+            return None;
+        }
+
+        Some(PlayerIdentity {
+            platform,
+            id,
+            local_id: unique_id.local_id,
+        })
+    }
+
+    /// The identity from a header `PlayerStats` entry. Bots and Epic players in replays that
+    /// predate the `PlayerID` property do not have an identity.
+    pub fn from_stats(stats: &PlayerStats<'_>) -> Option<PlayerIdentity> {
+        let platform = Platform::from_header(stats.platform()?)?;
+        let id = match platform {
+            Platform::Epic => String::from(stats.epic_account_id()?),
+            _ => stats.online_id()?.to_string(),
+        };
+
+        if id.is_empty() || id == "0" {
+            return None;
+        }
+
+        Some(PlayerIdentity {
+            platform,
+            id,
+            local_id: 0,
+        })
+    }
+
+    /// Whether both identities are for the same account, regardless of the local id
+    pub fn same_account(&self, other: &PlayerIdentity) -> bool {
+        self.platform == other.platform && self.id == other.id
+    }
+}
+
+impl fmt::Display for PlayerIdentity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.platform.prefix(), self.id)?;
+        if self.local_id != 0 {
+            write!(f, "/{}", self.local_id)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for PlayerIdentity {
+    type Err = IdentityError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (prefix, rest) = s.split_once(':').ok_or(IdentityError::MissingPlatform)?;
+        let platform = Platform::from_prefix(prefix)
+            .ok_or_else(|| IdentityError::UnknownPlatform(String::from(prefix)))?;
+
+        let (id, local_id) = match rest.split_once('/') {
+            Some((id, local)) => {
+                let local_id = local
+                    .parse::<u8>()
+                    .map_err(|_| IdentityError::InvalidLocalId(String::from(local)))?;
+                (id, local_id)
+            }
+            None => (rest, 0),
+        };
+
+        let valid = match platform {
+            Platform::Epic => !id.is_empty() && id.bytes().all(|x| x.is_ascii_alphanumeric()),
+            _ => id.parse::<u64>().is_ok(),
+        };
+
+        if !valid {
+            return Err(IdentityError::InvalidId(String::from(id)));
+        }
+
+        Ok(PlayerIdentity {
+            platform,
+            id: String::from(id),
+            local_id,
+        })
+    }
+}
+
+/// A player seen in the network data, the header, or both
+#[derive(Debug, Clone, PartialEq)]
+pub struct JoinedPlayer<'a> {
+    pub identity: Option<PlayerIdentity>,
+
+    /// The name last replicated in the network data or else the name in the header
+    pub name: String,
+
+    /// The PRI actor last associated with the player
+    pub actor_id: Option<ActorId>,
+
+    /// The player's entry of the header's `PlayerStats`
+    pub stats: Option<PlayerStats<'a>>,
+}
+
+/// Joins the players seen in the network data with the header's `PlayerStats` entries. Players
+/// are joined by identity and otherwise by name, as the identity may be missing (eg: bots) and a
+/// player's name may differ between the two. Split screen players share an identity in the
+/// header, so they are joined in the order of their local id unless their names match. Players
+/// appear in the order they were first seen in the network data followed by any header entries
+/// that were not joined.
+pub fn join_players(replay: &Replay) -> Vec<JoinedPlayer<'_>> {
+    let stats = replay.header().player_stats().unwrap_or_default();
+    let mut unjoined: Vec<_> = stats
+        .into_iter()
+        .map(|x| (PlayerIdentity::from_stats(&x), x))
+        .map(Some)
+        .collect();
+
+    let mut players = network_players(replay);
+
+    // Join split screen players in the order of their local id
+    let mut order: Vec<usize> = (0..players.len()).collect();
+    order.sort_by_key(|&i| players[i].identity.as_ref().map_or(0, |x| x.local_id));
+
+    // Join exact identities and names first, so that the fallbacks only consider what remains
+    for exact in [true, false] {
+        for &i in &order {
+            let player = &mut players[i];
+            if player.stats.is_some() {
+                continue;
+            }
+
+            let position = unjoined.iter().position(|entry| {
+                let Some((identity, stats)) = entry else {
+                    return false;
+                };
+
+                let same_name = stats.name() == Some(player.name.as_str());
+                match (&player.identity, identity) {
+                    (Some(x), Some(y)) => x.same_account(y) && (same_name || !exact),
+                    (_, None) => same_name,
+                    (None, Some(_)) => false,
+                }
+            });
+
+            if let Some((_, stats)) = position.and_then(|x| unjoined[x].take()) {
+                player.stats = Some(stats);
+            }
+        }
+    }
+
+    players.extend(
+        unjoined
+            .into_iter()
+            .flatten()
+            .map(|(identity, stats)| JoinedPlayer {
+                identity,
+                name: String::from(stats.name().unwrap_or_default()),
+                actor_id: None,
+                stats: Some(stats),
+            }),
+    );
+
+    players
+}
+
+/// The players replicated in the network data, as linked to their PRIs by the analytics tracker
+fn network_players(replay: &Replay) -> Vec<JoinedPlayer<'_>> {
+    let mut tracker = Tracker::new(replay);
+    for frame in frames(replay) {
+        tracker.apply(frame);
+    }
+
+    tracker
+        .into_players()
+        .into_iter()
+        .map(|x| JoinedPlayer {
+            identity: x.identity,
+            name: x.name,
+            actor_id: Some(x.actor_id),
+            stats: None,
+        })
+        .collect()
+}
+
+/// Players that queued together
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Party {
+    /// The player that the members replicated as their party leader. A player that queued alone
+    /// is their own leader. The leader may not be a member when they left before the replay
+    /// started recording.
+    pub leader: PlayerIdentity,
+
+    /// Members ordered by when they were first seen
+    pub members: Vec<PartyMember>,
+}
+
+impl Party {
+    /// Whether the party consists of a single player that queued alone
+    pub fn is_solo(&self) -> bool {
+        self.members.len() == 1 && self.members[0].identity == self.leader
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PartyMember {
+    pub identity: PlayerIdentity,
+
+    /// The name last replicated by the player's PRI or else by their reservation
+    pub name: String,
+
+    /// The slot of the player's reservation in `ProjectX.GRI_X:Reservations`. Reservations are
+    /// compacted as players leave, so this is the slot last replicated for the player.
+    pub reservation: Option<u32>,
+}
+
+/// Groups the players of the replay into parties from their replicated reservations and party
+/// leaders. Players without an identity (eg: bots) are not reported. Parties are ordered by when
+/// their first member was seen, and nothing is reported when the network data was not parsed.
+pub fn parties(replay: &Replay) -> Vec<Party> {
+    let mut tracker = Tracker::new(replay);
+    let mut members: Vec<PartyMember> = Vec::new();
+    let mut leaders: FnvHashMap<PlayerIdentity, PlayerIdentity> = FnvHashMap::default();
+
+    fn member<'a>(members: &'a mut Vec<PartyMember>, id: &PlayerIdentity) -> &'a mut PartyMember {
+        let index = match members.iter().position(|x| &x.identity == id) {
+            Some(index) => index,
+            None => {
+                members.push(PartyMember {
+                    identity: id.clone(),
+                    name: String::new(),
+                    reservation: None,
+                });
+                members.len() - 1
+            }
+        };
+        &mut members[index]
+    }
+
+    fn identity<'a>(tracker: &'a Tracker<'_>, actor_id: ActorId) -> Option<&'a PlayerIdentity> {
+        let player = tracker.pri_player(actor_id)?;
+        tracker.players()[player].identity.as_ref()
+    }
+
+    // The tracker links a PRI to its player before returning the frame's changes, so a leader
+    // replicated before the PRI's identity in the same frame is still resolved
+    let mut lead = |tracker: &Tracker<'_>, actor_id: ActorId, leader: &Option<Box<UniqueId>>| {
+        let Some(id) = identity(tracker, actor_id) else {
+            return;
+        };
+
+        match leader.as_deref().and_then(PlayerIdentity::from_unique_id) {
+            Some(leader) => leaders.insert(id.clone(), leader),
+            None => leaders.remove(id),
+        };
+    };
+
+    for frame in frames(replay) {
+        for change in tracker.apply(frame) {
+            let update = match change {
+                Change::Updated {
+                    update,
+                    changed: true,
+                } => update,
+                Change::Linked { actor_id, values } => {
+                    // Party leaders replicated before the player's name
+                    for (_, attribute) in &values {
+                        if let Attribute::PartyLeader(leader) = attribute {
+                            lead(&tracker, actor_id, leader);
+                        }
+                    }
+                    continue;
+                }
+                _ => continue,
+            };
+
+            if let Some(id) = identity(&tracker, update.actor_id) {
+                member(&mut members, id);
+            }
+
+            match &update.attribute {
+                Attribute::Reservation(reservation) => {
+                    let Some(id) = PlayerIdentity::from_unique_id(&reservation.unique_id) else {
+                        continue;
+                    };
+
+                    let member = member(&mut members, &id);
+                    member.reservation = Some(reservation.number);
+                    if let Some(name) = reservation.name.as_ref().filter(|_| member.name.is_empty())
+                    {
+                        member.name = name.clone();
+                    }
+                }
+                Attribute::PartyLeader(leader) => lead(&tracker, update.actor_id, leader),
+                _ => {}
+            }
+        }
+
+        // The name last replicated by a player's PRIs takes precedence over their reservation's
+        for player in tracker.players() {
+            let Some(id) = player.identity.as_ref() else {
+                continue;
+            };
+
+            let member = member(&mut members, id);
+            if member.name != player.name {
+                member.name.clone_from(&player.name);
+            }
+        }
+    }
+
+    let mut parties: Vec<Party> = Vec::new();
+    for member in members {
+        let leader = leaders.get(&member.identity).unwrap_or(&member.identity);
+        match parties.iter_mut().find(|x| &x.leader == leader) {
+            Some(party) => party.members.push(member),
+            None => parties.push(Party {
+                leader: leader.clone(),
+                members: vec![member],
+            }),
+        }
+    }
+
+    parties
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_identity_round_trip() {
+        let ids = [
+            "steam:76561198055442516",
+            "psn:496450030777679623",
+            "xbox:2535447016429069/1",
+            "switch:12579039502294648053",
+            "epic:62c2cffa806543e4add327f76cf9d7b6",
+            "psynet:1",
+            "qq:2",
+            "splitscreen:3",
+        ];
+
+        for id in ids {
+            let identity: PlayerIdentity = id.parse().unwrap();
+            assert_eq!(identity.to_string(), id);
+        }
+    }
+
+    #[test]
+    fn test_identity_parse_errors() {
+        assert_eq!(
+            "76561198055442516".parse::<PlayerIdentity>(),
+            Err(IdentityError::MissingPlatform)
+        );
+        assert_eq!(
+            "gog:1".parse::<PlayerIdentity>(),
+            Err(IdentityError::UnknownPlatform(String::from("gog")))
+        );
+        assert_eq!(
+            "steam:abc".parse::<PlayerIdentity>(),
+            Err(IdentityError::InvalidId(String::from("abc")))
+        );
+        assert_eq!(
+            "epic:".parse::<PlayerIdentity>(),
+            Err(IdentityError::InvalidId(String::new()))
+        );
+        assert_eq!(
+            "xbox:1/256".parse::<PlayerIdentity>(),
+            Err(IdentityError::InvalidLocalId(String::from("256")))
+        );
+    }
+
+    #[test]
+    fn test_identity_from_unique_id() {
+        let unique_id = UniqueId {
+            system_id: 4,
+            remote_id: RemoteId::Xbox(2535447016429069),
+            local_id: 1,
+        };
+        let identity = PlayerIdentity::from_unique_id(&unique_id).unwrap();
+        assert_eq!(identity.to_string(), "xbox:2535447016429069/1");
+
+        let bot = UniqueId {
+            system_id: 0,
+            remote_id: RemoteId::SplitScreen(0),
+            local_id: 0,
+        };
+        assert_eq!(PlayerIdentity::from_unique_id(&bot), None);
+
+        let unset = UniqueId {
+            system_id: 1,
+            remote_id: RemoteId::Steam(0),
+            local_id: 0,
+        };
+        assert_eq!(PlayerIdentity::from_unique_id(&unset), None);
+    }
+
+    #[test]
+    fn test_join_split_screen_players() {
+        use crate::models::{HeaderProp, NetworkFrames};
+        use crate::network::{Frame, ObjectId, StreamId, UpdatedAttribute};
+
+        let data = include_bytes!("../assets/replays/good/small-frames.replay");
+        let mut replay = crate::ParserBuilder::new(&data[..])
+            .never_parse_network_data()
+            .parse()
+            .unwrap();
+
+        let stats = |name: &str| {
+            vec![
+                (String::from("Name"), HeaderProp::Str(String::from(name))),
+                (
+                    String::from("Platform"),
+                    HeaderProp::Byte {
+                        kind: String::from("OnlinePlatform"),
+                        value: Some(String::from("OnlinePlatform_Dingo")),
+                    },
+                ),
+                (
+                    String::from("OnlineID"),
+                    HeaderProp::QWord(2535447016429069),
+                ),
+            ]
+        };
+
+        // Split screen players share the account's online id in the header
+        replay.properties = vec![(
+            String::from("PlayerStats"),
+            HeaderProp::Array(vec![stats("Host"), stats("Host(2)")]),
+        )];
+
+        replay.objects = vec![
+            String::from("Engine.PlayerReplicationInfo:UniqueId"),
+            String::from("Engine.PlayerReplicationInfo:PlayerName"),
+        ];
+
+        let update = |actor_id, object_id, attribute| UpdatedAttribute {
+            actor_id: ActorId(actor_id),
+            stream_id: StreamId(0),
+            object_id: ObjectId(object_id),
+            attribute,
+        };
+
+        let unique_id = |local_id| {
+            Attribute::UniqueId(Box::new(UniqueId {
+                system_id: 4,
+                remote_id: RemoteId::Xbox(2535447016429069),
+                local_id,
+            }))
+        };
+
+        // The guest is replicated first and neither name matches the header
+        replay.network_frames = Some(NetworkFrames {
+            frames: vec![Frame {
+                time: 0.0,
+                delta: 0.0,
+                new_actors: Vec::new(),
+                deleted_actors: Vec::new(),
+                updated_actors: vec![
+                    update(2, 0, unique_id(1)),
+                    update(2, 1, Attribute::String(String::from("Guest"))),
+                    update(1, 0, unique_id(0)),
+                    update(1, 1, Attribute::String(String::from("Player"))),
+                ],
+            }],
+        });
+
+        let players = join_players(&replay);
+        assert_eq!(players.len(), 2);
+
+        let joined: Vec<_> = players
+            .iter()
+            .map(|x| {
+                (
+                    x.identity.as_ref().unwrap().to_string(),
+                    x.stats.as_ref().and_then(|x| x.name()),
+                )
+            })
+            .collect();
+        assert_eq!(
+            joined,
+            vec![
+                (String::from("xbox:2535447016429069/1"), Some("Host(2)")),
+                (String::from("xbox:2535447016429069"), Some("Host")),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_join_rejoined_players_and_bots() {
+        use crate::models::NetworkFrames;
+        use crate::network::{Frame, ObjectId, StreamId, UpdatedAttribute};
+
+        let data = include_bytes!("../assets/replays/good/small-frames.replay");
+        let mut replay = crate::ParserBuilder::new(&data[..])
+            .never_parse_network_data()
+            .parse()
+            .unwrap();
+
+        replay.properties = Vec::new();
+        replay.objects = vec![
+            String::from("Engine.PlayerReplicationInfo:UniqueId"),
+            String::from("Engine.PlayerReplicationInfo:PlayerName"),
+        ];
+
+        let update = |actor_id, object_id, attribute| UpdatedAttribute {
+            actor_id: ActorId(actor_id),
+            stream_id: StreamId(0),
+            object_id: ObjectId(object_id),
+            attribute,
+        };
+
+        let name = |name: &str| Attribute::String(String::from(name));
+        let unique_id = Attribute::UniqueId(Box::new(UniqueId {
+            system_id: 1,
+            remote_id: RemoteId::Steam(76561198055442516),
+            local_id: 0,
+        }));
+
+        let frame = |deleted_actors, updated_actors| Frame {
+            time: 0.0,
+            delta: 0.0,
+            new_actors: Vec::new(),
+            deleted_actors,
+            updated_actors,
+        };
+
+        // The player rejoins with a new PRI under a new name, and the bot's PRI is recreated
+        replay.network_frames = Some(NetworkFrames {
+            frames: vec![
+                frame(
+                    Vec::new(),
+                    vec![
+                        update(1, 0, unique_id.clone()),
+                        update(1, 1, name("Player")),
+                        update(2, 1, name("Bot")),
+                    ],
+                ),
+                frame(vec![ActorId(1), ActorId(2)], Vec::new()),
+                frame(
+                    Vec::new(),
+                    vec![
+                        update(3, 1, name("Renamed")),
+                        update(3, 0, unique_id),
+                        update(4, 1, name("Bot")),
+                    ],
+                ),
+            ],
+        });
+
+        let players: Vec<_> = join_players(&replay)
+            .into_iter()
+            .map(|x| (x.identity.map(|x| x.to_string()), x.name, x.actor_id))
+            .collect();
+        assert_eq!(
+            players,
+            vec![
+                (
+                    Some(String::from("steam:76561198055442516")),
+                    String::from("Renamed"),
+                    Some(ActorId(3))
+                ),
+                (None, String::from("Bot"), Some(ActorId(4))),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_party_leader_replicated_before_player_name() {
+        use crate::models::NetworkFrames;
+        use crate::network::{Frame, ObjectId, StreamId, UpdatedAttribute};
+
+        let data = include_bytes!("../assets/replays/good/small-frames.replay");
+        let mut replay = crate::ParserBuilder::new(&data[..])
+            .never_parse_network_data()
+            .parse()
+            .unwrap();
+
+        replay.objects = vec![
+            String::from("Engine.PlayerReplicationInfo:UniqueId"),
+            String::from("Engine.PlayerReplicationInfo:PlayerName"),
+            String::from("TAGame.PRI_TA:PartyLeader"),
+        ];
+
+        let update = |object_id, attribute| UpdatedAttribute {
+            actor_id: ActorId(1),
+            stream_id: StreamId(0),
+            object_id: ObjectId(object_id),
+            attribute,
+        };
+
+        let unique_id = |id| {
+            Box::new(UniqueId {
+                system_id: 1,
+                remote_id: RemoteId::Steam(id),
+                local_id: 0,
+            })
+        };
+
+        let frame = |updated_actors| Frame {
+            time: 0.0,
+            delta: 0.0,
+            new_actors: Vec::new(),
+            deleted_actors: Vec::new(),
+            updated_actors,
+        };
+
+        replay.network_frames = Some(NetworkFrames {
+            frames: vec![
+                frame(vec![
+                    update(0, Attribute::UniqueId(unique_id(76561198055442516))),
+                    update(
+                        2,
+                        Attribute::PartyLeader(Some(unique_id(76561198101748375))),
+                    ),
+                ]),
+                frame(vec![update(1, Attribute::String(String::from("Member")))]),
+            ],
+        });
+
+        let parties = parties(&replay);
+        assert_eq!(parties.len(), 1);
+        assert_eq!(parties[0].leader.to_string(), "steam:76561198101748375");
+        assert_eq!(parties[0].members.len(), 1);
+        assert_eq!(parties[0].members[0].name, "Member");
+    }
+
+    #[test]
+    fn test_platform_from_header() {
+        assert_eq!(
+            Platform::from_header("OnlinePlatform_Dingo"),
+            Some(Platform::Xbox)
+        );
+        assert_eq!(
+            Platform::from_header("OnlinePlatform_NNX"),
+            Some(Platform::Switch)
+        );
+        assert_eq!(Platform::from_header("OnlinePlatform_Unknown"), None);
+    }
+}
@@ -70,19 +70,40 @@ extern crate serde;
 
 #[macro_use]
 mod macros;
-pub use self::errors::{AttributeError, FrameContext, FrameError, NetworkError, ParseError};
+#[cfg(feature = "rayon")]
+pub use self::batch::{parse_many, parse_many_files};
+pub use self::context::ParserContext;
+pub use self::errors::{
+    AttributeError, ColumnarError, FileError, FrameContext, FrameError, IdentityError, LimitError,
+    NetworkError, ParseError, QueryError,
+};
 pub use self::models::*;
 pub use self::network::attributes::*;
 pub use self::network::*;
-pub use self::parser::{CrcCheck, NetworkParse, ParserBuilder};
+pub use self::parser::{CrcCheck, Limits, NetworkParse, ParserBuilder};
+pub use self::query::{FromHeaderProp, HeaderPath, PathSegment};
+pub use self::replay_header::{Goal, Highlight, PlayerStats, ReplayHeader};
+pub use self::text::{ReplayStrings, RlString, TextEncoding};
+pub mod analytics;
+#[cfg(feature = "rayon")]
+mod batch;
 mod bits;
+#[cfg(feature = "catalog")]
+pub mod catalog;
+mod context;
 mod core_parser;
+pub mod corpus;
 pub mod crc;
 mod data;
 mod errors;
 mod header;
+pub mod hitbox;
+pub mod identity;
 mod models;
 mod network;
 mod parser;
 mod parsing_utils;
+pub mod query;
+mod replay_header;
 mod serde_utils;
+mod text;
@@ -3,19 +3,31 @@
 /// Here lies the data structures that a rocket league replay is decoded into. All of the models
 /// are contained in this one file because of serde.
 ///
-/// For serde, the focus is serialization, JSON serialization. Deserialization is not
-/// implemented for the types in this file because their JSON output is lossy (JSON isn't the best
-/// with different numeric/string types). The decoded network data is the exception: the types in
-/// `crate::network` round trip through serde exactly, so already-decoded frames can be persisted
-/// and restored without reparsing a replay. Asking "why JSON" would be next logical step, and
-/// that's due to other rocket league replay parsers (like Octane) using JSON; however, the output
-/// of this library is not compatible with that of other rocket league replay parsers.
-use crate::network::Frame;
+/// For serde, the focus is serialization, JSON serialization. The default serialization of header
+/// properties is lossy (JSON isn't the best with different numeric/string types): it deserializes
+/// back, but names and quadwords come back as strings. Instead, `Replay::tagged` serializes header
+/// properties with their type so that the output can be deserialized back into an identical
+/// `Replay` with `Tagged<Replay>`. The decoded network data in `crate::network` round trips
+/// through serde exactly either way. Asking "why JSON" would be next
+/// logical step, and that's due to other rocket league replay parsers (like Octane) using JSON;
+/// however, the output of this library is not compatible with that of other rocket league replay
+/// parsers.
+use crate::network::{ColumnarFrames, Frame};
+use crate::text::ReplayStrings;
+use serde::de::{self, Deserializer, MapAccess, SeqAccess, Visitor};
 use serde::ser::{SerializeMap, SerializeSeq, SerializeStruct};
-use serde::{Serialize, Serializer};
+use serde::{Deserialize, Serialize, Serializer};
+use std::borrow::Cow;
+use std::fmt;
+use std::marker::PhantomData;
 
 /// The structure that a rocket league replay is parsed into.
-#[derive(Serialize, PartialEq, Debug, Clone)]
+///
+/// The default deserialization is lossy: header properties that are serialized as a string (a
+/// `QWord`, `Name`, or `Str`) are all deserialized as a `Str` (see `HeaderProp`), so a replay
+/// that is serialized and deserialized may not equal the original. Serialize with
+/// `Replay::tagged` and deserialize with `Tagged<Replay>` for a lossless round trip.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
 pub struct Replay {
     pub header_size: i32,
     pub header_crc: u32,
@@ -26,11 +38,16 @@ pub struct Replay {
 
     /// Could use a map to represent properties but I don't want to assume that duplicate keys
     /// can't exist, so to be safe, use a traditional vector.
-    #[serde(serialize_with = "pair_vec")]
+    #[serde(serialize_with = "pair_vec", deserialize_with = "de_pair_vec")]
     pub properties: Vec<(String, HeaderProp)>,
     pub content_size: i32,
     pub content_crc: u32,
     pub network_frames: Option<NetworkFrames>,
+
+    /// The network data decoded into columns. Only populated when requested with
+    /// `ParserBuilder::columnar_frames`, in which case `network_frames` is absent.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub columnar_frames: Option<ColumnarFrames>,
     pub levels: Vec<String>,
     pub keyframes: Vec<KeyFrame>,
     pub debug_info: Vec<DebugInfo>,
@@ -40,10 +57,193 @@ pub struct Replay {
     pub names: Vec<String>,
     pub class_indices: Vec<ClassIndex>,
     pub net_cache: Vec<ClassNetCache>,
+
+    /// The original encoding of decoded strings. Only populated when requested with
+    /// `ParserBuilder::preserve_text_encodings`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub strings: Option<ReplayStrings>,
+}
+
+impl Replay {
+    /// Returns a serializable view of the replay where header properties are written with their
+    /// type, so that the output can be deserialized back into an identical `Replay` with
+    /// `Tagged<Replay>`.
+    ///
+    /// ```
+    /// # use boxcars::{ParserBuilder, Replay, Tagged};
+    /// let data = include_bytes!("../assets/replays/good/rumble.replay");
+    /// let replay = ParserBuilder::new(&data[..]).parse().unwrap();
+    /// let json = serde_json::to_string(&replay.tagged()).unwrap();
+    /// let Tagged(restored): Tagged<Replay> = serde_json::from_str(&json).unwrap();
+    /// assert_eq!(restored, replay);
+    /// ```
+    pub fn tagged(&self) -> Tagged<&Replay> {
+        Tagged(self)
+    }
+}
+
+/// A replay that borrows its strings from the replay data where possible. Most strings in a
+/// replay are ASCII and can be borrowed as is, so parsing into a `ReplayRef` with
+/// `ParserBuilder::parse_borrowed` avoids allocating them. Strings that need to be decoded
+/// (eg: UTF-16 player names) are owned. The debug info, tick marks, and class indices are always
+/// owned.
+#[derive(PartialEq, Debug, Clone)]
+pub struct ReplayRef<'a> {
+    pub header_size: i32,
+    pub header_crc: u32,
+    pub major_version: i32,
+    pub minor_version: i32,
+    pub net_version: Option<i32>,
+    pub game_type: Cow<'a, str>,
+    pub properties: Vec<(Cow<'a, str>, HeaderPropRef<'a>)>,
+    pub content_size: i32,
+    pub content_crc: u32,
+    pub network_frames: Option<NetworkFrames>,
+    pub columnar_frames: Option<ColumnarFrames>,
+    pub levels: Vec<Cow<'a, str>>,
+    pub keyframes: Vec<KeyFrame>,
+    pub debug_info: Vec<DebugInfo>,
+    pub tick_marks: Vec<TickMark>,
+    pub packages: Vec<Cow<'a, str>>,
+    pub objects: Vec<Cow<'a, str>>,
+    pub names: Vec<Cow<'a, str>>,
+    pub class_indices: Vec<ClassIndex>,
+    pub net_cache: Vec<ClassNetCache>,
+    pub strings: Option<ReplayStrings>,
+}
+
+impl ReplayRef<'_> {
+    /// Returns the first header property with the given key
+    pub fn get(&self, key: &str) -> Option<&HeaderPropRef<'_>> {
+        find_prop_ref(&self.properties, key)
+    }
+
+    /// Copies all borrowed data into a `Replay`
+    pub fn into_owned(self) -> Replay {
+        Replay {
+            header_size: self.header_size,
+            header_crc: self.header_crc,
+            major_version: self.major_version,
+            minor_version: self.minor_version,
+            net_version: self.net_version,
+            game_type: self.game_type.into_owned(),
+            properties: owned_props(self.properties),
+            content_size: self.content_size,
+            content_crc: self.content_crc,
+            network_frames: self.network_frames,
+            columnar_frames: self.columnar_frames,
+            levels: owned_strs(self.levels),
+            keyframes: self.keyframes,
+            debug_info: self.debug_info,
+            tick_marks: self.tick_marks,
+            packages: owned_strs(self.packages),
+            objects: owned_strs(self.objects),
+            names: owned_strs(self.names),
+            class_indices: self.class_indices,
+            net_cache: self.net_cache,
+            strings: self.strings,
+        }
+    }
+}
+
+fn owned_strs(data: Vec<Cow<'_, str>>) -> Vec<String> {
+    data.into_iter().map(Cow::into_owned).collect()
+}
+
+fn owned_props(props: Vec<(Cow<'_, str>, HeaderPropRef<'_>)>) -> Vec<(String, HeaderProp)> {
+    props
+        .into_iter()
+        .map(|(k, v)| (k.into_owned(), v.into_owned()))
+        .collect()
+}
+
+pub(crate) fn find_prop_ref<'a, 'b>(
+    properties: &'a [(Cow<'b, str>, HeaderPropRef<'b>)],
+    key: &str,
+) -> Option<&'a HeaderPropRef<'b>> {
+    properties
+        .iter()
+        .find(|(k, _)| k == key)
+        .map(|(_, prop)| prop)
+}
+
+/// The borrowed counterpart of `HeaderProp` found in a `ReplayRef`
+#[derive(PartialEq, Debug, Clone)]
+pub enum HeaderPropRef<'a> {
+    Array(Vec<Vec<(Cow<'a, str>, HeaderPropRef<'a>)>>),
+    Bool(bool),
+    Byte {
+        kind: Cow<'a, str>,
+        value: Option<Cow<'a, str>>,
+    },
+    Float(f32),
+    Int(i32),
+    Name(Cow<'a, str>),
+    QWord(u64),
+    Str(Cow<'a, str>),
+    Struct {
+        name: Cow<'a, str>,
+        fields: Vec<(Cow<'a, str>, HeaderPropRef<'a>)>,
+    },
+    Unknown {
+        kind: Cow<'a, str>,
+        raw: Cow<'a, [u8]>,
+    },
+}
+
+impl HeaderPropRef<'_> {
+    /// If the property is an int (or a quadword that fits), returns the value
+    pub fn as_i32(&self) -> Option<i32> {
+        match *self {
+            HeaderPropRef::Int(x) => Some(x),
+            HeaderPropRef::QWord(x) => i32::try_from(x).ok(),
+            _ => None,
+        }
+    }
+
+    /// If the property is a name or string, returns the value
+    pub fn as_string(&self) -> Option<&str> {
+        match self {
+            HeaderPropRef::Name(x) | HeaderPropRef::Str(x) => Some(x),
+            _ => None,
+        }
+    }
+
+    /// Copies all borrowed data into a `HeaderProp`
+    ///
+    /// ```
+    /// # use boxcars::{HeaderProp, HeaderPropRef};
+    /// # use std::borrow::Cow;
+    /// let prop = HeaderPropRef::Str(Cow::Borrowed("abc"));
+    /// assert_eq!(prop.into_owned(), HeaderProp::Str(String::from("abc")));
+    /// ```
+    pub fn into_owned(self) -> HeaderProp {
+        match self {
+            HeaderPropRef::Array(x) => HeaderProp::Array(x.into_iter().map(owned_props).collect()),
+            HeaderPropRef::Bool(x) => HeaderProp::Bool(x),
+            HeaderPropRef::Byte { kind, value } => HeaderProp::Byte {
+                kind: kind.into_owned(),
+                value: value.map(Cow::into_owned),
+            },
+            HeaderPropRef::Float(x) => HeaderProp::Float(x),
+            HeaderPropRef::Int(x) => HeaderProp::Int(x),
+            HeaderPropRef::Name(x) => HeaderProp::Name(x.into_owned()),
+            HeaderPropRef::QWord(x) => HeaderProp::QWord(x),
+            HeaderPropRef::Str(x) => HeaderProp::Str(x.into_owned()),
+            HeaderPropRef::Struct { name, fields } => HeaderProp::Struct {
+                name: name.into_owned(),
+                fields: owned_props(fields),
+            },
+            HeaderPropRef::Unknown { kind, raw } => HeaderProp::Unknown {
+                kind: kind.into_owned(),
+                raw: raw.into_owned(),
+            },
+        }
+    }
 }
 
 /// The frames decoded from the network data
-#[derive(Serialize, PartialEq, Debug, Clone)]
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
 pub struct NetworkFrames {
     pub frames: Vec<Frame>,
 }
@@ -52,7 +252,7 @@ pub struct NetworkFrames {
 /// the game (eg. a goal). The tick mark is placed before the event happens so there is a ramp-up
 /// time. For instance, a tickmark could be at frame 396 for a goal at frame 441. At 30 fps, this
 /// would be 1.5 seconds of ramp up time.
-#[derive(Serialize, PartialEq, Debug, Clone)]
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
 pub struct TickMark {
     pub description: String,
     pub frame: i32,
@@ -63,7 +263,7 @@ pub struct TickMark {
 /// match up with the frames decoded from the network data.
 ///
 /// [wikipedia]: https://en.wikipedia.org/wiki/Key_frame#Video_compression
-#[derive(Serialize, PartialEq, Debug, Clone, Copy)]
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Copy)]
 pub struct KeyFrame {
     pub time: f32,
     pub frame: i32,
@@ -78,6 +278,12 @@ pub struct KeyFrame {
 ///
 /// A property can be a number, string, or a more complex object such as an array containing
 /// additional properties.
+///
+/// The default serialization only writes the value, so the default deserialization is lossy: a
+/// `QWord` or `Name` is deserialized as a `Str`, as all three are written as a string. Use the
+/// tagged representation written by `HeaderProp::tagged` (eg: `{"Int":3}` or
+/// `{"QWord":"76561198101748375"}`) and read with `Tagged<HeaderProp>` to preserve the property
+/// type.
 #[derive(PartialEq, Debug, Clone)]
 pub enum HeaderProp {
     Array(Vec<Vec<(String, HeaderProp)>>),
@@ -95,6 +301,14 @@ pub enum HeaderProp {
         name: String,
         fields: Vec<(String, HeaderProp)>,
     },
+
+    /// A property of a type that boxcars does not recognize. The raw bytes of the value are
+    /// retained as they were found in the replay. Only produced when opted into via
+    /// `ParserBuilder::retain_unknown_properties`.
+    Unknown {
+        kind: String,
+        raw: Vec<u8>,
+    },
 }
 
 impl HeaderProp {
@@ -218,10 +432,25 @@ impl HeaderProp {
     pub fn is_byte(&self) -> bool {
         matches!(self, HeaderProp::Byte { .. })
     }
+
+    /// Returns a serializable view of the property that preserves the property type
+    /// ```
+    /// # use boxcars::HeaderProp;
+    /// let v = HeaderProp::QWord(10);
+    /// # use boxcars::Tagged;
+    /// let json = serde_json::to_string(&v.tagged()).unwrap();
+    /// assert_eq!(json, r#"{"QWord":"10"}"#);
+    ///
+    /// let Tagged(restored): Tagged<HeaderProp> = serde_json::from_str(&json).unwrap();
+    /// assert_eq!(restored, v);
+    /// ```
+    pub fn tagged(&self) -> Tagged<&HeaderProp> {
+        Tagged(self)
+    }
 }
 
 /// Debugging info stored in the replay if debugging is enabled.
-#[derive(Serialize, PartialEq, Debug, Clone)]
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
 pub struct DebugInfo {
     pub frame: i32,
     pub user: String,
@@ -229,7 +458,7 @@ pub struct DebugInfo {
 }
 
 /// A mapping between an object's name and its index. Largely redundant
-#[derive(Serialize, PartialEq, Debug, Clone)]
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
 pub struct ClassIndex {
     /// Should be equivalent to `Replay::objects(self.index)`
     pub class: String,
@@ -240,7 +469,7 @@ pub struct ClassIndex {
 
 /// A mapping between an object (that's an attribute)'s index and what its id will be when encoded
 /// in the network data
-#[derive(Serialize, PartialEq, Debug, Clone, Copy)]
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Copy)]
 pub struct CacheProp {
     /// The index that the object appears in the `Replay::objects`
     pub object_ind: i32,
@@ -251,7 +480,7 @@ pub struct CacheProp {
 }
 
 /// Contains useful information when decoding the network stream
-#[derive(Serialize, PartialEq, Debug, Clone)]
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
 pub struct ClassNetCache {
     /// The index that the object appears in the `Replay::objects`
     pub object_ind: i32,
@@ -286,6 +515,37 @@ where
     state.end()
 }
 
+/// Inverse of `pair_vec`: deserialize a map into a vector of key value tuples while preserving
+/// the order and any duplicate keys.
+fn de_pair_vec<'de, D, V>(deserializer: D) -> Result<Vec<(String, V)>, D::Error>
+where
+    D: Deserializer<'de>,
+    V: Deserialize<'de>,
+{
+    struct PairVisitor<V>(PhantomData<V>);
+
+    impl<'de, V> Visitor<'de> for PairVisitor<V>
+    where
+        V: Deserialize<'de>,
+    {
+        type Value = Vec<(String, V)>;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("a map of properties")
+        }
+
+        fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+            let mut result = Vec::with_capacity(map.size_hint().unwrap_or(0));
+            while let Some(entry) = map.next_entry()? {
+                result.push(entry);
+            }
+            Ok(result)
+        }
+    }
+
+    deserializer.deserialize_map(PairVisitor(PhantomData))
+}
+
 /// By default serde will generate a serialization method that writes out the enum as well as the
 /// enum value. Since header values are self describing in JSON, we do not need to serialize the
 /// enum type. This is slightly lossy as in the serialized format it will be ambiguous if a value
@@ -332,10 +592,268 @@ impl Serialize for HeaderProp {
             HeaderProp::Int(ref x) => serializer.serialize_i32(*x),
             HeaderProp::QWord(ref x) => serializer.collect_str(x),
             HeaderProp::Name(ref x) | HeaderProp::Str(ref x) => serializer.serialize_str(x),
+            HeaderProp::Unknown { ref kind, ref raw } => {
+                let mut unknown = serializer.serialize_struct("Unknown", 2)?;
+                unknown.serialize_field("kind", kind)?;
+                unknown.serialize_field("raw", raw)?;
+                unknown.end()
+            }
         }
     }
 }
 
+/// Inverse of the default serialization. Header properties that are serialized as a string (ie:
+/// `Name`, `QWord`, and `Str`) can't be told apart, so they are deserialized as a `Str`.
+impl<'de> Deserialize<'de> for HeaderProp {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_any(HeaderPropVisitor)
+    }
+}
+
+struct HeaderPropVisitor;
+
+impl<'de> Visitor<'de> for HeaderPropVisitor {
+    type Value = HeaderProp;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a header property")
+    }
+
+    fn visit_bool<E: de::Error>(self, v: bool) -> Result<Self::Value, E> {
+        Ok(HeaderProp::Bool(v))
+    }
+
+    fn visit_i64<E: de::Error>(self, v: i64) -> Result<Self::Value, E> {
+        i32::try_from(v)
+            .map(HeaderProp::Int)
+            .map_err(|_| E::invalid_value(de::Unexpected::Signed(v), &self))
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<Self::Value, E> {
+        i32::try_from(v)
+            .map(HeaderProp::Int)
+            .map_err(|_| E::invalid_value(de::Unexpected::Unsigned(v), &self))
+    }
+
+    fn visit_f64<E: de::Error>(self, v: f64) -> Result<Self::Value, E> {
+        Ok(HeaderProp::Float(v as f32))
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+        Ok(HeaderProp::Str(String::from(v)))
+    }
+
+    fn visit_string<E: de::Error>(self, v: String) -> Result<Self::Value, E> {
+        Ok(HeaderProp::Str(v))
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut result = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+        while let Some(Pairs(elem)) = seq.next_element()? {
+            result.push(elem);
+        }
+        Ok(HeaderProp::Array(result))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut name: Option<String> = None;
+        let mut fields: Option<Vec<(String, HeaderProp)>> = None;
+        let mut kind: Option<String> = None;
+        let mut value: Option<Option<String>> = None;
+        let mut raw: Option<Vec<u8>> = None;
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_str() {
+                "name" => name = Some(map.next_value()?),
+                "fields" => fields = Some(map.next_value::<Pairs>()?.0),
+                "kind" => kind = Some(map.next_value()?),
+                "value" => value = Some(map.next_value()?),
+                "raw" => raw = Some(map.next_value()?),
+                _ => {
+                    map.next_value::<de::IgnoredAny>()?;
+                }
+            }
+        }
+
+        match (name, fields, kind, value, raw) {
+            (Some(name), Some(fields), None, None, None) => Ok(HeaderProp::Struct { name, fields }),
+            (None, None, Some(kind), Some(value), None) => Ok(HeaderProp::Byte { kind, value }),
+            (None, None, Some(kind), None, Some(raw)) => Ok(HeaderProp::Unknown { kind, raw }),
+            _ => Err(de::Error::custom(
+                "expected the fields of a struct, byte, or unknown property",
+            )),
+        }
+    }
+}
+
+/// A map of header properties in the default representation
+#[derive(Deserialize)]
+struct Pairs(#[serde(deserialize_with = "de_pair_vec")] Vec<(String, HeaderProp)>);
+
+/// A header property or replay in the tagged representation. Header properties are written as
+/// `{"Type": value}` instead of only the value, so an `Int` stays an `Int` and isn't confused
+/// with a `QWord` or `Float`. A borrowed value is serialized (see `Replay::tagged`) and an owned
+/// value is deserialized.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Tagged<T>(pub T);
+
+impl Serialize for Tagged<&HeaderProp> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        TaggedHeaderProp::serialize(self.0, serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Tagged<HeaderProp> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        TaggedHeaderProp::deserialize(deserializer).map(Tagged)
+    }
+}
+
+impl Serialize for Tagged<&Replay> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        TaggedReplay::serialize(self.0, serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Tagged<Replay> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        TaggedReplay::deserialize(deserializer).map(Tagged)
+    }
+}
+
+// The tagged representation is derived from these shadows of `HeaderProp` and `Replay`, which
+// only differ in how header properties are written. Serde checks that a shadow has the same
+// fields and variants as the type it shadows, so they can't silently fall out of sync.
+#[derive(Serialize, Deserialize)]
+#[serde(remote = "HeaderProp", rename = "HeaderProp")]
+enum TaggedHeaderProp {
+    Array(#[serde(with = "tagged_pair_vecs")] Vec<Vec<(String, HeaderProp)>>),
+    Bool(bool),
+    Byte {
+        kind: String,
+        value: Option<String>,
+    },
+    Float(f32),
+    Int(i32),
+    Name(String),
+    #[serde(
+        serialize_with = "crate::serde_utils::display_it",
+        deserialize_with = "crate::serde_utils::parse_it"
+    )]
+    QWord(u64),
+    Str(String),
+    Struct {
+        name: String,
+        #[serde(with = "tagged_pairs")]
+        fields: Vec<(String, HeaderProp)>,
+    },
+    Unknown {
+        kind: String,
+        raw: Vec<u8>,
+    },
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(remote = "Replay", rename = "Replay")]
+struct TaggedReplay {
+    header_size: i32,
+    header_crc: u32,
+    major_version: i32,
+    minor_version: i32,
+    net_version: Option<i32>,
+    game_type: String,
+    #[serde(with = "tagged_pairs")]
+    properties: Vec<(String, HeaderProp)>,
+    content_size: i32,
+    content_crc: u32,
+    network_frames: Option<NetworkFrames>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    columnar_frames: Option<ColumnarFrames>,
+    levels: Vec<String>,
+    keyframes: Vec<KeyFrame>,
+    debug_info: Vec<DebugInfo>,
+    tick_marks: Vec<TickMark>,
+    packages: Vec<String>,
+    objects: Vec<String>,
+    names: Vec<String>,
+    class_indices: Vec<ClassIndex>,
+    net_cache: Vec<ClassNetCache>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    strings: Option<ReplayStrings>,
+}
+
+/// (De)serializes header properties as a map of tagged properties
+mod tagged_pairs {
+    use super::*;
+
+    pub fn serialize<S>(pairs: &[(String, HeaderProp)], serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut state = serializer.serialize_map(Some(pairs.len()))?;
+        for (key, val) in pairs {
+            state.serialize_entry(key, &Tagged(val))?;
+        }
+        state.end()
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Vec<(String, HeaderProp)>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let pairs: Vec<(String, Tagged<HeaderProp>)> = de_pair_vec(deserializer)?;
+        Ok(pairs
+            .into_iter()
+            .map(|(key, Tagged(val))| (key, val))
+            .collect())
+    }
+}
+
+/// (De)serializes the elements of an array property as maps of tagged properties
+mod tagged_pair_vecs {
+    use super::*;
+
+    #[derive(Deserialize)]
+    struct Elem(#[serde(deserialize_with = "tagged_pairs::deserialize")] Vec<(String, HeaderProp)>);
+
+    #[derive(Serialize)]
+    struct ElemRef<'a>(
+        #[serde(serialize_with = "tagged_pairs::serialize")] &'a [(String, HeaderProp)],
+    );
+
+    pub fn serialize<S>(
+        elems: &[Vec<(String, HeaderProp)>],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_seq(elems.iter().map(|x| ElemRef(x)))
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Vec<Vec<(String, HeaderProp)>>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let elems: Vec<Elem> = Vec::deserialize(deserializer)?;
+        Ok(elems.into_iter().map(|x| x.0).collect())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -369,6 +887,75 @@ mod tests {
         assert!(actual.contains("\"frame\":1738"));
     }
 
+    #[test]
+    fn tagged_header_prop_roundtrip() {
+        let prop = HeaderProp::Array(vec![vec![
+            (String::from("frame"), HeaderProp::Int(441)),
+            (String::from("frame"), HeaderProp::QWord(u64::MAX)),
+            (String::from("Float"), HeaderProp::Float(1.5)),
+            (
+                String::from("Platform"),
+                HeaderProp::Byte {
+                    kind: String::from("OnlinePlatform"),
+                    value: Some(String::from("OnlinePlatform_Steam")),
+                },
+            ),
+            (
+                String::from("PlayerID"),
+                HeaderProp::Struct {
+                    name: String::from("UniqueNetId"),
+                    fields: vec![(String::from("Bot"), HeaderProp::Bool(false))],
+                },
+            ),
+            (
+                String::from("Map"),
+                HeaderProp::Name(String::from("Park_P")),
+            ),
+        ]]);
+
+        let json = to_json(&prop.tagged());
+        assert!(json.contains(r#"{"QWord":"18446744073709551615"}"#));
+        let Tagged(restored): Tagged<HeaderProp> = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored, prop);
+    }
+
+    #[test]
+    fn default_header_prop_roundtrip() {
+        let prop = HeaderProp::Array(vec![vec![
+            (String::from("frame"), HeaderProp::Int(441)),
+            (String::from("frame"), HeaderProp::Float(2.0)),
+            (
+                String::from("Platform"),
+                HeaderProp::Byte {
+                    kind: String::from("OnlinePlatform"),
+                    value: None,
+                },
+            ),
+            (
+                String::from("PlayerID"),
+                HeaderProp::Struct {
+                    name: String::from("UniqueNetId"),
+                    fields: vec![(String::from("Bot"), HeaderProp::Bool(false))],
+                },
+            ),
+            (
+                String::from("Raw"),
+                HeaderProp::Unknown {
+                    kind: String::from("DelegateProperty"),
+                    raw: vec![1, 2],
+                },
+            ),
+            (String::from("Map"), HeaderProp::Str(String::from("Park_P"))),
+        ]]);
+
+        let restored: HeaderProp = serde_json::from_str(&to_json(&prop)).unwrap();
+        assert_eq!(restored, prop);
+
+        // Types that share a representation with a string are restored as a string
+        let restored: HeaderProp = serde_json::from_str(&to_json(&HeaderProp::QWord(10))).unwrap();
+        assert_eq!(restored, HeaderProp::Str(String::from("10")));
+    }
+
     #[test]
     fn serialize_header_bool() {
         assert_eq!(to_json(&HeaderProp::Bool(false)), "false");
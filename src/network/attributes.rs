@@ -1,5 +1,5 @@
 use crate::bits::RlBits;
-use crate::errors::AttributeError;
+use crate::errors::{AttributeError, LimitError};
 use crate::network::{
     ActorId, ObjectId, ObjectIndex, Quaternion, Rotation, Vector3f, VersionTriplet,
 };
@@ -430,6 +430,21 @@ pub struct Impulse {
     pub speed: f32,
 }
 
+impl Impulse {
+    /// The unit vector the impulse is applied in. The rotation packs the yaw in the upper 16 bits
+    /// and the pitch in the lower 16 bits, both in unreal rotation units (65536 per full turn).
+    pub(crate) fn direction(&self) -> Vector3f {
+        let unit = std::f32::consts::TAU / 65536.0;
+        let yaw = f32::from((self.compressed_rotation >> 16) as u16) * unit;
+        let pitch = f32::from(self.compressed_rotation as u16) * unit;
+        Vector3f {
+            x: pitch.cos() * yaw.cos(),
+            y: pitch.cos() * yaw.sin(),
+            z: pitch.sin(),
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ReplicatedBoost {
     pub grant_count: u8,
@@ -487,6 +502,7 @@ impl ProductValueDecoder {
         bits: &mut LittleEndianReader<'_>,
         obj_ind: ObjectId,
         buf: &mut [u8],
+        max_string_size: Option<usize>,
     ) -> Option<ProductValue> {
         if obj_ind == self.color_ind {
             if self.version >= VersionTriplet(868, 23, 8) {
@@ -510,7 +526,9 @@ impl ProductValueDecoder {
                     .map(ProductValue::OldPaint)
             }
         } else if obj_ind == self.title_ind {
-            decode_text(bits, buf).ok().map(ProductValue::Title)
+            decode_text(bits, buf, max_string_size)
+                .ok()
+                .map(ProductValue::Title)
         } else if obj_ind == self.special_edition_ind {
             bits.read_bits(31)
                 .map(|x| x as u32)
@@ -536,6 +554,9 @@ pub(crate) struct AttributeDecoder {
     pub(crate) version: VersionTriplet,
     pub(crate) product_decoder: ProductValueDecoder,
     pub(crate) is_rl_223: bool,
+
+    /// The largest string in bytes that will be decoded (see `Limits::max_string_size`)
+    pub(crate) max_string_size: Option<usize>,
 }
 
 impl AttributeDecoder {
@@ -898,7 +919,7 @@ impl AttributeDecoder {
         let unknown = bits
             .read_bit()
             .ok_or(AttributeError::NotEnoughDataFor("RepStatTitle"))?;
-        let name = decode_text(bits, buf)?;
+        let name = decode_text(bits, buf, self.max_string_size)?;
         let unknown2 = bits
             .read_bit()
             .ok_or(AttributeError::NotEnoughDataFor("RepStatTitle"))?;
@@ -1301,7 +1322,11 @@ impl AttributeDecoder {
         bits: &mut LittleEndianReader<'_>,
         buf: &mut [u8],
     ) -> Result<Attribute, AttributeError> {
-        Ok(Attribute::String(decode_text(bits, buf)?))
+        Ok(Attribute::String(decode_text(
+            bits,
+            buf,
+            self.max_string_size,
+        )?))
     }
 
     pub fn decode_unique_id(
@@ -1309,7 +1334,7 @@ impl AttributeDecoder {
         bits: &mut LittleEndianReader<'_>,
         buf: &mut [u8],
     ) -> Result<Attribute, AttributeError> {
-        decode_unique_id(bits, self.version.net_version(), buf)
+        decode_unique_id(bits, self.version.net_version(), buf, self.max_string_size)
             .map(Box::new)
             .map(Attribute::UniqueId)
     }
@@ -1321,9 +1346,9 @@ impl AttributeDecoder {
     ) -> Result<Attribute, AttributeError> {
         let component = "Reservation";
         let number = get_or!(bits.read_bits(3).map(|x| x as u32), component)?;
-        let unique = decode_unique_id(bits, self.version.net_version(), buf)?;
+        let unique = decode_unique_id(bits, self.version.net_version(), buf, self.max_string_size)?;
         let name = if unique.system_id != 0 {
-            Some(decode_text(bits, buf)?)
+            Some(decode_text(bits, buf, self.max_string_size)?)
         } else if unique.remote_id != RemoteId::SplitScreen(0) {
             // A split screen of zero seems to mean rocket host replays,
             // which use null terminated string up to an arbitrary length.
@@ -1334,6 +1359,8 @@ impl AttributeDecoder {
                     0 => break,
                     c => result.push(c as char),
                 }
+
+                check_text_size(result.len(), self.max_string_size)?;
             }
             Some(result)
         } else {
@@ -1372,6 +1399,7 @@ impl AttributeDecoder {
                     self.version.net_version(),
                     system_id,
                     buf,
+                    self.max_string_size,
                 )?;
                 Ok(Attribute::PartyLeader(Some(Box::new(id))))
             } else {
@@ -1388,11 +1416,11 @@ impl AttributeDecoder {
         buf: &mut [u8],
     ) -> Result<Attribute, AttributeError> {
         let component = "Private Match";
-        let mutators = decode_text(bits, buf)?;
+        let mutators = decode_text(bits, buf, self.max_string_size)?;
         let joinable_by = get_or!(bits.read_u32(), component)?;
         let max_players = get_or!(bits.read_u32(), component)?;
-        let game_name = decode_text(bits, buf)?;
-        let password = decode_text(bits, buf)?;
+        let game_name = decode_text(bits, buf, self.max_string_size)?;
+        let password = decode_text(bits, buf, self.max_string_size)?;
         let flag = get_or!(bits.read_bit(), component)?;
 
         Ok(Attribute::PrivateMatch(Box::new(PrivateMatchSettings {
@@ -1445,7 +1473,9 @@ impl AttributeDecoder {
     fn decode_product(&self, bits: &mut LittleEndianReader<'_>, buf: &mut [u8]) -> Option<Product> {
         let unknown = bits.read_bit()?;
         let obj_ind = ObjectId(bits.read_i32()?);
-        let val = self.product_decoder.decode(bits, obj_ind, buf)?;
+        let val = self
+            .product_decoder
+            .decode(bits, obj_ind, buf, self.max_string_size)?;
 
         Some(Product {
             unknown,
@@ -1510,9 +1540,22 @@ fn decode_explosion(bits: &mut LittleEndianReader<'_>, net_version: i32) -> Opti
     })
 }
 
+/// Returns an error if a string of the given size in bytes exceeds the limit
+fn check_text_size(bytes: usize, max_size: Option<usize>) -> Result<(), AttributeError> {
+    match max_size {
+        Some(limit) if bytes > limit => {
+            Err(AttributeError::LimitExceeded(LimitError::StringSize(limit)))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Decodes a length prefixed string. The size is checked against the limit before any of the
+/// string is read.
 fn decode_text(
     bits: &mut LittleEndianReader<'_>,
     buf: &mut [u8],
+    max_size: Option<usize>,
 ) -> Result<String, AttributeError> {
     use std::cmp::Ordering;
 
@@ -1528,6 +1571,7 @@ fn decode_text(
             let bytes = size
                 .checked_mul(-2)
                 .ok_or(AttributeError::TooBigString(size))? as usize;
+            check_text_size(bytes, max_size)?;
             if bytes > buf.len() || !bits.read_bytes(&mut buf[..bytes]) {
                 Err(AttributeError::TooBigString(size))
             } else if let Ok(x) = decode_utf16(&buf[..bytes]) {
@@ -1538,10 +1582,11 @@ fn decode_text(
         }
         Ordering::Greater => {
             let bytes = size as usize;
+            check_text_size(bytes, max_size)?;
             if bytes > buf.len() || !bits.read_bytes(&mut buf[..bytes]) {
                 Err(AttributeError::TooBigString(size))
             } else if let Ok(x) = decode_windows1252(&buf[..bytes]) {
-                Ok(x)
+                Ok(x.into_owned())
             } else {
                 Err(AttributeError::TooBigString(size))
             }
@@ -1619,11 +1664,12 @@ fn decode_unique_id(
     bits: &mut LittleEndianReader<'_>,
     net_version: i32,
     buf: &mut [u8],
+    max_string_size: Option<usize>,
 ) -> Result<UniqueId, AttributeError> {
     let system_id = bits
         .read_u8()
         .ok_or(AttributeError::NotEnoughDataFor("System id"))?;
-    decode_unique_id_with_system_id(bits, net_version, system_id, buf)
+    decode_unique_id_with_system_id(bits, net_version, system_id, buf, max_string_size)
 }
 
 fn decode_unique_id_with_system_id(
@@ -1631,6 +1677,7 @@ fn decode_unique_id_with_system_id(
     net_version: i32,
     system_id: u8,
     buf: &mut [u8],
+    max_string_size: Option<usize>,
 ) -> Result<UniqueId, AttributeError> {
     let remote_id = match system_id {
         0 => bits
@@ -1718,7 +1765,7 @@ fn decode_unique_id_with_system_id(
                 }))
             }
         }
-        11 => Ok(RemoteId::Epic(decode_text(bits, buf)?)),
+        11 => Ok(RemoteId::Epic(decode_text(bits, buf, max_string_size)?)),
         x => Err(AttributeError::UnrecognizedRemoteId(x)),
     }?;
 
@@ -1749,4 +1796,45 @@ mod tests {
                 <= ::std::mem::size_of::<RigidBody>() + ::std::mem::size_of::<usize>()
         );
     }
+
+    #[test]
+    fn test_text_limit_checked_before_read() {
+        let mut data = 6i32.to_le_bytes().to_vec();
+        data.extend_from_slice(b"hello\0");
+        let mut buf = [0u8; 1024];
+
+        let mut bits = LittleEndianReader::new(&data);
+        assert_eq!(
+            decode_text(&mut bits, &mut buf, Some(5)),
+            Err(AttributeError::LimitExceeded(LimitError::StringSize(5)))
+        );
+
+        // Only the size was read
+        assert_eq!(bits.bits_remaining(), Some(6 * 8));
+
+        let mut bits = LittleEndianReader::new(&data);
+        assert_eq!(
+            decode_text(&mut bits, &mut buf, Some(6)),
+            Ok(String::from("hello"))
+        );
+    }
+
+    #[test]
+    fn test_impulse_direction() {
+        let impulse = Impulse {
+            compressed_rotation: 0x4000_0000,
+            speed: 200000.0,
+        };
+        let direction = impulse.direction();
+        assert!(direction.x.abs() < 1e-6);
+        assert!((direction.y - 1.0).abs() < 1e-6);
+        assert_eq!(direction.z, 0.0);
+
+        let impulse = Impulse {
+            compressed_rotation: 0x0000_4000,
+            speed: 200000.0,
+        };
+        let direction = impulse.direction();
+        assert!((direction.z - 1.0).abs() < 1e-6);
+    }
 }
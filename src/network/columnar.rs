@@ -0,0 +1,630 @@
+//! A struct-of-arrays representation of the network frames.
+//!
+//! Each `Frame` owns three vectors and every update is a large `Attribute` enum, so a long replay
+//! is made up of hundreds of thousands of small allocations. `ColumnarFrames` stores the same
+//! data in a handful of contiguous vectors: the common attribute types each get their own typed
+//! column, so analysis of (for instance) every rigid body update is a linear scan over plain
+//! structs.
+//!
+//! `ParserBuilder::columnar_frames` decodes the network data straight into columns, so the
+//! frames are never materialized and peak memory stays close to the size of the columns.
+
+use super::frame_decoder::FrameSink;
+use crate::errors::ColumnarError;
+use crate::models::NetworkFrames;
+use crate::network::attributes::{ActiveActor, Attribute, RigidBody};
+use crate::network::models::*;
+use std::ops::Range;
+
+/// The number of variants in `ColumnKind`
+const COLUMNS: usize = 12;
+
+/// The updates of a single attribute type. Each row of the column is spread across its parallel
+/// vectors, so they are always the same length.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+pub struct Column<T> {
+    /// The index of the frame that the update occurred in
+    pub frame: Vec<u32>,
+    pub actor_id: Vec<ActorId>,
+    pub stream_id: Vec<StreamId>,
+    pub object_id: Vec<ObjectId>,
+    pub values: Vec<T>,
+}
+
+impl<T> Default for Column<T> {
+    fn default() -> Self {
+        Column {
+            frame: Vec::new(),
+            actor_id: Vec::new(),
+            stream_id: Vec::new(),
+            object_id: Vec::new(),
+            values: Vec::new(),
+        }
+    }
+}
+
+impl<T> Column<T> {
+    /// The number of updates in the column
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// The number of rows if all the parallel vectors have the same length
+    fn rows(&self) -> Option<usize> {
+        let len = self.values.len();
+        let same = self.frame.len() == len
+            && self.actor_id.len() == len
+            && self.stream_id.len() == len
+            && self.object_id.len() == len;
+        same.then_some(len)
+    }
+
+    fn push(&mut self, row: Row, value: T) {
+        self.frame.push(row.frame);
+        self.actor_id.push(row.actor_id);
+        self.stream_id.push(row.stream_id);
+        self.object_id.push(row.object_id);
+        self.values.push(value);
+    }
+
+    fn updated(&self, ind: usize, attribute: Attribute) -> UpdatedAttribute {
+        UpdatedAttribute {
+            actor_id: self.actor_id[ind],
+            stream_id: self.stream_id[ind],
+            object_id: self.object_id[ind],
+            attribute,
+        }
+    }
+}
+
+/// The values shared by all columns
+#[derive(Debug, Clone, Copy)]
+struct Row {
+    frame: u32,
+    actor_id: ActorId,
+    stream_id: StreamId,
+    object_id: ObjectId,
+}
+
+impl Row {
+    fn new(frame: u32, update: &UpdatedAttribute) -> Self {
+        Row {
+            frame,
+            actor_id: update.actor_id,
+            stream_id: update.stream_id,
+            object_id: update.object_id,
+        }
+    }
+}
+
+/// The column an update was stored in
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone, Copy, Hash)]
+pub enum ColumnKind {
+    RigidBody,
+    Boolean,
+    Byte,
+    Enum,
+    Int,
+    Int64,
+    QWord,
+    Float,
+    String,
+    ActiveActor,
+    Location,
+    Other,
+}
+
+/// Network frames stored as columns instead of a list of frames. Decode into columns with
+/// `ParserBuilder::columnar_frames`, or convert from `NetworkFrames` with `From`, and back with
+/// `ColumnarFrames::to_frames`.
+///
+/// ```
+/// # use boxcars::{ColumnarFrames, ParserBuilder};
+/// let data = include_bytes!("../../assets/replays/good/rumble.replay");
+/// let replay = ParserBuilder::new(&data[..]).must_parse_network_data().parse().unwrap();
+/// let columns = ColumnarFrames::from(replay.network_frames.unwrap());
+///
+/// // The location of every rigid body update without touching any other attribute
+/// let highest = columns
+///     .rigid_bodies
+///     .values
+///     .iter()
+///     .map(|x| x.location.z)
+///     .fold(f32::MIN, f32::max);
+/// assert!(highest > 0.0);
+/// ```
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Default)]
+pub struct ColumnarFrames {
+    /// The time in seconds of each frame
+    pub times: Vec<f32>,
+
+    /// The time difference of each frame from the previous frame
+    pub deltas: Vec<f32>,
+
+    /// The new actors of all frames, where the new actors of frame `i` are found at
+    /// `new_actor_offsets[i]..new_actor_offsets[i + 1]`
+    pub new_actors: Vec<NewActor>,
+    pub new_actor_offsets: Vec<u32>,
+
+    /// The deleted actors of all frames, indexed like `new_actors`
+    pub deleted_actors: Vec<ActorId>,
+    pub deleted_actor_offsets: Vec<u32>,
+
+    /// The column of each update in the order they were decoded, indexed like `new_actors`.
+    /// Updates of the same kind are stored in their column in the same order.
+    pub update_kinds: Vec<ColumnKind>,
+    pub update_offsets: Vec<u32>,
+
+    pub rigid_bodies: Column<RigidBody>,
+    pub booleans: Column<bool>,
+    pub bytes: Column<u8>,
+    pub enums: Column<u16>,
+    pub ints: Column<i32>,
+    pub int64s: Column<i64>,
+    pub qwords: Column<u64>,
+    pub floats: Column<f32>,
+    pub strings: Column<String>,
+    pub active_actors: Column<ActiveActor>,
+    pub locations: Column<Vector3f>,
+
+    /// Updates of every attribute type without a dedicated column
+    pub others: Column<Attribute>,
+}
+
+impl ColumnarFrames {
+    /// The number of frames
+    pub fn len(&self) -> usize {
+        self.times.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.times.is_empty()
+    }
+
+    /// The range of `new_actors` that were created in the given frame
+    pub fn new_actors_range(&self, frame: usize) -> Range<usize> {
+        offset_range(&self.new_actor_offsets, frame)
+    }
+
+    /// The range of `deleted_actors` that were deleted in the given frame
+    pub fn deleted_actors_range(&self, frame: usize) -> Range<usize> {
+        offset_range(&self.deleted_actor_offsets, frame)
+    }
+
+    /// The range of `update_kinds` that were updated in the given frame
+    pub fn updates_range(&self, frame: usize) -> Range<usize> {
+        offset_range(&self.update_offsets, frame)
+    }
+
+    /// Converts the frames into columns
+    pub fn from_frames(frames: &[Frame]) -> Self {
+        let mut result = ColumnarFrames::with_frames(frames.len());
+        for (i, frame) in frames.iter().enumerate() {
+            result.push_frame(i as u32, frame);
+            for update in &frame.updated_actors {
+                let row = Row::new(i as u32, update);
+                result.push_update(row, update.attribute.clone());
+            }
+        }
+        result.finish();
+        result
+    }
+
+    /// Checks that the offsets and columns are consistent with each other, which holds for
+    /// columns created by boxcars but not necessarily for those that were deserialized or
+    /// modified.
+    pub fn validate(&self) -> Result<(), ColumnarError> {
+        if self.times.len() != self.deltas.len() {
+            return Err(ColumnarError::FrameLength(
+                self.times.len(),
+                self.deltas.len(),
+            ));
+        }
+
+        let offsets = [
+            ("new actors", &self.new_actor_offsets, self.new_actors.len()),
+            (
+                "deleted actors",
+                &self.deleted_actor_offsets,
+                self.deleted_actors.len(),
+            ),
+            ("updates", &self.update_offsets, self.update_kinds.len()),
+        ];
+
+        for (name, offsets, len) in offsets {
+            // Columns without frames may omit the trailing offset
+            if offsets.is_empty() && self.is_empty() && len == 0 {
+                continue;
+            }
+
+            let valid = offsets.len() == self.len() + 1
+                && offsets.windows(2).all(|x| x[0] <= x[1])
+                && offsets.last().map(|&x| x as usize) == Some(len);
+            if !valid {
+                return Err(ColumnarError::InvalidOffsets(name));
+            }
+        }
+
+        let mut counts = [0usize; COLUMNS];
+        for &kind in &self.update_kinds {
+            counts[kind as usize] += 1;
+        }
+
+        let kinds = [
+            ColumnKind::RigidBody,
+            ColumnKind::Boolean,
+            ColumnKind::Byte,
+            ColumnKind::Enum,
+            ColumnKind::Int,
+            ColumnKind::Int64,
+            ColumnKind::QWord,
+            ColumnKind::Float,
+            ColumnKind::String,
+            ColumnKind::ActiveActor,
+            ColumnKind::Location,
+            ColumnKind::Other,
+        ];
+
+        for kind in kinds {
+            if self.column_rows(kind) != Some(counts[kind as usize]) {
+                return Err(ColumnarError::ColumnLength(kind));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Reconstructs the frames from the columns, after checking that they are consistent with
+    /// `validate`
+    pub fn to_frames(&self) -> Result<Vec<Frame>, ColumnarError> {
+        self.validate()?;
+        let mut cursors = [0usize; COLUMNS];
+        let mut frames = Vec::with_capacity(self.len());
+        for i in 0..self.len() {
+            let updated_actors = self.update_kinds[self.updates_range(i)]
+                .iter()
+                .map(|&kind| {
+                    let ind = cursors[kind as usize];
+                    cursors[kind as usize] += 1;
+                    self.update(kind, ind)
+                })
+                .collect();
+
+            frames.push(Frame {
+                time: self.times[i],
+                delta: self.deltas[i],
+                new_actors: self.new_actors[self.new_actors_range(i)].to_vec(),
+                deleted_actors: self.deleted_actors[self.deleted_actors_range(i)].to_vec(),
+                updated_actors,
+            });
+        }
+
+        Ok(frames)
+    }
+
+    fn column_rows(&self, kind: ColumnKind) -> Option<usize> {
+        match kind {
+            ColumnKind::RigidBody => self.rigid_bodies.rows(),
+            ColumnKind::Boolean => self.booleans.rows(),
+            ColumnKind::Byte => self.bytes.rows(),
+            ColumnKind::Enum => self.enums.rows(),
+            ColumnKind::Int => self.ints.rows(),
+            ColumnKind::Int64 => self.int64s.rows(),
+            ColumnKind::QWord => self.qwords.rows(),
+            ColumnKind::Float => self.floats.rows(),
+            ColumnKind::String => self.strings.rows(),
+            ColumnKind::ActiveActor => self.active_actors.rows(),
+            ColumnKind::Location => self.locations.rows(),
+            ColumnKind::Other => self.others.rows(),
+        }
+    }
+
+    fn update(&self, kind: ColumnKind, ind: usize) -> UpdatedAttribute {
+        match kind {
+            ColumnKind::RigidBody => self
+                .rigid_bodies
+                .updated(ind, Attribute::RigidBody(self.rigid_bodies.values[ind])),
+            ColumnKind::Boolean => self
+                .booleans
+                .updated(ind, Attribute::Boolean(self.booleans.values[ind])),
+            ColumnKind::Byte => self
+                .bytes
+                .updated(ind, Attribute::Byte(self.bytes.values[ind])),
+            ColumnKind::Enum => self
+                .enums
+                .updated(ind, Attribute::Enum(self.enums.values[ind])),
+            ColumnKind::Int => self
+                .ints
+                .updated(ind, Attribute::Int(self.ints.values[ind])),
+            ColumnKind::Int64 => self
+                .int64s
+                .updated(ind, Attribute::Int64(self.int64s.values[ind])),
+            ColumnKind::QWord => self
+                .qwords
+                .updated(ind, Attribute::QWord(self.qwords.values[ind])),
+            ColumnKind::Float => self
+                .floats
+                .updated(ind, Attribute::Float(self.floats.values[ind])),
+            ColumnKind::String => self
+                .strings
+                .updated(ind, Attribute::String(self.strings.values[ind].clone())),
+            ColumnKind::ActiveActor => self
+                .active_actors
+                .updated(ind, Attribute::ActiveActor(self.active_actors.values[ind])),
+            ColumnKind::Location => self
+                .locations
+                .updated(ind, Attribute::Location(self.locations.values[ind])),
+            ColumnKind::Other => self.others.updated(ind, self.others.values[ind].clone()),
+        }
+    }
+
+    pub(crate) fn with_frames(len: usize) -> Self {
+        ColumnarFrames {
+            times: Vec::with_capacity(len),
+            deltas: Vec::with_capacity(len),
+            new_actor_offsets: Vec::with_capacity(len + 1),
+            deleted_actor_offsets: Vec::with_capacity(len + 1),
+            update_offsets: Vec::with_capacity(len + 1),
+            ..ColumnarFrames::default()
+        }
+    }
+
+    fn push_frame(&mut self, ind: u32, frame: &Frame) {
+        debug_assert_eq!(ind as usize, self.times.len());
+        self.push_actors(
+            frame.time,
+            frame.delta,
+            &frame.new_actors,
+            &frame.deleted_actors,
+        );
+    }
+
+    fn push_actors(
+        &mut self,
+        time: f32,
+        delta: f32,
+        new_actors: &[NewActor],
+        deleted_actors: &[ActorId],
+    ) {
+        self.times.push(time);
+        self.deltas.push(delta);
+        self.new_actor_offsets.push(self.new_actors.len() as u32);
+        self.new_actors.extend_from_slice(new_actors);
+        self.deleted_actor_offsets
+            .push(self.deleted_actors.len() as u32);
+        self.deleted_actors.extend_from_slice(deleted_actors);
+        self.update_offsets.push(self.update_kinds.len() as u32);
+    }
+
+    fn push_update(&mut self, row: Row, attribute: Attribute) {
+        let kind = match attribute {
+            Attribute::RigidBody(x) => {
+                self.rigid_bodies.push(row, x);
+                ColumnKind::RigidBody
+            }
+            Attribute::Boolean(x) => {
+                self.booleans.push(row, x);
+                ColumnKind::Boolean
+            }
+            Attribute::Byte(x) => {
+                self.bytes.push(row, x);
+                ColumnKind::Byte
+            }
+            Attribute::Enum(x) => {
+                self.enums.push(row, x);
+                ColumnKind::Enum
+            }
+            Attribute::Int(x) => {
+                self.ints.push(row, x);
+                ColumnKind::Int
+            }
+            Attribute::Int64(x) => {
+                self.int64s.push(row, x);
+                ColumnKind::Int64
+            }
+            Attribute::QWord(x) => {
+                self.qwords.push(row, x);
+                ColumnKind::QWord
+            }
+            Attribute::Float(x) => {
+                self.floats.push(row, x);
+                ColumnKind::Float
+            }
+            Attribute::String(x) => {
+                self.strings.push(row, x);
+                ColumnKind::String
+            }
+            Attribute::ActiveActor(x) => {
+                self.active_actors.push(row, x);
+                ColumnKind::ActiveActor
+            }
+            Attribute::Location(x) => {
+                self.locations.push(row, x);
+                ColumnKind::Location
+            }
+            x => {
+                self.others.push(row, x);
+                ColumnKind::Other
+            }
+        };
+
+        self.update_kinds.push(kind);
+    }
+
+    /// Writes the trailing offsets so that the last frame has an end
+    pub(crate) fn finish(&mut self) {
+        self.new_actor_offsets.push(self.new_actors.len() as u32);
+        self.deleted_actor_offsets
+            .push(self.deleted_actors.len() as u32);
+        self.update_offsets.push(self.update_kinds.len() as u32);
+    }
+}
+
+impl From<NetworkFrames> for ColumnarFrames {
+    /// Converts the frames into columns, moving the attributes instead of cloning them
+    fn from(network: NetworkFrames) -> Self {
+        let mut result = ColumnarFrames::with_frames(network.frames.len());
+        for (i, mut frame) in network.frames.into_iter().enumerate() {
+            let updates = std::mem::take(&mut frame.updated_actors);
+            result.push_frame(i as u32, &frame);
+            for update in updates {
+                let row = Row::new(i as u32, &update);
+                result.push_update(row, update.attribute);
+            }
+        }
+        result.finish();
+        result
+    }
+}
+
+impl FrameSink for ColumnarFrames {
+    fn frames_len(&self) -> usize {
+        self.len()
+    }
+
+    fn first_time(&self) -> Option<f32> {
+        self.times.first().copied()
+    }
+
+    fn push(
+        &mut self,
+        time: f32,
+        delta: f32,
+        new_actors: &mut Vec<NewActor>,
+        deleted_actors: &mut Vec<ActorId>,
+        updated_actors: &mut Vec<UpdatedAttribute>,
+    ) {
+        let frame = self.len() as u32;
+        self.push_actors(time, delta, new_actors, deleted_actors);
+        new_actors.clear();
+        deleted_actors.clear();
+        for update in updated_actors.drain(..) {
+            let row = Row::new(frame, &update);
+            self.push_update(row, update.attribute);
+        }
+    }
+
+    fn frames(&self) -> Vec<Frame> {
+        let mut columns = self.clone();
+        columns.finish();
+        columns.to_frames().unwrap_or_default()
+    }
+}
+
+fn offset_range(offsets: &[u32], frame: usize) -> Range<usize> {
+    offsets[frame] as usize..offsets[frame + 1] as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn update(actor: i32, attribute: Attribute) -> UpdatedAttribute {
+        UpdatedAttribute {
+            actor_id: ActorId(actor),
+            stream_id: StreamId(actor + 10),
+            object_id: ObjectId(actor + 20),
+            attribute,
+        }
+    }
+
+    #[test]
+    fn columnar_roundtrip() {
+        let frames = vec![
+            Frame {
+                time: 1.0,
+                delta: 0.5,
+                new_actors: vec![NewActor {
+                    actor_id: ActorId(1),
+                    name_id: None,
+                    object_id: ObjectId(2),
+                    initial_trajectory: Trajectory {
+                        location: None,
+                        rotation: None,
+                    },
+                }],
+                deleted_actors: vec![],
+                updated_actors: vec![
+                    update(1, Attribute::Int(5)),
+                    update(2, Attribute::String(String::from("a"))),
+                    update(1, Attribute::Int(6)),
+                    update(3, Attribute::GameMode(1, 2)),
+                ],
+            },
+            Frame {
+                time: 1.5,
+                delta: 0.5,
+                new_actors: vec![],
+                deleted_actors: vec![ActorId(1)],
+                updated_actors: vec![update(2, Attribute::Boolean(true))],
+            },
+        ];
+
+        let columns = ColumnarFrames::from_frames(&frames);
+        assert_eq!(columns.len(), 2);
+        assert_eq!(columns.ints.values, vec![5, 6]);
+        assert_eq!(columns.ints.frame, vec![0, 0]);
+        assert_eq!(columns.booleans.frame, vec![1]);
+        assert_eq!(columns.others.values, vec![Attribute::GameMode(1, 2)]);
+        assert_eq!(columns.updates_range(0), 0..4);
+        assert_eq!(columns.deleted_actors_range(1), 0..1);
+        assert_eq!(columns.to_frames().unwrap(), frames);
+
+        let network = NetworkFrames {
+            frames: frames.clone(),
+        };
+        assert_eq!(ColumnarFrames::from(network), columns);
+    }
+
+    #[test]
+    fn columnar_empty() {
+        let columns = ColumnarFrames::from_frames(&[]);
+        assert!(columns.is_empty());
+        assert_eq!(columns.update_offsets, vec![0]);
+        assert!(columns.to_frames().unwrap().is_empty());
+        assert!(ColumnarFrames::default().to_frames().unwrap().is_empty());
+    }
+
+    #[test]
+    fn columnar_validate() {
+        let frames = vec![Frame {
+            time: 1.0,
+            delta: 0.5,
+            new_actors: vec![],
+            deleted_actors: vec![ActorId(1)],
+            updated_actors: vec![update(1, Attribute::Int(5))],
+        }];
+
+        let columns = ColumnarFrames::from_frames(&frames);
+        assert_eq!(columns.validate(), Ok(()));
+
+        let mut bad = columns.clone();
+        bad.deltas.clear();
+        assert_eq!(bad.to_frames(), Err(ColumnarError::FrameLength(1, 0)));
+
+        let mut bad = columns.clone();
+        bad.deleted_actor_offsets = vec![0, 2];
+        assert_eq!(
+            bad.to_frames(),
+            Err(ColumnarError::InvalidOffsets("deleted actors"))
+        );
+
+        let mut bad = columns.clone();
+        bad.ints.values.push(6);
+        assert_eq!(
+            bad.to_frames(),
+            Err(ColumnarError::ColumnLength(ColumnKind::Int))
+        );
+
+        let mut bad = columns;
+        bad.update_kinds[0] = ColumnKind::Float;
+        assert_eq!(
+            bad.to_frames(),
+            Err(ColumnarError::ColumnLength(ColumnKind::Int))
+        );
+    }
+}
@@ -1,14 +1,16 @@
 use bitter::{BitReader, LittleEndianReader};
-use fnv::FnvHashMap;
+use fnv::{FnvHashMap, FnvHashSet};
+use std::mem;
 
 use crate::bits::RlBits;
-use crate::errors::{AttributeError, FrameContext, FrameError, NetworkError};
-use crate::network::attributes::{AttributeDecoder, ProductValueDecoder};
+use crate::errors::{AttributeError, FrameContext, FrameError, LimitError, NetworkError};
+use crate::network::attributes::{Attribute, AttributeDecoder, ProductValueDecoder};
 use crate::network::models::{
-    ActorId, Frame, NewActor, ObjectId, SpawnTrajectory, StreamId, Trajectory, UpdatedAttribute,
+    ActorId, Frame, NewActor, ObjectId, Progress, SpawnTrajectory, StreamId, Trajectory,
+    UpdatedAttribute,
 };
-use crate::network::{CacheInfo, VersionTriplet};
-use crate::parser::ReplayBody;
+use crate::network::{CacheInfo, ProgressCallback, UpdateFilter, VersionTriplet};
+use crate::parser::{Limits, ReplayBody};
 
 #[derive(Debug)]
 pub(crate) struct RawSegmentedArray<T> {
@@ -114,23 +116,201 @@ where
     }
 }
 
+/// Where the decoded frames are stored
+pub(crate) trait FrameSink {
+    /// The number of frames stored
+    fn frames_len(&self) -> usize;
+
+    /// The time of the first frame stored
+    fn first_time(&self) -> Option<f32>;
+
+    /// Stores a frame made up of the contents of the buffers, leaving the buffers empty for the
+    /// next frame
+    fn push(
+        &mut self,
+        time: f32,
+        delta: f32,
+        new_actors: &mut Vec<NewActor>,
+        deleted_actors: &mut Vec<ActorId>,
+        updated_actors: &mut Vec<UpdatedAttribute>,
+    );
+
+    /// The frames stored so far, for the context of an error
+    fn frames(&self) -> Vec<Frame>;
+}
+
+impl FrameSink for Vec<Frame> {
+    fn frames_len(&self) -> usize {
+        self.len()
+    }
+
+    fn first_time(&self) -> Option<f32> {
+        self.first().map(|x| x.time)
+    }
+
+    fn push(
+        &mut self,
+        time: f32,
+        delta: f32,
+        new_actors: &mut Vec<NewActor>,
+        deleted_actors: &mut Vec<ActorId>,
+        updated_actors: &mut Vec<UpdatedAttribute>,
+    ) {
+        Vec::push(
+            self,
+            Frame {
+                time,
+                delta,
+                new_actors: take_buffer(new_actors),
+                deleted_actors: take_buffer(deleted_actors),
+                updated_actors: take_buffer(updated_actors),
+            },
+        );
+    }
+
+    fn frames(&self) -> Vec<Frame> {
+        self.clone()
+    }
+}
+
+/// The buffers that the actors of a frame are decoded into before the frame is stored in the sink.
+/// They can be kept between replays so that their allocations are reused (see `ParserContext`).
+#[derive(Debug, Clone, Default)]
+pub(crate) struct FrameBuffers {
+    pub new_actors: Vec<NewActor>,
+    pub deleted_actors: Vec<ActorId>,
+    pub updated_actors: Vec<UpdatedAttribute>,
+}
+
+impl FrameBuffers {
+    /// Empties the buffers while keeping their allocations
+    fn clear(&mut self) {
+        self.new_actors.clear();
+        self.deleted_actors.clear();
+        self.updated_actors.clear();
+    }
+}
+
+/// Moves the contents out of the buffer into a vector of their exact size. The buffer keeps its
+/// allocation so that it is reused for the next frame.
+#[allow(clippy::drain_collect)]
+fn take_buffer<T>(buffer: &mut Vec<T>) -> Vec<T> {
+    buffer.drain(..).collect()
+}
+
 pub(crate) struct FrameDecoder<'a, 'b: 'a> {
     pub frames_len: usize,
+
+    /// Stop decoding at the first frame that is more than this many seconds after the first frame
+    pub stop_after_time: Option<f32>,
+    pub limits: Limits,
+    pub progress: Option<&'a ProgressCallback>,
     pub product_decoder: ProductValueDecoder,
     pub max_channels: u32,
     pub channel_bits: u32,
     pub body: &'a ReplayBody<'b>,
-    pub spawns: &'a Vec<SpawnTrajectory>,
-    pub object_ind_attributes: Vec<Option<CacheInfo>>,
+    pub spawns: &'a [SpawnTrajectory],
+    pub object_ind_attributes: &'a [Option<CacheInfo>],
+    pub filter: UpdateFilter,
     pub version: VersionTriplet,
     pub is_lan: bool,
     pub is_rl_223: bool,
 }
 
+/// Tracks the resources used by the decoded frames so that the limits can be enforced as each
+/// actor and update is decoded, instead of after a whole frame is in memory
+#[derive(Debug, Default)]
+struct Usage {
+    limits: Limits,
+
+    /// The actors currently alive, only tracked when there is a limit
+    live_actors: Option<FnvHashSet<ActorId>>,
+    updates: usize,
+    output_bytes: usize,
+}
+
+impl Usage {
+    fn new(limits: Limits) -> Self {
+        Usage {
+            limits,
+            live_actors: limits.max_live_actors.map(|_| FnvHashSet::default()),
+            ..Usage::default()
+        }
+    }
+
+    fn add_frame(&mut self) -> Result<(), LimitError> {
+        self.add_bytes(mem::size_of::<Frame>())
+    }
+
+    fn spawn(&mut self, actor: ActorId) -> Result<(), LimitError> {
+        if let (Some(live), Some(limit)) = (self.live_actors.as_mut(), self.limits.max_live_actors)
+        {
+            live.insert(actor);
+            if live.len() > limit {
+                return Err(LimitError::LiveActors(limit));
+            }
+        }
+
+        self.add_bytes(mem::size_of::<NewActor>())
+    }
+
+    fn delete(&mut self, actor: ActorId) -> Result<(), LimitError> {
+        if let Some(live) = self.live_actors.as_mut() {
+            live.remove(&actor);
+        }
+
+        self.add_bytes(mem::size_of::<ActorId>())
+    }
+
+    /// Accounts for an update that will be stored
+    fn add_update(&mut self, attribute: &Attribute) -> Result<(), LimitError> {
+        self.updates += 1;
+        if let Some(limit) = self.limits.max_updates.filter(|x| self.updates > *x) {
+            return Err(LimitError::Updates(limit));
+        }
+
+        let text = match attribute {
+            Attribute::String(text) => text.len(),
+            _ => 0,
+        };
+
+        self.add_bytes(mem::size_of::<UpdatedAttribute>() + text)
+    }
+
+    fn add_bytes(&mut self, bytes: usize) -> Result<(), LimitError> {
+        self.output_bytes += bytes;
+        match self.limits.max_output_bytes {
+            Some(limit) if self.output_bytes > limit => Err(LimitError::OutputBytes(limit)),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Errors that stop the decoding of a frame
+#[derive(Debug)]
+enum DecodeError {
+    Frame(FrameError),
+    Limit(LimitError),
+}
+
+impl From<FrameError> for DecodeError {
+    fn from(error: FrameError) -> Self {
+        DecodeError::Frame(error)
+    }
+}
+
+impl From<LimitError> for DecodeError {
+    fn from(error: LimitError) -> Self {
+        DecodeError::Limit(error)
+    }
+}
+
+/// The outcome of decoding a frame. The actors of a decoded frame are left in the buffers.
 #[derive(Debug)]
 enum DecodedFrame {
     EndFrame,
-    Frame(Frame),
+    PastStopTime,
+    Frame { time: f32, delta: f32 },
 }
 
 impl FrameDecoder<'_, '_> {
@@ -182,13 +362,15 @@ impl FrameDecoder<'_, '_> {
         new_actors: &mut Vec<NewActor>,
         deleted_actors: &mut Vec<ActorId>,
         updated_actors: &mut Vec<UpdatedAttribute>,
-    ) -> Result<DecodedFrame, FrameError> {
+        usage: &mut Usage,
+        first_time: Option<f32>,
+    ) -> Result<DecodedFrame, DecodeError> {
         let time = bits
             .read_f32()
             .ok_or(FrameError::NotEnoughDataFor("Time"))?;
 
         if time < 0.0 || (time > 0.0 && time < 1e-10) {
-            return Err(FrameError::TimeOutOfRange { time });
+            return Err(DecodeError::Frame(FrameError::TimeOutOfRange { time }));
         }
 
         let delta = bits
@@ -196,20 +378,26 @@ impl FrameDecoder<'_, '_> {
             .ok_or(FrameError::NotEnoughDataFor("Delta"))?;
 
         if delta < 0.0 || (delta > 0.0 && delta < 1e-10) {
-            return Err(FrameError::DeltaOutOfRange { delta });
+            return Err(DecodeError::Frame(FrameError::DeltaOutOfRange { delta }));
         }
 
         if time == 0.0 && delta == 0.0 {
             return Ok(DecodedFrame::EndFrame);
         }
 
+        if self.is_past_stop_time(first_time, time) {
+            return Ok(DecodedFrame::PastStopTime);
+        }
+
+        usage.add_frame()?;
+
         while bits
             .read_bit()
             .ok_or(FrameError::NotEnoughDataFor("Actor data"))?
         {
             bits.refill_lookahead();
             if bits.lookahead_bits() < self.channel_bits + 1 + 1 {
-                return Err(FrameError::NotEnoughDataFor("Actor Id"));
+                return Err(DecodeError::Frame(FrameError::NotEnoughDataFor("Actor Id")));
             }
 
             let max = u64::from(self.max_channels);
@@ -238,6 +426,7 @@ impl FrameDecoder<'_, '_> {
                         })?;
 
                     actors.insert(actor.actor_id, (actor.object_id, cache_info));
+                    usage.spawn(actor.actor_id)?;
                     new_actors.push(actor);
                 } else {
                     // We'll be updating an existing actor with some attributes so we need
@@ -256,7 +445,9 @@ impl FrameDecoder<'_, '_> {
                         // values now
                         bits.refill_lookahead();
                         if bits.lookahead_bits() < cache_info.prop_id_bits + 1 {
-                            return Err(FrameError::NotEnoughDataFor("Prop id"));
+                            return Err(DecodeError::Frame(FrameError::NotEnoughDataFor(
+                                "Prop id",
+                            )));
                         }
 
                         let stream_id_raw = bits.peek_bits_max_computed(
@@ -279,103 +470,97 @@ impl FrameDecoder<'_, '_> {
 
                         let attribute = attr_decoder.decode(attr.attribute, bits, buf).map_err(
                             |e| match e {
-                                AttributeError::Unimplemented => FrameError::MissingAttribute {
-                                    actor: actor_id,
-                                    actor_object: *object_id,
-                                    attribute_stream: stream_id,
-                                },
-                                e => FrameError::AttributeError {
+                                AttributeError::LimitExceeded(e) => DecodeError::Limit(e),
+                                AttributeError::Unimplemented => {
+                                    DecodeError::Frame(FrameError::MissingAttribute {
+                                        actor: actor_id,
+                                        actor_object: *object_id,
+                                        attribute_stream: stream_id,
+                                    })
+                                }
+                                e => DecodeError::Frame(FrameError::AttributeError {
                                     actor: actor_id,
                                     actor_object: *object_id,
                                     attribute_stream: stream_id,
                                     error: e,
-                                },
+                                }),
                             },
                         )?;
 
-                        updated_actors.push(UpdatedAttribute {
-                            actor_id,
-                            stream_id,
-                            object_id: attr.object_id,
-                            attribute,
-                        });
+                        if self.filter.keeps(*object_id, attr.object_id) {
+                            usage.add_update(&attribute)?;
+                            updated_actors.push(UpdatedAttribute {
+                                actor_id,
+                                stream_id,
+                                object_id: attr.object_id,
+                                attribute,
+                            });
+                        }
                     }
                 }
             } else {
+                usage.delete(actor_id)?;
                 deleted_actors.push(actor_id);
                 actors.delete(actor_id);
             }
         }
 
-        let next_actors = if new_actors.is_empty() {
-            Vec::new()
-        } else {
-            std::mem::replace(
-                new_actors,
-                Vec::with_capacity(new_actors.len().next_power_of_two()),
-            )
-        };
-
-        let next_updated = if updated_actors.is_empty() {
-            Vec::new()
-        } else {
-            std::mem::replace(
-                updated_actors,
-                Vec::with_capacity(updated_actors.len().next_power_of_two()),
-            )
-        };
-
-        let next_deleted = if deleted_actors.is_empty() {
-            Vec::new()
-        } else {
-            std::mem::replace(
-                deleted_actors,
-                Vec::with_capacity(deleted_actors.len().next_power_of_two()),
-            )
-        };
+        Ok(DecodedFrame::Frame { time, delta })
+    }
 
-        Ok(DecodedFrame::Frame(Frame {
-            time,
-            delta,
-            new_actors: next_actors,
-            deleted_actors: next_deleted,
-            updated_actors: next_updated,
-        }))
+    fn is_past_stop_time(&self, first_time: Option<f32>, time: f32) -> bool {
+        match (self.stop_after_time, first_time) {
+            (Some(secs), Some(first)) => time - first > secs,
+            _ => false,
+        }
     }
 
-    pub fn decode_frames(&self) -> Result<Vec<Frame>, NetworkError> {
+    /// Decodes the frames into the sink, using the buffers for the actors of each frame
+    pub fn decode_frames<S: FrameSink>(
+        &self,
+        frames: &mut S,
+        buffers: &mut FrameBuffers,
+    ) -> Result<(), NetworkError> {
         let attr_decoder = AttributeDecoder {
             version: self.version,
             product_decoder: self.product_decoder,
             is_rl_223: self.is_rl_223,
+            max_string_size: self.limits.max_string_size,
         };
 
-        let mut frames: Vec<Frame> = Vec::with_capacity(self.frames_len);
         let mut actors = SegmentedArray::new(200);
         let mut bits = LittleEndianReader::new(self.body.network_data);
-        let mut new_actors = Vec::new();
-        let mut updated_actors = Vec::new();
-        let mut deleted_actors = Vec::new();
+        // A previous decode that failed may have left actors in the buffers
+        buffers.clear();
+        let FrameBuffers {
+            new_actors,
+            deleted_actors,
+            updated_actors,
+        } = buffers;
         let mut buf = [0u8; 1024];
+        let mut usage = Usage::new(self.limits);
 
-        while !bits.is_empty() && frames.len() < self.frames_len {
+        while !bits.is_empty() && frames.frames_len() < self.frames_len {
             let frame = self
                 .decode_frame(
                     &attr_decoder,
                     &mut bits,
                     &mut buf,
                     &mut actors,
-                    &mut new_actors,
-                    &mut deleted_actors,
-                    &mut updated_actors,
+                    new_actors,
+                    deleted_actors,
+                    updated_actors,
+                    &mut usage,
+                    frames.first_time(),
                 )
-                .map_err(|e| {
-                    NetworkError::FrameError(
+                .map_err(|e| match e {
+                    DecodeError::Limit(e) => NetworkError::LimitExceeded(e),
+                    DecodeError::Frame(e) => NetworkError::FrameError(
                         e,
                         Box::new(FrameContext {
-                            objects: self.body.objects.clone(),
-                            object_attributes: self.object_ind_attributes.clone(),
-                            frames: frames.clone(),
+                            objects: self.body.objects.iter().map(|x| x.to_string()).collect(),
+                            object_attributes: self.object_ind_attributes.to_vec(),
+                            frames: frames.frames(),
                             actors: actors
                                 .raw
                                 .array
@@ -396,12 +581,29 @@ impl FrameDecoder<'_, '_> {
                             new_actors: new_actors.clone(),
                             updated_actors: updated_actors.clone(),
                         }),
-                    )
+                    ),
                 })?;
 
             match frame {
                 DecodedFrame::EndFrame => break,
-                DecodedFrame::Frame(frame) => frames.push(frame),
+                DecodedFrame::PastStopTime => return Ok(()),
+                DecodedFrame::Frame { time, delta } => {
+                    frames.push(time, delta, new_actors, deleted_actors, updated_actors);
+
+                    if let Some(progress) = self.progress {
+                        let bits_consumed =
+                            self.body.network_data.len() * 8 - bits.bits_remaining().unwrap_or(0);
+                        let flow = progress.report(Progress {
+                            frames: frames.frames_len(),
+                            total_frames: self.frames_len,
+                            bits_consumed,
+                        });
+
+                        if flow.is_break() {
+                            return Err(NetworkError::Cancelled);
+                        }
+                    }
+                }
             }
         }
 
@@ -410,6 +612,61 @@ impl FrameDecoder<'_, '_> {
             let _ = bits.read_u32();
         }
 
-        Ok(frames)
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn take_buffer_keeps_allocation() {
+        let mut buffer = vec![ActorId(1), ActorId(2)];
+        let ptr = buffer.as_ptr();
+        assert_eq!(take_buffer(&mut buffer), vec![ActorId(1), ActorId(2)]);
+        assert!(buffer.is_empty());
+        assert_eq!(buffer.as_ptr(), ptr);
+    }
+
+    #[test]
+    fn usage_checks_each_update() {
+        let mut usage = Usage::new(Limits {
+            max_updates: Some(2),
+            ..Limits::default()
+        });
+
+        assert_eq!(usage.add_update(&Attribute::Int(1)), Ok(()));
+        assert_eq!(usage.add_update(&Attribute::Int(2)), Ok(()));
+        assert_eq!(
+            usage.add_update(&Attribute::Int(3)),
+            Err(LimitError::Updates(2))
+        );
+    }
+
+    #[test]
+    fn usage_checks_each_actor() {
+        let mut usage = Usage::new(Limits {
+            max_live_actors: Some(1),
+            ..Limits::default()
+        });
+
+        assert_eq!(usage.spawn(ActorId(1)), Ok(()));
+        assert_eq!(usage.spawn(ActorId(1)), Ok(()));
+        assert_eq!(usage.delete(ActorId(1)), Ok(()));
+        assert_eq!(usage.spawn(ActorId(2)), Ok(()));
+        assert_eq!(usage.spawn(ActorId(3)), Err(LimitError::LiveActors(1)));
+    }
+
+    #[test]
+    fn usage_counts_string_bytes() {
+        let limit = mem::size_of::<UpdatedAttribute>() + 4;
+        let mut usage = Usage::new(Limits {
+            max_output_bytes: Some(limit),
+            ..Limits::default()
+        });
+
+        let text = Attribute::String(String::from("abcde"));
+        assert_eq!(usage.add_update(&text), Err(LimitError::OutputBytes(limit)));
     }
 }
@@ -1,21 +1,28 @@
 pub(crate) use self::attributes::*;
+pub use self::columnar::*;
 pub use self::models::*;
 pub(crate) use self::object_index::*;
 
 pub mod attributes;
+mod columnar;
 mod frame_decoder;
 mod models;
 mod object_index;
 
-use crate::data::{ATTRIBUTES, SPAWN_STATS};
-use crate::errors::NetworkError;
+use crate::data::{ATTRIBUTES, PARENT_CLASSES, SPAWN_STATS};
+use crate::errors::{LimitError, NetworkError};
 use crate::header::Header;
 use crate::models::*;
 use crate::network::frame_decoder::FrameDecoder;
-use crate::parser::ReplayBody;
+use crate::parser::{Limits, ReplayBody};
 use fnv::FnvHashMap;
+pub(crate) use frame_decoder::FrameBuffers;
 use frame_decoder::SegmentedArray;
+use std::borrow::Cow;
 use std::cmp;
+use std::fmt;
+use std::ops::ControlFlow;
+use std::sync::Arc;
 
 #[derive(PartialEq, Debug, Clone)]
 pub(crate) struct CacheInfo {
@@ -30,7 +37,7 @@ pub(crate) struct ObjectAttribute {
     pub(crate) object_id: ObjectId,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub(crate) struct VersionTriplet(pub i32, pub i32, pub i32);
 
 impl VersionTriplet {
@@ -39,122 +46,269 @@ impl VersionTriplet {
     }
 }
 
-pub(crate) fn parse(header: &Header, body: &ReplayBody) -> Result<NetworkFrames, NetworkError> {
-    let version = VersionTriplet(
-        header.major_version,
-        header.minor_version,
-        header.net_version.unwrap_or(0),
-    );
+/// Options from the `ParserBuilder` that affect how the network data is decoded
+#[derive(Debug, Clone, PartialEq, Default)]
+pub(crate) struct NetworkOptions {
+    pub(crate) only_attributes: Option<Vec<String>>,
+    pub(crate) only_classes: Option<Vec<String>>,
+    pub(crate) stop_after_time: Option<f32>,
+    pub(crate) stop_after_frames: Option<usize>,
+    pub(crate) limits: Limits,
+    pub(crate) progress: Option<ProgressCallback>,
+    pub(crate) columnar: bool,
+}
+
+/// The decoded network data, stored as frames or as columns depending on `NetworkOptions`
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum DecodedNetwork {
+    Frames(NetworkFrames),
+    Columnar(Box<ColumnarFrames>),
+}
 
-    let object_index = object_index::ObjectIndex::new(&body.objects);
+/// A shared callback that is invoked with the decoding progress and can cancel decoding
+#[derive(Clone)]
+pub(crate) struct ProgressCallback(
+    pub(crate) Arc<dyn Fn(Progress) -> ControlFlow<()> + Send + Sync>,
+);
 
-    // Create a parallel vector where we lookup how to decode an object's initial trajectory
-    // when they spawn as a new actor
-    let mut spawns: Vec<Option<SpawnTrajectory>> = vec![None; body.objects.len()];
-    for (object_name, spawn) in SPAWN_STATS.iter() {
-        let Some(id) = object_index.by_name(object_name) else {
-            continue;
+impl ProgressCallback {
+    pub(crate) fn report(&self, progress: Progress) -> ControlFlow<()> {
+        (self.0)(progress)
+    }
+}
+
+impl fmt::Debug for ProgressCallback {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ProgressCallback")
+    }
+}
+
+impl PartialEq for ProgressCallback {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+/// Which attribute updates are stored, resolved against a replay's objects. Each list is indexed
+/// by object id and absent when every update is kept.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub(crate) struct UpdateFilter {
+    attributes: Option<Vec<bool>>,
+    classes: Option<Vec<bool>>,
+}
+
+impl UpdateFilter {
+    pub(crate) fn new(options: &NetworkOptions, objects: &[Cow<'_, str>]) -> Self {
+        let attributes = options.only_attributes.as_ref().map(|names| {
+            objects
+                .iter()
+                .map(|x| names.iter().any(|n| n == x))
+                .collect()
+        });
+
+        let classes = options
+            .only_classes
+            .as_ref()
+            .map(|names| objects.iter().map(|x| is_instance_of(x, names)).collect());
+
+        UpdateFilter {
+            attributes,
+            classes,
+        }
+    }
+
+    /// Returns true if an update to the attribute of an actor should be stored
+    pub(crate) fn keeps(&self, actor_object: ObjectId, attribute: ObjectId) -> bool {
+        // `Option::is_none_or` requires a newer rust than the minimum supported version
+        #[allow(clippy::unnecessary_map_or)]
+        let pass = |filter: &Option<Vec<bool>>, id: ObjectId| {
+            filter
+                .as_ref()
+                .map_or(true, |x| x.get(usize::from(id)).copied().unwrap_or(false))
         };
 
-        spawns[id.0 as usize] = Some(*spawn);
+        pass(&self.attributes, attribute) && pass(&self.classes, actor_object)
+    }
+}
+
+/// Returns true if the object or any of its ancestors is one of the given classes
+pub(crate) fn is_instance_of<S: AsRef<str>>(object: &str, classes: &[S]) -> bool {
+    let mut name = object;
+    loop {
+        if classes.iter().any(|x| x.as_ref() == name) {
+            return true;
+        }
+
+        match PARENT_CLASSES.get(normalize_object(name)) {
+            Some(parent) => name = parent,
+            None => return false,
+        }
     }
+}
+
+/// The lookup tables needed to decode the network data. They are derived solely from the
+/// replay's version, objects, and net cache, so they can be shared between replays from the same
+/// patch.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct NetworkSetup {
+    spawns: Vec<SpawnTrajectory>,
+    object_ind_attributes: Vec<Option<CacheInfo>>,
+    product_decoder: ProductValueDecoder,
+}
 
-    let mut parent_stack = Vec::with_capacity(body.objects.len());
-    for name in &body.objects {
-        let mut result = SpawnTrajectory::None;
-        for object in object_index.hierarchy(name) {
-            match spawns[object.0 as usize] {
-                Some(spawn) => {
-                    result = spawn;
-                    break;
+impl NetworkSetup {
+    pub(crate) fn new(
+        version: VersionTriplet,
+        objects: &[Cow<'_, str>],
+        net_cache: &[ClassNetCache],
+    ) -> Result<Self, NetworkError> {
+        let object_index = object_index::ObjectIndex::new(objects);
+
+        // Create a parallel vector where we lookup how to decode an object's initial trajectory
+        // when they spawn as a new actor
+        let mut spawns: Vec<Option<SpawnTrajectory>> = vec![None; objects.len()];
+        for (object_name, spawn) in SPAWN_STATS.iter() {
+            let Some(id) = object_index.by_name(object_name) else {
+                continue;
+            };
+
+            spawns[id.0 as usize] = Some(*spawn);
+        }
+
+        let mut parent_stack = Vec::with_capacity(objects.len());
+        for name in objects {
+            let mut result = SpawnTrajectory::None;
+            for object in object_index.hierarchy(name) {
+                match spawns[object.0 as usize] {
+                    Some(spawn) => {
+                        result = spawn;
+                        break;
+                    }
+                    None => parent_stack.push(object),
                 }
-                None => parent_stack.push(object),
+            }
+
+            for ind in parent_stack.drain(..) {
+                spawns[ind.0 as usize] = Some(result)
             }
         }
 
-        for ind in parent_stack.drain(..) {
-            spawns[ind.0 as usize] = Some(result)
+        let mut net_properties: FnvHashMap<ObjectId, Vec<(_, _)>> =
+            FnvHashMap::with_capacity_and_hasher(net_cache.len(), Default::default());
+        for cache in net_cache {
+            let key = ObjectId(cache.object_ind);
+            let properties = net_properties.entry(key).or_default();
+            properties.reserve(cache.properties.len());
+
+            for x in &cache.properties {
+                let attr = objects
+                    .get(x.object_ind as usize)
+                    .map(|x| {
+                        ATTRIBUTES
+                            .get(x.as_ref())
+                            .copied()
+                            .unwrap_or(AttributeTag::NotImplemented)
+                    })
+                    .ok_or(NetworkError::StreamTooLargeIndex(x.stream_id, x.object_ind))?;
+
+                properties.push((
+                    StreamId(x.stream_id),
+                    ObjectAttribute {
+                        attribute: attr,
+                        object_id: ObjectId(x.object_ind),
+                    },
+                ));
+            }
         }
-    }
 
-    let mut net_properties: FnvHashMap<ObjectId, Vec<(_, _)>> =
-        FnvHashMap::with_capacity_and_hasher(body.net_cache.len(), Default::default());
-    for cache in &body.net_cache {
-        let key = ObjectId(cache.object_ind);
-        let properties = net_properties.entry(key).or_default();
-        properties.reserve(cache.properties.len());
-
-        for x in &cache.properties {
-            let attr = body
-                .objects
-                .get(x.object_ind as usize)
-                .map(|x| {
-                    ATTRIBUTES
-                        .get(x)
-                        .copied()
-                        .unwrap_or(AttributeTag::NotImplemented)
-                })
-                .ok_or(NetworkError::StreamTooLargeIndex(x.stream_id, x.object_ind))?;
-
-            properties.push((
-                StreamId(x.stream_id),
-                ObjectAttribute {
-                    attribute: attr,
-                    object_id: ObjectId(x.object_ind),
-                },
-            ));
+        let mut object_ind_attrs: FnvHashMap<ObjectId, FnvHashMap<StreamId, ObjectAttribute>> =
+            FnvHashMap::with_capacity_and_hasher(objects.len(), Default::default());
+
+        let mut acc_attrs = Vec::with_capacity(32);
+        for name in objects {
+            net_traversal(
+                name.as_ref(),
+                &mut acc_attrs,
+                &mut parent_stack,
+                &net_properties,
+                &object_index,
+                &mut object_ind_attrs,
+            );
         }
-    }
 
-    let mut object_ind_attrs: FnvHashMap<ObjectId, FnvHashMap<StreamId, ObjectAttribute>> =
-        FnvHashMap::with_capacity_and_hasher(body.objects.len(), Default::default());
-
-    let mut acc_attrs = Vec::with_capacity(32);
-    for name in &body.objects {
-        net_traversal(
-            name.as_str(),
-            &mut acc_attrs,
-            &mut parent_stack,
-            &net_properties,
-            &object_index,
-            &mut object_ind_attrs,
-        );
-    }
+        let mut object_ind_attributes: Vec<Option<CacheInfo>> = Vec::with_capacity(objects.len());
+        object_ind_attributes.resize_with(objects.len(), || None);
+
+        let iter = object_ind_attrs.into_iter().map(|(obj_id, attrs)| {
+            let id = obj_id;
+            let max = attrs
+                .keys()
+                .map(|&x| i32::from(x))
+                .max()
+                .unwrap_or(2)
+                .saturating_add(1);
+            let mut attributes = SegmentedArray::new(64);
+            for (k, v) in attrs {
+                attributes.insert(k, v);
+            }
+
+            let max_bit_width = crate::bits::bit_width(max as u64);
+            Ok((
+                id,
+                CacheInfo {
+                    max_prop_id: max as u32,
+                    prop_id_bits: cmp::max(max_bit_width, 1) - 1,
+                    attributes,
+                },
+            ))
+        });
 
-    let mut object_ind_attributes: Vec<Option<CacheInfo>> = Vec::with_capacity(body.objects.len());
-    object_ind_attributes.resize_with(body.objects.len(), || None);
-
-    let iter = object_ind_attrs.into_iter().map(|(obj_id, attrs)| {
-        let id = obj_id;
-        let max = attrs
-            .keys()
-            .map(|&x| i32::from(x))
-            .max()
-            .unwrap_or(2)
-            .saturating_add(1);
-        let mut attributes = SegmentedArray::new(64);
-        for (k, v) in attrs {
-            attributes.insert(k, v);
+        for x in iter {
+            let (object, cache) = x?;
+            object_ind_attributes[object.0 as usize] = Some(cache);
         }
 
-        let max_bit_width = crate::bits::bit_width(max as u64);
-        Ok((
-            id,
-            CacheInfo {
-                max_prop_id: max as u32,
-                prop_id_bits: cmp::max(max_bit_width, 1) - 1,
-                attributes,
-            },
-        ))
-    });
-
-    for x in iter {
-        let (object, cache) = x?;
-        object_ind_attributes[object.0 as usize] = Some(cache);
+        let product_decoder = ProductValueDecoder::create(version, &object_index);
+        let spawns = spawns
+            .into_iter()
+            .map(|x| x.unwrap_or(SpawnTrajectory::None))
+            .collect();
+
+        Ok(NetworkSetup {
+            spawns,
+            object_ind_attributes,
+            product_decoder,
+        })
     }
+}
+
+pub(crate) fn version(header: &Header) -> VersionTriplet {
+    VersionTriplet(
+        header.major_version,
+        header.minor_version,
+        header.net_version.unwrap_or(0),
+    )
+}
 
-    let product_decoder = ProductValueDecoder::create(version, &object_index);
+pub(crate) fn parse(
+    header: &Header,
+    body: &ReplayBody,
+    options: &NetworkOptions,
+) -> Result<DecodedNetwork, NetworkError> {
+    let setup = NetworkSetup::new(version(header), &body.objects, &body.net_cache)?;
+    let mut buffers = FrameBuffers::default();
+    parse_with_setup(header, body, &setup, &mut buffers, options)
+}
 
+/// Decodes the network data with lookup tables previously created for the replay's version,
+/// objects, and net cache. The actors of each frame are decoded into the given buffers.
+pub(crate) fn parse_with_setup(
+    header: &Header,
+    body: &ReplayBody,
+    setup: &NetworkSetup,
+    buffers: &mut FrameBuffers,
+    options: &NetworkOptions,
+) -> Result<DecodedNetwork, NetworkError> {
     // 1023 stolen from rattletrap
     let max_channels = header.max_channels().unwrap_or(1023) as u32;
     let channel_width = crate::bits::bit_width(u64::from(max_channels)).saturating_sub(1);
@@ -168,28 +322,49 @@ pub(crate) fn parse(header: &Header, body: &ReplayBody) -> Result<NetworkFrames,
             return Err(NetworkError::TooManyFrames(frame_len));
         }
 
-        let spawns = spawns
-            .drain(..)
-            .map(|x| x.unwrap_or(SpawnTrajectory::None))
-            .collect();
+        let frames_len = options
+            .stop_after_frames
+            .map_or(frame_len as usize, |x| x.min(frame_len as usize));
+
+        if let Some(limit) = options.limits.max_frames {
+            if frames_len > limit {
+                return Err(NetworkError::LimitExceeded(LimitError::Frames(limit)));
+            }
+        }
 
         let frame_decoder = FrameDecoder {
-            frames_len: frame_len as usize,
-            product_decoder,
+            frames_len,
+            stop_after_time: options.stop_after_time,
+            limits: options.limits,
+            progress: options.progress.as_ref(),
+            product_decoder: setup.product_decoder,
             max_channels,
             channel_bits,
             body,
-            spawns: &spawns,
-            object_ind_attributes,
-            version,
+            spawns: &setup.spawns,
+            object_ind_attributes: &setup.object_ind_attributes,
+            filter: UpdateFilter::new(options, &body.objects),
+            version: version(header),
             is_lan,
             is_rl_223,
         };
-        Ok(NetworkFrames {
-            frames: frame_decoder.decode_frames()?,
-        })
+
+        if options.columnar {
+            let mut columns = ColumnarFrames::with_frames(frames_len);
+            frame_decoder.decode_frames(&mut columns, buffers)?;
+            columns.finish();
+            Ok(DecodedNetwork::Columnar(Box::new(columns)))
+        } else {
+            let mut frames = Vec::with_capacity(frames_len);
+            frame_decoder.decode_frames(&mut frames, buffers)?;
+            Ok(DecodedNetwork::Frames(NetworkFrames { frames }))
+        }
+    } else if options.columnar {
+        let mut columns = ColumnarFrames::default();
+        columns.finish();
+        Ok(DecodedNetwork::Columnar(Box::new(columns)))
     } else {
-        Ok(NetworkFrames { frames: Vec::new() })
+        Ok(DecodedNetwork::Frames(NetworkFrames { frames: Vec::new() }))
     }
 }
 
@@ -239,4 +414,28 @@ mod tests {
         assert!(version > VersionTriplet(18, 26, 1));
         assert!(version > VersionTriplet(18, 27, 0));
     }
+
+    #[test]
+    fn test_update_filter() {
+        let objects = [
+            Cow::Borrowed("Archetypes.Car.Car_Default"),
+            Cow::Borrowed("Archetypes.Ball.Ball_Default"),
+            Cow::Borrowed("TAGame.RBActor_TA:ReplicatedRBState"),
+        ];
+
+        let options = NetworkOptions {
+            only_attributes: Some(vec![String::from("TAGame.RBActor_TA:ReplicatedRBState")]),
+            only_classes: Some(vec![String::from("TAGame.Vehicle_TA")]),
+            ..NetworkOptions::default()
+        };
+
+        let filter = UpdateFilter::new(&options, &objects);
+        assert!(filter.keeps(ObjectId(0), ObjectId(2)));
+        assert!(!filter.keeps(ObjectId(1), ObjectId(2)));
+        assert!(!filter.keeps(ObjectId(0), ObjectId(1)));
+        assert!(!filter.keeps(ObjectId(0), ObjectId(10)));
+
+        let filter = UpdateFilter::new(&NetworkOptions::default(), &objects);
+        assert!(filter.keeps(ObjectId(1), ObjectId(10)));
+    }
 }
@@ -232,6 +232,21 @@ pub struct UpdatedAttribute {
     pub attribute: Attribute,
 }
 
+/// The progress of decoding the network data, reported after each frame
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Progress {
+    /// The number of frames decoded so far
+    pub frames: usize,
+
+    /// The number of frames that decoding will stop at: the number of frames in the replay
+    /// according to the header, or fewer when capped by `ParserBuilder::stop_after_frames`.
+    /// Decoding may stop earlier due to `ParserBuilder::stop_after_time`.
+    pub total_frames: usize,
+
+    /// The number of bits of the network data consumed so far
+    pub bits_consumed: usize,
+}
+
 /// Contains the time and any new information that occurred during a frame
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Frame {
@@ -1,5 +1,6 @@
 use crate::{data::PARENT_CLASSES, ObjectId};
 use fnv::FnvHashMap;
+use std::borrow::Cow;
 
 use super::normalize_object;
 
@@ -12,12 +13,12 @@ pub(crate) struct ObjectIndex<'a> {
 }
 
 impl<'a> ObjectIndex<'a> {
-    pub(crate) fn new(objects: &'a [String]) -> Self {
+    pub(crate) fn new(objects: &'a [Cow<'_, str>]) -> Self {
         let mut name_index: FnvHashMap<&str, ObjectId> = FnvHashMap::default();
 
         for (i, name) in objects.iter().enumerate() {
             let val = ObjectId(i as i32);
-            name_index.entry(name.as_str()).or_insert(val);
+            name_index.entry(name.as_ref()).or_insert(val);
         }
 
         Self { name_index }
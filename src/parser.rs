@@ -215,12 +215,17 @@
 //! off. This means that the actor is deleted and that the given actor id can be
 //! recycled.
 
+use crate::context::ParserContext;
 use crate::core_parser::CoreParser;
 use crate::crc::calc_crc;
 use crate::errors::{NetworkError, ParseError};
 use crate::header::{self, Header};
 use crate::models::*;
-use crate::network;
+use crate::network::{self, DecodedNetwork, NetworkOptions, Progress, ProgressCallback};
+use crate::text::{self, ReplayStrings, RlString};
+use std::borrow::Cow;
+use std::ops::ControlFlow;
+use std::sync::Arc;
 
 /// Determines under what circumstances the parser should perform the crc check for replay
 /// corruption. Since the crc check is the most time consuming part when parsing the header,
@@ -258,6 +263,44 @@ pub enum NetworkParse {
     IgnoreOnError,
 }
 
+/// Caps on the resources used to parse a replay, for when the replay comes from an untrusted
+/// source. Each limit is unbounded when `None`. Exceeding a limit results in a
+/// `LimitError`, though a limit exceeded while decoding the network data is subject to the
+/// `NetworkParse` option like any other network error.
+///
+/// ```
+/// # use boxcars::{Limits, ParserBuilder};
+/// let data = include_bytes!("../assets/replays/good/rumble.replay");
+/// let limits = Limits {
+///     max_updates: Some(100),
+///     ..Limits::default()
+/// };
+///
+/// let replay = ParserBuilder::new(&data[..])
+///     .must_parse_network_data()
+///     .with_limits(limits)
+///     .parse();
+/// assert!(replay.is_err());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Limits {
+    /// The maximum number of network frames
+    pub max_frames: Option<usize>,
+
+    /// The maximum number of actors alive at any point while decoding
+    pub max_live_actors: Option<usize>,
+
+    /// The maximum number of attribute updates stored across all frames
+    pub max_updates: Option<usize>,
+
+    /// The maximum size in bytes of any string in the header, body, or network data
+    pub max_string_size: Option<usize>,
+
+    /// The maximum estimated size in bytes of the decoded network frames. Heap allocations
+    /// nested inside of attributes (other than strings) are not counted.
+    pub max_output_bytes: Option<usize>,
+}
+
 /// The main entry point to parsing replays in boxcars. Allows one to customize parsing options,
 /// such as only parsing the header and forgoing crc (corruption) checks.
 #[derive(Debug, Clone, PartialEq)]
@@ -265,6 +308,9 @@ pub struct ParserBuilder<'a> {
     data: &'a [u8],
     crc_check: Option<CrcCheck>,
     network_parse: Option<NetworkParse>,
+    retain_unknown_properties: bool,
+    preserve_text_encodings: bool,
+    network: NetworkOptions,
 }
 
 impl<'a> ParserBuilder<'a> {
@@ -273,6 +319,9 @@ impl<'a> ParserBuilder<'a> {
             data,
             crc_check: None,
             network_parse: None,
+            retain_unknown_properties: false,
+            preserve_text_encodings: false,
+            network: NetworkOptions::default(),
         }
     }
 
@@ -316,37 +365,271 @@ impl<'a> ParserBuilder<'a> {
         self
     }
 
+    /// Instead of failing on a header property of an unrecognized type, use the property's size
+    /// to retain its raw bytes as a `HeaderProp::Unknown`. Useful for header-only ingest that
+    /// should keep working when a patch introduces a new property type.
+    pub fn retain_unknown_properties(mut self) -> ParserBuilder<'a> {
+        self.retain_unknown_properties = true;
+        self
+    }
+
+    /// Record the original encoding of the strings decoded from the header and body in
+    /// `Replay::strings`, for lossless rewriting or diagnosing mojibake in player names.
+    pub fn preserve_text_encodings(mut self) -> ParserBuilder<'a> {
+        self.preserve_text_encodings = true;
+        self
+    }
+
+    /// Only store the updates of the given attributes in the network frames (eg:
+    /// `TAGame.RBActor_TA:ReplicatedRBState`). All attributes are still decoded to stay in sync
+    /// with the network data, so this reduces memory use and not decoding time. New and deleted
+    /// actors are always stored.
+    ///
+    /// ```
+    /// # use boxcars::{Attribute, ParserBuilder};
+    /// let data = include_bytes!("../assets/replays/good/rumble.replay");
+    /// let replay = ParserBuilder::new(&data[..])
+    ///     .must_parse_network_data()
+    ///     .only_attributes(["TAGame.RBActor_TA:ReplicatedRBState"])
+    ///     .parse()
+    ///     .unwrap();
+    ///
+    /// let frames = replay.network_frames.unwrap().frames;
+    /// assert!(frames
+    ///     .iter()
+    ///     .flat_map(|x| x.updated_actors.iter())
+    ///     .all(|x| matches!(x.attribute, Attribute::RigidBody(_))));
+    /// ```
+    pub fn only_attributes<I, S>(mut self, attributes: I) -> ParserBuilder<'a>
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.network.only_attributes = Some(attributes.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Only store the attribute updates of actors that are an instance of one of the given
+    /// classes (eg: `TAGame.Ball_TA`), which includes their subclasses and archetypes. As with
+    /// `only_attributes`, all attributes are still decoded.
+    pub fn only_classes<I, S>(mut self, classes: I) -> ParserBuilder<'a>
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.network.only_classes = Some(classes.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Stop decoding the network data at the first frame that occurs more than the given number
+    /// of seconds after the first frame. Useful when only the start of a match (lineups,
+    /// loadouts, car bodies) is of interest.
+    ///
+    /// ```
+    /// # use boxcars::ParserBuilder;
+    /// let data = include_bytes!("../assets/replays/good/rumble.replay");
+    /// let replay = ParserBuilder::new(&data[..])
+    ///     .must_parse_network_data()
+    ///     .stop_after_time(5.0)
+    ///     .parse()
+    ///     .unwrap();
+    ///
+    /// let frames = replay.network_frames.unwrap().frames;
+    /// let elapsed = frames.last().unwrap().time - frames[0].time;
+    /// assert!(elapsed <= 5.0);
+    /// ```
+    pub fn stop_after_time(mut self, secs: f32) -> ParserBuilder<'a> {
+        self.network.stop_after_time = Some(secs);
+        self
+    }
+
+    /// Stop decoding the network data after the given number of frames
+    pub fn stop_after_frames(mut self, frames: usize) -> ParserBuilder<'a> {
+        self.network.stop_after_frames = Some(frames);
+        self
+    }
+
+    /// Invoke the callback with the progress after each frame of the network data is decoded.
+    /// Returning `ControlFlow::Break` cancels decoding with `NetworkError::Cancelled`, which,
+    /// like any other network error, is subject to the `NetworkParse` option.
+    ///
+    /// ```
+    /// # use boxcars::{NetworkError, ParseError, ParserBuilder};
+    /// # use std::ops::ControlFlow;
+    /// let data = include_bytes!("../assets/replays/good/rumble.replay");
+    /// let replay = ParserBuilder::new(&data[..])
+    ///     .must_parse_network_data()
+    ///     .on_progress(|progress| {
+    ///         if progress.frames < 100 {
+    ///             ControlFlow::Continue(())
+    ///         } else {
+    ///             ControlFlow::Break(())
+    ///         }
+    ///     })
+    ///     .parse();
+    ///
+    /// let expected = ParseError::NetworkError(Box::new(NetworkError::Cancelled));
+    /// assert_eq!(replay.unwrap_err(), expected);
+    /// ```
+    pub fn on_progress<F>(mut self, callback: F) -> ParserBuilder<'a>
+    where
+        F: Fn(Progress) -> ControlFlow<()> + Send + Sync + 'static,
+    {
+        self.network.progress = Some(ProgressCallback(Arc::new(callback)));
+        self
+    }
+
+    /// Decode the network data straight into `Replay::columnar_frames` instead of
+    /// `Replay::network_frames`. The frames are never materialized, so peak memory is close to
+    /// the size of the columns rather than the frames plus the columns.
+    ///
+    /// ```
+    /// # use boxcars::ParserBuilder;
+    /// let data = include_bytes!("../assets/replays/good/rumble.replay");
+    /// let replay = ParserBuilder::new(&data[..])
+    ///     .must_parse_network_data()
+    ///     .columnar_frames()
+    ///     .parse()
+    ///     .unwrap();
+    ///
+    /// assert!(replay.network_frames.is_none());
+    /// let columns = replay.columnar_frames.unwrap();
+    /// assert!(!columns.rigid_bodies.is_empty());
+    /// ```
+    pub fn columnar_frames(mut self) -> ParserBuilder<'a> {
+        self.network.columnar = true;
+        self
+    }
+
+    /// Enforce the given limits while parsing
+    pub fn with_limits(mut self, limits: Limits) -> ParserBuilder<'a> {
+        self.network.limits = limits;
+        self
+    }
+
+    /// Returns a builder with the same configuration for different replay data. Useful when the
+    /// same configuration is applied to many replays.
+    ///
+    /// ```
+    /// # use boxcars::ParserBuilder;
+    /// let config = ParserBuilder::new(&[]).never_parse_network_data();
+    /// let data = include_bytes!("../assets/replays/good/rumble.replay");
+    /// let replay = config.with_data(&data[..]).parse().unwrap();
+    /// assert!(replay.network_frames.is_none());
+    /// ```
+    pub fn with_data<'b>(&self, data: &'b [u8]) -> ParserBuilder<'b> {
+        ParserBuilder {
+            data,
+            crc_check: self.crc_check,
+            network_parse: self.network_parse,
+            retain_unknown_properties: self.retain_unknown_properties,
+            preserve_text_encodings: self.preserve_text_encodings,
+            network: self.network.clone(),
+        }
+    }
+
     pub fn parse(self) -> Result<Replay, ParseError> {
+        self.parse_with(None).map(ReplayRef::into_owned)
+    }
+
+    /// Parses the replay into a `ReplayRef` that borrows its strings from the replay data
+    /// instead of allocating them. Useful when many replays are parsed only to read a few
+    /// fields.
+    ///
+    /// ```
+    /// # use boxcars::ParserBuilder;
+    /// # use std::borrow::Cow;
+    /// let data = include_bytes!("../assets/replays/good/rumble.replay");
+    /// let replay = ParserBuilder::new(&data[..])
+    ///     .never_parse_network_data()
+    ///     .parse_borrowed()
+    ///     .unwrap();
+    /// assert!(matches!(replay.objects[0], Cow::Borrowed(_)));
+    /// ```
+    pub fn parse_borrowed(self) -> Result<ReplayRef<'a>, ParseError> {
+        self.parse_with(None)
+    }
+
+    pub(crate) fn parse_with(
+        self,
+        context: Option<&mut ParserContext>,
+    ) -> Result<ReplayRef<'a>, ParseError> {
         let mut parser = Parser::new(
             self.data,
             self.crc_check.unwrap_or(CrcCheck::OnError),
             self.network_parse.unwrap_or(NetworkParse::IgnoreOnError),
         );
-        parser.parse()
+        parser.retain_unknown_properties = self.retain_unknown_properties;
+        parser
+            .core
+            .limit_string_size(self.network.limits.max_string_size);
+        parser.network = self.network;
+        if self.preserve_text_encodings {
+            parser.core.record_texts();
+        }
+        parser.parse(context)
     }
 }
 
 /// Intermediate parsing structure for the body / footer
 #[derive(Debug, PartialEq)]
 pub struct ReplayBody<'a> {
-    pub levels: Vec<String>,
+    pub levels: Vec<Cow<'a, str>>,
     pub keyframes: Vec<KeyFrame>,
     pub debug_info: Vec<DebugInfo>,
     pub tick_marks: Vec<TickMark>,
-    pub packages: Vec<String>,
-    pub objects: Vec<String>,
-    pub names: Vec<String>,
+    pub packages: Vec<Cow<'a, str>>,
+    pub objects: Vec<Cow<'a, str>>,
+    pub names: Vec<Cow<'a, str>>,
     pub class_indices: Vec<ClassIndex>,
     pub net_cache: Vec<ClassNetCache>,
     pub network_data: &'a [u8],
 }
 
+/// Splits the text recorded while parsing the header and body into the sections they were decoded
+/// from. Text is recorded in the order it is parsed, so the sections can be recovered from their
+/// lengths.
+fn replay_strings(
+    header: &Header,
+    body: &ReplayBody<'_>,
+    header_texts: Vec<RlString>,
+    body_texts: Vec<RlString>,
+) -> Option<ReplayStrings> {
+    let (game_type, properties) = text::header_strings(header_texts, &header.properties)?;
+
+    let mut body_texts = body_texts.into_iter();
+    let mut take = |n: usize| body_texts.by_ref().take(n).collect::<Vec<_>>();
+    let levels = take(body.levels.len());
+    let debug_info = take(body.debug_info.len() * 2);
+    let debug_info = debug_info
+        .chunks_exact(2)
+        .map(|x| (x[0].clone(), x[1].clone()))
+        .collect();
+    let tick_marks = take(body.tick_marks.len());
+    let packages = take(body.packages.len());
+    let objects = take(body.objects.len());
+    let names = take(body.names.len());
+
+    Some(ReplayStrings {
+        game_type,
+        properties,
+        levels,
+        debug_info,
+        tick_marks,
+        packages,
+        objects,
+        names,
+    })
+}
+
 /// Holds the current state of parsing a replay
 #[derive(Debug, Clone, PartialEq)]
 pub struct Parser<'a> {
     core: CoreParser<'a>,
     crc_check: CrcCheck,
     network_parse: NetworkParse,
+    retain_unknown_properties: bool,
+    network: NetworkOptions,
 }
 
 impl<'a> Parser<'a> {
@@ -355,10 +638,12 @@ impl<'a> Parser<'a> {
             core: CoreParser::new(data),
             crc_check,
             network_parse,
+            retain_unknown_properties: false,
+            network: NetworkOptions::default(),
         }
     }
 
-    fn parse(&mut self) -> Result<Replay, ParseError> {
+    fn parse(&mut self, context: Option<&mut ParserContext>) -> Result<ReplayRef<'a>, ParseError> {
         let header_size = self.core.take_i32("header size")?;
         let header_crc = self.core.take_u32("header crc")?;
 
@@ -367,6 +652,7 @@ impl<'a> Parser<'a> {
         })?;
 
         let header = self.crc_section(header_data, header_crc, "header", Self::parse_header)?;
+        let header_texts = self.core.take_texts();
 
         let content_size = self.core.take_i32("content size")?;
         let content_crc = self.core.take_u32("content crc")?;
@@ -376,20 +662,32 @@ impl<'a> Parser<'a> {
         })?;
 
         let body = self.crc_section(content_data, content_crc, "body", Self::parse_body)?;
-
-        let network: Option<NetworkFrames> = match self.network_parse {
+        let strings =
+            header_texts
+                .zip(self.core.take_texts())
+                .and_then(|(header_texts, body_texts)| {
+                    replay_strings(&header, &body, header_texts, body_texts)
+                });
+
+        let network: Option<DecodedNetwork> = match self.network_parse {
             NetworkParse::Always => Some(
-                self.parse_network(&header, &body)
+                self.parse_network(&header, &body, context)
                     .map_err(|x| ParseError::NetworkError(Box::new(x)))?,
             ),
             NetworkParse::IgnoreOnError => self
-                .parse_network(&header, &body)
+                .parse_network(&header, &body, context)
                 .map_err(|x| ParseError::NetworkError(Box::new(x)))
                 .ok(),
             NetworkParse::Never => None,
         };
 
-        Ok(Replay {
+        let (network_frames, columnar_frames) = match network {
+            Some(DecodedNetwork::Frames(x)) => (Some(x), None),
+            Some(DecodedNetwork::Columnar(x)) => (None, Some(*x)),
+            None => (None, None),
+        };
+
+        Ok(ReplayRef {
             header_size,
             header_crc,
             major_version: header.major_version,
@@ -399,7 +697,8 @@ impl<'a> Parser<'a> {
             properties: header.properties,
             content_size,
             content_crc,
-            network_frames: network,
+            network_frames,
+            columnar_frames,
             levels: body.levels,
             keyframes: body.keyframes,
             debug_info: body.debug_info,
@@ -409,6 +708,7 @@ impl<'a> Parser<'a> {
             names: body.names,
             class_indices: body.class_indices,
             net_cache: body.net_cache,
+            strings,
         })
     }
 
@@ -416,12 +716,19 @@ impl<'a> Parser<'a> {
         &mut self,
         header: &Header,
         body: &ReplayBody<'_>,
-    ) -> Result<NetworkFrames, NetworkError> {
-        network::parse(header, body)
+        context: Option<&mut ParserContext>,
+    ) -> Result<DecodedNetwork, NetworkError> {
+        match context {
+            Some(context) => {
+                let (setup, buffers) = context.network_setup(network::version(header), body)?;
+                network::parse_with_setup(header, body, setup, buffers, &self.network)
+            }
+            None => network::parse(header, body, &self.network),
+        }
     }
 
-    fn parse_header(&mut self) -> Result<Header, ParseError> {
-        header::parse_header(&mut self.core)
+    fn parse_header(&mut self) -> Result<Header<'a>, ParseError> {
+        header::parse_header(&mut self.core, self.retain_unknown_properties)
     }
 
     /// Parses a section and performs a crc check as configured
@@ -520,7 +827,7 @@ impl<'a> Parser<'a> {
     fn parse_tickmarks(&mut self) -> Result<Vec<TickMark>, ParseError> {
         self.core.list_of(|s| {
             Ok(TickMark {
-                description: s.parse_text()?,
+                description: s.parse_text()?.into_owned(),
                 frame: s.take::<4>().map(i32::from_le_bytes)?,
             })
         })
@@ -540,8 +847,8 @@ impl<'a> Parser<'a> {
         self.core.list_of(|s| {
             Ok(DebugInfo {
                 frame: s.take::<4>().map(i32::from_le_bytes)?,
-                user: s.parse_text()?,
-                text: s.parse_text()?,
+                user: s.parse_text()?.into_owned(),
+                text: s.parse_text()?.into_owned(),
             })
         })
     }
@@ -617,42 +924,42 @@ mod tests {
     #[test]
     fn test_the_parsing_empty() {
         let mut parser = Parser::new(&[], CrcCheck::Never, NetworkParse::Never);
-        assert!(parser.parse().is_err());
+        assert!(parser.parse(None).is_err());
     }
 
     #[test]
     fn test_the_parsing_text_too_long() {
         let data = include_bytes!("../assets/replays/bad/fuzz-string-too-long.replay");
         let mut parser = Parser::new(&data[..], CrcCheck::Never, NetworkParse::Never);
-        assert!(parser.parse().is_err())
+        assert!(parser.parse(None).is_err())
     }
 
     #[test]
     fn test_the_parsing_text_too_long2() {
         let data = include_bytes!("../assets/replays/bad/fuzz-string-too-long2.replay");
         let mut parser = Parser::new(&data[..], CrcCheck::Never, NetworkParse::Always);
-        let _ = parser.parse().unwrap_err();
+        let _ = parser.parse(None).unwrap_err();
     }
 
     #[test]
     fn test_fuzz_corpus_slice_index() {
         let data = include_bytes!("../assets/replays/bad/fuzz-slice-index.replay");
         let mut parser = Parser::new(&data[..], CrcCheck::Never, NetworkParse::Never);
-        assert!(parser.parse().is_err())
+        assert!(parser.parse(None).is_err())
     }
 
     #[test]
     fn test_the_fuzz_corpus_abs_panic() {
         let data = include_bytes!("../assets/replays/bad/fuzz-corpus.replay");
         let mut parser = Parser::new(&data[..], CrcCheck::Never, NetworkParse::Never);
-        assert!(parser.parse().is_err())
+        assert!(parser.parse(None).is_err())
     }
 
     #[test]
     fn test_the_fuzz_corpus_large_list() {
         let data = include_bytes!("../assets/replays/bad/fuzz-list-too-large.replay");
         let mut parser = Parser::new(&data[..], CrcCheck::Never, NetworkParse::Never);
-        let err = parser.parse().unwrap_err();
+        let err = parser.parse(None).unwrap_err();
         assert!(format!("{}", err)
             .starts_with("Could not decode replay debug info at offset (1010894): list of size"));
     }
@@ -661,7 +968,7 @@ mod tests {
     fn test_the_fuzz_corpus_large_list_on_error_crc() {
         let data = include_bytes!("../assets/replays/bad/fuzz-list-too-large.replay");
         let mut parser = Parser::new(&data[..], CrcCheck::OnError, NetworkParse::Never);
-        let err = parser.parse().unwrap_err();
+        let err = parser.parse(None).unwrap_err();
         assert_eq!(
             "Failed to parse body and crc check failed. Replay is corrupt",
             format!("{}", err)
@@ -675,7 +982,7 @@ mod tests {
     fn test_the_fuzz_corpus_large_list_always_crc() {
         let data = include_bytes!("../assets/replays/bad/fuzz-list-too-large.replay");
         let mut parser = Parser::new(&data[..], CrcCheck::Always, NetworkParse::Never);
-        let err = parser.parse().unwrap_err();
+        let err = parser.parse(None).unwrap_err();
         assert_eq!(
             "Crc mismatch. Expected 3765941959 but received 1314727725",
             format!("{}", err)
@@ -687,7 +994,7 @@ mod tests {
     fn test_the_fuzz_object_id_too_large() {
         let data = include_bytes!("../assets/replays/bad/fuzz-large-object-id.replay");
         let mut parser = Parser::new(&data[..], CrcCheck::Never, NetworkParse::Always);
-        let err = parser.parse().unwrap_err();
+        let err = parser.parse(None).unwrap_err();
         assert!(err.source().is_some());
     }
 
@@ -695,7 +1002,7 @@ mod tests {
     fn test_the_fuzz_too_many_frames() {
         let data = include_bytes!("../assets/replays/bad/fuzz-too-many-frames.replay");
         let mut parser = Parser::new(&data[..], CrcCheck::Never, NetworkParse::Always);
-        let err = parser.parse().unwrap_err();
+        let err = parser.parse(None).unwrap_err();
         assert_eq!("Too many frames to decode: 738197735", format!("{}", err));
         assert!(err.source().is_some());
     }
@@ -707,7 +1014,7 @@ mod tests {
         // Changing this byte won't make the parsing fail but will make the crc check fail
         data[4775] = 100;
         let mut parser = Parser::new(&data[..], CrcCheck::Always, NetworkParse::Never);
-        let res = parser.parse();
+        let res = parser.parse(None);
         assert!(res.is_err());
         assert_eq!(
             "Crc mismatch. Expected 337843175 but received 2877465516",
@@ -715,6 +1022,6 @@ mod tests {
         );
 
         parser = Parser::new(&data[..], CrcCheck::OnError, NetworkParse::Never);
-        assert!(parser.parse().is_ok());
+        assert!(parser.parse(None).is_ok());
     }
 }
@@ -1,5 +1,6 @@
 use crate::errors::ParseError;
 use encoding_rs::{UTF_16LE, WINDOWS_1252};
+use std::borrow::Cow;
 
 /// Reads a string of a given size from the data. The size includes a null
 /// character as the last character, so we drop it in the returned string
@@ -17,8 +18,9 @@ pub fn decode_utf16(input: &[u8]) -> Result<String, ParseError> {
     Ok(String::from(s))
 }
 
-pub fn decode_windows1252(input: &[u8]) -> Result<String, ParseError> {
+/// Decodes windows-1252 text, borrowing the input when it is ASCII
+pub fn decode_windows1252(input: &[u8]) -> Result<Cow<'_, str>, ParseError> {
     let data = &input[..input.len().saturating_sub(1)];
     let (s, _) = WINDOWS_1252.decode_without_bom_handling(data);
-    Ok(String::from(s))
+    Ok(s)
 }
@@ -0,0 +1,521 @@
+//! # Header Queries
+//!
+//! Header properties nest: arrays contain lists of key value pairs and structs contain fields.
+//! Rather than writing bespoke recursion to reach a nested value, a path can be used to select
+//! values out of the header.
+//!
+//! A query is a list of segments:
+//!
+//! - `Key`: selects the value of every property with the given key (a struct's fields are
+//!   searched when the current value is a struct)
+//! - `[3]`: selects the fourth entry of an array
+//! - `[*]` (or `*`): selects every entry of an array or every property
+//!
+//! Keys are separated by a `.` and indices are wrapped in brackets, so
+//! `PlayerStats[*].PlayerID.EpicAccountId` selects the epic account id of every player.
+//!
+//! ```
+//! # use boxcars::ParserBuilder;
+//! let data = include_bytes!("../assets/replays/good/rumble.replay");
+//! let replay = ParserBuilder::new(&data[..])
+//!     .never_parse_network_data()
+//!     .parse()
+//!     .unwrap();
+//!
+//! let scorers: Vec<&str> = replay.query_as("Goals[*].PlayerName").unwrap();
+//! assert_eq!(scorers[0], "Cakeboss");
+//!
+//! let first = replay.get_path(&["Goals".into(), 0.into(), "PlayerName".into()]);
+//! assert_eq!(first.and_then(|x| x.as_string()), Some("Cakeboss"));
+//! ```
+
+use crate::errors::QueryError;
+use crate::models::{HeaderProp, Replay};
+use std::fmt;
+
+/// A single step in a path through nested header properties
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PathSegment<'a> {
+    /// The properties (or struct fields) with the given key
+    Key(&'a str),
+
+    /// The nth entry of an array
+    Index(usize),
+
+    /// Every entry of an array or every property
+    Wildcard,
+}
+
+impl<'a> From<&'a str> for PathSegment<'a> {
+    fn from(key: &'a str) -> Self {
+        PathSegment::Key(key)
+    }
+}
+
+impl From<usize> for PathSegment<'_> {
+    fn from(index: usize) -> Self {
+        PathSegment::Index(index)
+    }
+}
+
+impl fmt::Display for PathSegment<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PathSegment::Key(key) => write!(f, "{}", key),
+            PathSegment::Index(index) => write!(f, "[{}]", index),
+            PathSegment::Wildcard => write!(f, "[*]"),
+        }
+    }
+}
+
+/// A parsed query that can be evaluated against header properties multiple times
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HeaderPath<'a> {
+    segments: Vec<PathSegment<'a>>,
+}
+
+impl<'a> HeaderPath<'a> {
+    /// Parses a query like `PlayerStats[*].Name`
+    pub fn parse(query: &'a str) -> Result<Self, QueryError> {
+        let mut segments = Vec::new();
+        let bytes = query.as_bytes();
+        let mut pos = 0;
+        let mut expect_key = true;
+
+        while pos < bytes.len() {
+            match bytes[pos] {
+                b'[' => {
+                    let end = query[pos..]
+                        .find(']')
+                        .map(|x| x + pos)
+                        .ok_or(QueryError::UnterminatedIndex(pos))?;
+                    let inner = &query[pos + 1..end];
+                    if inner == "*" {
+                        segments.push(PathSegment::Wildcard);
+                    } else {
+                        let index = inner
+                            .parse::<usize>()
+                            .map_err(|_| QueryError::InvalidIndex(String::from(inner)))?;
+                        segments.push(PathSegment::Index(index));
+                    }
+                    pos = end + 1;
+                    expect_key = false;
+                }
+                b'.' if !expect_key && pos + 1 < bytes.len() => {
+                    pos += 1;
+                    expect_key = true;
+                }
+                b'.' | b']' => {
+                    return Err(QueryError::UnexpectedCharacter(bytes[pos] as char, pos))
+                }
+                _ if expect_key => {
+                    let end = query[pos..]
+                        .find(['.', '[', ']'])
+                        .map_or(query.len(), |x| x + pos);
+                    let key = &query[pos..end];
+                    if key == "*" {
+                        segments.push(PathSegment::Wildcard);
+                    } else {
+                        segments.push(PathSegment::Key(key));
+                    }
+                    pos = end;
+                    expect_key = false;
+                }
+                _ => {
+                    let c = query[pos..].chars().next().unwrap_or_default();
+                    return Err(QueryError::UnexpectedCharacter(c, pos));
+                }
+            }
+        }
+
+        if segments.is_empty() {
+            return Err(QueryError::Empty);
+        }
+
+        Ok(HeaderPath { segments })
+    }
+
+    pub fn segments(&self) -> &[PathSegment<'a>] {
+        &self.segments
+    }
+
+    /// Returns every value in the properties matched by the path
+    pub fn select<'b>(&self, properties: &'b [(String, HeaderProp)]) -> Vec<&'b HeaderProp> {
+        let mut result = Vec::new();
+        select_props(properties, &self.segments, &mut result);
+        result
+    }
+
+    /// Returns a mutable reference to every value in the properties matched by the path
+    pub fn select_mut<'b>(
+        &self,
+        properties: &'b mut [(String, HeaderProp)],
+    ) -> Vec<&'b mut HeaderProp> {
+        let mut result = Vec::new();
+        select_props_mut(properties, &self.segments, &mut result);
+        result
+    }
+}
+
+impl<'a> From<Vec<PathSegment<'a>>> for HeaderPath<'a> {
+    fn from(segments: Vec<PathSegment<'a>>) -> Self {
+        HeaderPath { segments }
+    }
+}
+
+impl fmt::Display for HeaderPath<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, segment) in self.segments.iter().enumerate() {
+            if i != 0 && !matches!(segment, PathSegment::Index(_) | PathSegment::Wildcard) {
+                write!(f, ".")?;
+            }
+            write!(f, "{}", segment)?;
+        }
+        Ok(())
+    }
+}
+
+/// Conversion from a selected header property into a typed value
+pub trait FromHeaderProp<'a>: Sized {
+    fn from_header_prop(prop: &'a HeaderProp) -> Option<Self>;
+}
+
+impl<'a> FromHeaderProp<'a> for &'a HeaderProp {
+    fn from_header_prop(prop: &'a HeaderProp) -> Option<Self> {
+        Some(prop)
+    }
+}
+
+impl<'a> FromHeaderProp<'a> for &'a str {
+    fn from_header_prop(prop: &'a HeaderProp) -> Option<Self> {
+        prop.as_string()
+    }
+}
+
+impl FromHeaderProp<'_> for bool {
+    fn from_header_prop(prop: &HeaderProp) -> Option<Self> {
+        prop.as_bool()
+    }
+}
+
+impl FromHeaderProp<'_> for f32 {
+    fn from_header_prop(prop: &HeaderProp) -> Option<Self> {
+        prop.as_float()
+    }
+}
+
+impl FromHeaderProp<'_> for i32 {
+    fn from_header_prop(prop: &HeaderProp) -> Option<Self> {
+        prop.as_i32()
+    }
+}
+
+impl FromHeaderProp<'_> for u64 {
+    fn from_header_prop(prop: &HeaderProp) -> Option<Self> {
+        prop.as_u64()
+    }
+}
+
+impl Replay {
+    /// Returns every header value matched by the query
+    pub fn query(&self, query: &str) -> Result<Vec<&HeaderProp>, QueryError> {
+        HeaderPath::parse(query).map(|path| path.select(&self.properties))
+    }
+
+    /// Returns a mutable reference to every header value matched by the query
+    pub fn query_mut(&mut self, query: &str) -> Result<Vec<&mut HeaderProp>, QueryError> {
+        HeaderPath::parse(query).map(|path| path.select_mut(&mut self.properties))
+    }
+
+    /// Returns every header value matched by the query that is of the requested type. Values of
+    /// any other type are skipped.
+    pub fn query_as<'a, T>(&'a self, query: &str) -> Result<Vec<T>, QueryError>
+    where
+        T: FromHeaderProp<'a>,
+    {
+        let props = self.query(query)?;
+        Ok(props.into_iter().filter_map(T::from_header_prop).collect())
+    }
+
+    /// Returns the first header value found at the path
+    pub fn get_path(&self, path: &[PathSegment<'_>]) -> Option<&HeaderProp> {
+        let mut result = Vec::new();
+        select_props(&self.properties, path, &mut result);
+        result.into_iter().next()
+    }
+
+    /// Returns a mutable reference to the first header value found at the path
+    pub fn get_path_mut(&mut self, path: &[PathSegment<'_>]) -> Option<&mut HeaderProp> {
+        let mut result = Vec::new();
+        select_props_mut(&mut self.properties, path, &mut result);
+        result.into_iter().next()
+    }
+}
+
+impl HeaderProp {
+    /// Returns the first value found at a path relative to this property. An empty path returns
+    /// the property itself.
+    /// ```
+    /// # use boxcars::HeaderProp;
+    /// let v = HeaderProp::Array(vec![
+    ///     vec![(String::from("frame"), HeaderProp::Int(441))]
+    /// ]);
+    ///
+    /// assert_eq!(v.get_path(&[0.into(), "frame".into()]), Some(&HeaderProp::Int(441)));
+    /// assert_eq!(v.get_path(&[1.into(), "frame".into()]), None);
+    /// ```
+    pub fn get_path(&self, path: &[PathSegment<'_>]) -> Option<&HeaderProp> {
+        let mut result = Vec::new();
+        select_prop(self, path, &mut result);
+        result.into_iter().next()
+    }
+
+    /// Returns a mutable reference to the first value found at a path relative to this property
+    pub fn get_path_mut(&mut self, path: &[PathSegment<'_>]) -> Option<&mut HeaderProp> {
+        let mut result = Vec::new();
+        select_prop_mut(self, path, &mut result);
+        result.into_iter().next()
+    }
+
+    /// Returns every value matched by a query relative to this property (eg: `[*].Name`)
+    pub fn query(&self, query: &str) -> Result<Vec<&HeaderProp>, QueryError> {
+        let path = HeaderPath::parse(query)?;
+        let mut result = Vec::new();
+        select_prop(self, path.segments(), &mut result);
+        Ok(result)
+    }
+
+    /// Returns a mutable reference to every value matched by a query relative to this property
+    pub fn query_mut(&mut self, query: &str) -> Result<Vec<&mut HeaderProp>, QueryError> {
+        let path = HeaderPath::parse(query)?;
+        let mut result = Vec::new();
+        select_prop_mut(self, path.segments(), &mut result);
+        Ok(result)
+    }
+}
+
+fn select_props<'a>(
+    props: &'a [(String, HeaderProp)],
+    path: &[PathSegment<'_>],
+    out: &mut Vec<&'a HeaderProp>,
+) {
+    // A list of properties is not a value, so an empty path selects nothing
+    let Some((segment, rest)) = path.split_first() else {
+        return;
+    };
+
+    match *segment {
+        PathSegment::Key(key) => {
+            for (_, prop) in props.iter().filter(|(k, _)| k == key) {
+                select_prop(prop, rest, out);
+            }
+        }
+        PathSegment::Wildcard => {
+            for (_, prop) in props {
+                select_prop(prop, rest, out);
+            }
+        }
+        PathSegment::Index(_) => {}
+    }
+}
+
+fn select_prop<'a>(prop: &'a HeaderProp, path: &[PathSegment<'_>], out: &mut Vec<&'a HeaderProp>) {
+    let Some((segment, rest)) = path.split_first() else {
+        out.push(prop);
+        return;
+    };
+
+    match (segment, prop) {
+        (PathSegment::Index(ind), HeaderProp::Array(arr)) => {
+            if let Some(entry) = arr.get(*ind) {
+                select_props(entry, rest, out);
+            }
+        }
+        (PathSegment::Wildcard, HeaderProp::Array(arr)) => {
+            for entry in arr {
+                select_props(entry, rest, out);
+            }
+        }
+        (PathSegment::Key(_) | PathSegment::Wildcard, HeaderProp::Struct { fields, .. }) => {
+            select_props(fields, path, out);
+        }
+        _ => {}
+    }
+}
+
+fn select_props_mut<'a>(
+    props: &'a mut [(String, HeaderProp)],
+    path: &[PathSegment<'_>],
+    out: &mut Vec<&'a mut HeaderProp>,
+) {
+    let Some((segment, rest)) = path.split_first() else {
+        return;
+    };
+
+    match *segment {
+        PathSegment::Key(key) => {
+            for (_, prop) in props.iter_mut().filter(|(k, _)| k == key) {
+                select_prop_mut(prop, rest, out);
+            }
+        }
+        PathSegment::Wildcard => {
+            for (_, prop) in props {
+                select_prop_mut(prop, rest, out);
+            }
+        }
+        PathSegment::Index(_) => {}
+    }
+}
+
+fn select_prop_mut<'a>(
+    prop: &'a mut HeaderProp,
+    path: &[PathSegment<'_>],
+    out: &mut Vec<&'a mut HeaderProp>,
+) {
+    let Some((segment, rest)) = path.split_first() else {
+        out.push(prop);
+        return;
+    };
+
+    match (segment, prop) {
+        (PathSegment::Index(ind), HeaderProp::Array(arr)) => {
+            if let Some(entry) = arr.get_mut(*ind) {
+                select_props_mut(entry, rest, out);
+            }
+        }
+        (PathSegment::Wildcard, HeaderProp::Array(arr)) => {
+            for entry in arr {
+                select_props_mut(entry, rest, out);
+            }
+        }
+        (PathSegment::Key(_) | PathSegment::Wildcard, HeaderProp::Struct { fields, .. }) => {
+            select_props_mut(fields, path, out);
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn prop(key: &str, val: HeaderProp) -> (String, HeaderProp) {
+        (String::from(key), val)
+    }
+
+    fn stats() -> Vec<(String, HeaderProp)> {
+        vec![
+            prop("TeamSize", HeaderProp::Int(2)),
+            prop(
+                "PlayerStats",
+                HeaderProp::Array(vec![
+                    vec![
+                        prop("Name", HeaderProp::Str(String::from("a"))),
+                        prop(
+                            "PlayerID",
+                            HeaderProp::Struct {
+                                name: String::from("UniqueNetId"),
+                                fields: vec![prop(
+                                    "EpicAccountId",
+                                    HeaderProp::Str(String::from("e1")),
+                                )],
+                            },
+                        ),
+                    ],
+                    vec![prop("Name", HeaderProp::Str(String::from("b")))],
+                ]),
+            ),
+        ]
+    }
+
+    #[test]
+    fn test_parse_paths() {
+        let path = HeaderPath::parse("PlayerStats[*].PlayerID.EpicAccountId").unwrap();
+        assert_eq!(
+            path.segments(),
+            &[
+                PathSegment::Key("PlayerStats"),
+                PathSegment::Wildcard,
+                PathSegment::Key("PlayerID"),
+                PathSegment::Key("EpicAccountId"),
+            ]
+        );
+        assert_eq!(path.to_string(), "PlayerStats[*].PlayerID.EpicAccountId");
+
+        let path = HeaderPath::parse("[1][2].*").unwrap();
+        assert_eq!(
+            path.segments(),
+            &[
+                PathSegment::Index(1),
+                PathSegment::Index(2),
+                PathSegment::Wildcard
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_invalid_paths() {
+        assert_eq!(HeaderPath::parse(""), Err(QueryError::Empty));
+        assert_eq!(
+            HeaderPath::parse("Goals[0"),
+            Err(QueryError::UnterminatedIndex(5))
+        );
+        assert_eq!(
+            HeaderPath::parse("Goals[a]"),
+            Err(QueryError::InvalidIndex(String::from("a")))
+        );
+        assert_eq!(
+            HeaderPath::parse("Goals..a"),
+            Err(QueryError::UnexpectedCharacter('.', 6))
+        );
+        assert_eq!(
+            HeaderPath::parse("Goals."),
+            Err(QueryError::UnexpectedCharacter('.', 5))
+        );
+        assert_eq!(
+            HeaderPath::parse("Goals[0]a"),
+            Err(QueryError::UnexpectedCharacter('a', 8))
+        );
+    }
+
+    #[test]
+    fn test_select() {
+        let props = stats();
+        let names = HeaderPath::parse("PlayerStats[*].Name")
+            .unwrap()
+            .select(&props);
+        assert_eq!(
+            names,
+            vec![
+                &HeaderProp::Str(String::from("a")),
+                &HeaderProp::Str(String::from("b"))
+            ]
+        );
+
+        let ids = HeaderPath::parse("PlayerStats[*].PlayerID.EpicAccountId")
+            .unwrap()
+            .select(&props);
+        assert_eq!(ids, vec![&HeaderProp::Str(String::from("e1"))]);
+
+        let missing = HeaderPath::parse("PlayerStats[5].Name")
+            .unwrap()
+            .select(&props);
+        assert!(missing.is_empty());
+
+        let entries = HeaderPath::parse("PlayerStats[0]").unwrap().select(&props);
+        assert!(entries.is_empty());
+    }
+
+    #[test]
+    fn test_select_mut() {
+        let mut props = stats();
+        let path = HeaderPath::parse("PlayerStats[*].Name").unwrap();
+        for name in path.select_mut(&mut props) {
+            *name = HeaderProp::Str(String::from("anonymous"));
+        }
+
+        let names = path.select(&props);
+        assert_eq!(names.len(), 2);
+        assert!(names.iter().all(|x| x.as_string() == Some("anonymous")));
+    }
+}
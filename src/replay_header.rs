@@ -0,0 +1,411 @@
+use crate::models::{HeaderProp, Replay};
+
+/// A typed view over the commonly used header properties of a replay.
+///
+/// The header is stored as a list of key value pairs and which keys are present (and how they are
+/// spelled) has changed over the years. For instance, older replays omit `Team0Score` if the team
+/// didn't score, `BuildVersion` is missing from the very first replays, and highlights have been
+/// written as both `HighLights` and `Highlights`. Every accessor returns `None` when the property
+/// is absent or isn't of the expected type instead of failing.
+///
+/// ```
+/// # use boxcars::ParserBuilder;
+/// let data = include_bytes!("../assets/replays/good/rumble.replay");
+/// let replay = ParserBuilder::new(&data[..])
+///     .never_parse_network_data()
+///     .parse()
+///     .unwrap();
+///
+/// let header = replay.header();
+/// assert_eq!(header.team_size(), Some(3));
+/// assert_eq!(header.map_name(), Some("stadium_foggy_p"));
+/// assert_eq!(header.goals().unwrap().len(), 7);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ReplayHeader<'a> {
+    properties: &'a [(String, HeaderProp)],
+}
+
+impl<'a> ReplayHeader<'a> {
+    pub fn new(properties: &'a [(String, HeaderProp)]) -> Self {
+        ReplayHeader { properties }
+    }
+
+    /// Returns the first property with the given key
+    pub fn get(&self, key: &str) -> Option<&'a HeaderProp> {
+        find_prop(self.properties, key)
+    }
+
+    /// Number of players on each team (eg: 3 for a 3v3)
+    pub fn team_size(&self) -> Option<i32> {
+        self.get("TeamSize").and_then(int_prop)
+    }
+
+    /// Number of goals scored by the blue team. Older replays omit this property when the team
+    /// didn't score.
+    pub fn team0_score(&self) -> Option<i32> {
+        self.get("Team0Score").and_then(int_prop)
+    }
+
+    /// Number of goals scored by the orange team. Older replays omit this property when the team
+    /// didn't score.
+    pub fn team1_score(&self) -> Option<i32> {
+        self.get("Team1Score").and_then(int_prop)
+    }
+
+    /// The team that won the match, only written by recent replays
+    pub fn winning_team(&self) -> Option<i32> {
+        self.get("WinningTeam").and_then(int_prop)
+    }
+
+    pub fn goals(&self) -> Option<Vec<Goal<'a>>> {
+        let goals = self.get("Goals")?.as_array()?;
+        Some(goals.iter().map(|props| Goal::new(props)).collect())
+    }
+
+    /// Highlights have been written under both the `HighLights` and `Highlights` keys
+    pub fn highlights(&self) -> Option<Vec<Highlight<'a>>> {
+        let highlights = self
+            .get("HighLights")
+            .or_else(|| self.get("Highlights"))?
+            .as_array()?;
+        Some(
+            highlights
+                .iter()
+                .map(|props| Highlight::new(props))
+                .collect(),
+        )
+    }
+
+    pub fn player_stats(&self) -> Option<Vec<PlayerStats<'a>>> {
+        let stats = self.get("PlayerStats")?.as_array()?;
+        Some(stats.iter().map(|props| PlayerStats::new(props)).collect())
+    }
+
+    /// Date the replay was recorded as formatted by the game (eg: `2016-09-08:19-35` or
+    /// `2020-01-31 18-49-02`)
+    pub fn date(&self) -> Option<&'a str> {
+        self.get("Date").and_then(HeaderProp::as_string)
+    }
+
+    pub fn map_name(&self) -> Option<&'a str> {
+        self.get("MapName").and_then(HeaderProp::as_string)
+    }
+
+    pub fn id(&self) -> Option<&'a str> {
+        self.get("Id").and_then(HeaderProp::as_string)
+    }
+
+    /// The name the user gave when saving the replay
+    pub fn replay_name(&self) -> Option<&'a str> {
+        self.get("ReplayName").and_then(HeaderProp::as_string)
+    }
+
+    /// The kind of match (eg: `Online`, `Private`, `Lan`, `Offline`)
+    pub fn match_type(&self) -> Option<&'a str> {
+        self.get("MatchType").and_then(HeaderProp::as_string)
+    }
+
+    /// The name of the player that recorded the replay
+    pub fn player_name(&self) -> Option<&'a str> {
+        self.get("PlayerName").and_then(HeaderProp::as_string)
+    }
+
+    pub fn num_frames(&self) -> Option<i32> {
+        self.get("NumFrames").and_then(int_prop)
+    }
+
+    pub fn record_fps(&self) -> Option<f32> {
+        self.get("RecordFPS").and_then(HeaderProp::as_float)
+    }
+
+    pub fn keyframe_delay(&self) -> Option<f32> {
+        self.get("KeyframeDelay").and_then(HeaderProp::as_float)
+    }
+
+    pub fn max_channels(&self) -> Option<i32> {
+        self.get("MaxChannels").and_then(int_prop)
+    }
+
+    pub fn max_replay_size_mb(&self) -> Option<i32> {
+        self.get("MaxReplaySizeMB").and_then(int_prop)
+    }
+
+    pub fn build_version(&self) -> Option<&'a str> {
+        self.get("BuildVersion").and_then(HeaderProp::as_string)
+    }
+
+    pub fn build_id(&self) -> Option<i32> {
+        self.get("BuildID").and_then(int_prop)
+    }
+
+    pub fn changelist(&self) -> Option<i32> {
+        self.get("Changelist").and_then(int_prop)
+    }
+
+    pub fn game_version(&self) -> Option<i32> {
+        self.get("GameVersion").and_then(int_prop)
+    }
+
+    pub fn replay_version(&self) -> Option<i32> {
+        self.get("ReplayVersion").and_then(int_prop)
+    }
+
+    pub fn replay_last_save_version(&self) -> Option<i32> {
+        self.get("ReplayLastSaveVersion").and_then(int_prop)
+    }
+
+    pub fn reserve_megabytes(&self) -> Option<i32> {
+        self.get("ReserveMegabytes").and_then(int_prop)
+    }
+
+    pub fn primary_player_team(&self) -> Option<i32> {
+        self.get("PrimaryPlayerTeam").and_then(int_prop)
+    }
+
+    pub fn unfair_team_size(&self) -> Option<i32> {
+        self.get("UnfairTeamSize").and_then(int_prop)
+    }
+
+    /// The match guid has been written under both the `MatchGUID` and `MatchGuid` keys
+    pub fn match_guid(&self) -> Option<&'a str> {
+        self.get("MatchGUID")
+            .or_else(|| self.get("MatchGuid"))
+            .and_then(HeaderProp::as_string)
+    }
+
+    /// Unix timestamp of when the match started
+    pub fn match_start_epoch(&self) -> Option<u64> {
+        self.get("MatchStartEpoch").and_then(u64_prop)
+    }
+
+    pub fn total_seconds_played(&self) -> Option<f32> {
+        self.get("TotalSecondsPlayed")
+            .and_then(HeaderProp::as_float)
+    }
+
+    pub fn forfeit(&self) -> Option<bool> {
+        self.get("bForfeit").and_then(HeaderProp::as_bool)
+    }
+
+    pub fn unfinished_match(&self) -> Option<bool> {
+        self.get("bIsUnfinishedMatchReplay")
+            .and_then(HeaderProp::as_bool)
+    }
+}
+
+impl Replay {
+    /// Returns a typed view over the header properties
+    pub fn header(&self) -> ReplayHeader<'_> {
+        ReplayHeader::new(&self.properties)
+    }
+}
+
+/// An entry of the `Goals` header property
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Goal<'a> {
+    /// The raw properties of the entry
+    pub properties: &'a [(String, HeaderProp)],
+}
+
+impl<'a> Goal<'a> {
+    pub fn new(properties: &'a [(String, HeaderProp)]) -> Self {
+        Goal { properties }
+    }
+
+    /// The frame the goal was scored on
+    pub fn frame(&self) -> Option<i32> {
+        find_prop(self.properties, "frame").and_then(int_prop)
+    }
+
+    pub fn player_name(&self) -> Option<&'a str> {
+        find_prop(self.properties, "PlayerName").and_then(HeaderProp::as_string)
+    }
+
+    pub fn player_team(&self) -> Option<i32> {
+        find_prop(self.properties, "PlayerTeam").and_then(int_prop)
+    }
+}
+
+/// An entry of the `HighLights` header property
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Highlight<'a> {
+    /// The raw properties of the entry
+    pub properties: &'a [(String, HeaderProp)],
+}
+
+impl<'a> Highlight<'a> {
+    pub fn new(properties: &'a [(String, HeaderProp)]) -> Self {
+        Highlight { properties }
+    }
+
+    pub fn frame(&self) -> Option<i32> {
+        find_prop(self.properties, "frame").and_then(int_prop)
+    }
+
+    pub fn car_name(&self) -> Option<&'a str> {
+        find_prop(self.properties, "CarName").and_then(HeaderProp::as_string)
+    }
+
+    pub fn ball_name(&self) -> Option<&'a str> {
+        find_prop(self.properties, "BallName").and_then(HeaderProp::as_string)
+    }
+
+    /// Only present when the highlight is of a goal
+    pub fn goal_actor_name(&self) -> Option<&'a str> {
+        find_prop(self.properties, "GoalActorName").and_then(HeaderProp::as_string)
+    }
+}
+
+/// An entry of the `PlayerStats` header property. Players that left before the end of the match
+/// may be absent.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PlayerStats<'a> {
+    /// The raw properties of the entry
+    pub properties: &'a [(String, HeaderProp)],
+}
+
+impl<'a> PlayerStats<'a> {
+    pub fn new(properties: &'a [(String, HeaderProp)]) -> Self {
+        PlayerStats { properties }
+    }
+
+    pub fn get(&self, key: &str) -> Option<&'a HeaderProp> {
+        find_prop(self.properties, key)
+    }
+
+    pub fn name(&self) -> Option<&'a str> {
+        self.get("Name").and_then(HeaderProp::as_string)
+    }
+
+    /// The online platform (eg: `OnlinePlatform_Steam`)
+    pub fn platform(&self) -> Option<&'a str> {
+        match self.get("Platform")? {
+            HeaderProp::Byte { value, .. } => value.as_deref(),
+            x => x.as_string(),
+        }
+    }
+
+    pub fn online_id(&self) -> Option<u64> {
+        self.get("OnlineID").and_then(u64_prop)
+    }
+
+    /// The epic account id found in the `PlayerID` struct of recent replays
+    pub fn epic_account_id(&self) -> Option<&'a str> {
+        match self.get("PlayerID")? {
+            HeaderProp::Struct { fields, .. } => {
+                find_prop(fields, "EpicAccountId").and_then(HeaderProp::as_string)
+            }
+            _ => None,
+        }
+    }
+
+    pub fn team(&self) -> Option<i32> {
+        self.get("Team").and_then(int_prop)
+    }
+
+    pub fn score(&self) -> Option<i32> {
+        self.get("Score").and_then(int_prop)
+    }
+
+    pub fn goals(&self) -> Option<i32> {
+        self.get("Goals").and_then(int_prop)
+    }
+
+    pub fn assists(&self) -> Option<i32> {
+        self.get("Assists").and_then(int_prop)
+    }
+
+    pub fn saves(&self) -> Option<i32> {
+        self.get("Saves").and_then(int_prop)
+    }
+
+    pub fn shots(&self) -> Option<i32> {
+        self.get("Shots").and_then(int_prop)
+    }
+
+    pub fn is_bot(&self) -> Option<bool> {
+        self.get("bBot").and_then(HeaderProp::as_bool)
+    }
+}
+
+pub(crate) fn find_prop<'a>(
+    properties: &'a [(String, HeaderProp)],
+    key: &str,
+) -> Option<&'a HeaderProp> {
+    properties
+        .iter()
+        .find(|(k, _)| k == key)
+        .map(|(_, prop)| prop)
+}
+
+/// Integers are written as an `IntProperty`, but be lenient with quadwords that fit
+fn int_prop(prop: &HeaderProp) -> Option<i32> {
+    match *prop {
+        HeaderProp::Int(x) => Some(x),
+        HeaderProp::QWord(x) => i32::try_from(x).ok(),
+        _ => None,
+    }
+}
+
+fn u64_prop(prop: &HeaderProp) -> Option<u64> {
+    match *prop {
+        HeaderProp::QWord(x) => Some(x),
+        HeaderProp::Int(x) => u64::try_from(x).ok(),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn prop(key: &str, val: HeaderProp) -> (String, HeaderProp) {
+        (String::from(key), val)
+    }
+
+    #[test]
+    fn test_header_missing_properties() {
+        let header = ReplayHeader::new(&[]);
+        assert_eq!(header.team_size(), None);
+        assert_eq!(header.goals(), None);
+        assert_eq!(header.highlights(), None);
+        assert_eq!(header.build_version(), None);
+    }
+
+    #[test]
+    fn test_header_alternate_spellings() {
+        let props = vec![
+            prop("MatchGuid", HeaderProp::Str(String::from("abc"))),
+            prop(
+                "Highlights",
+                HeaderProp::Array(vec![vec![
+                    prop("frame", HeaderProp::Int(10)),
+                    prop("CarName", HeaderProp::Name(String::from("Car_TA_1"))),
+                ]]),
+            ),
+        ];
+
+        let header = ReplayHeader::new(&props);
+        assert_eq!(header.match_guid(), Some("abc"));
+
+        let highlights = header.highlights().unwrap();
+        assert_eq!(highlights[0].frame(), Some(10));
+        assert_eq!(highlights[0].car_name(), Some("Car_TA_1"));
+        assert_eq!(highlights[0].goal_actor_name(), None);
+    }
+
+    #[test]
+    fn test_header_lenient_numbers() {
+        let props = vec![
+            prop("MatchStartEpoch", HeaderProp::Int(1000)),
+            prop("TeamSize", HeaderProp::QWord(2)),
+            prop("NumFrames", HeaderProp::Str(String::from("3"))),
+        ];
+
+        let header = ReplayHeader::new(&props);
+        assert_eq!(header.match_start_epoch(), Some(1000));
+        assert_eq!(header.team_size(), Some(2));
+        assert_eq!(header.num_frames(), None);
+    }
+}
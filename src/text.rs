@@ -0,0 +1,207 @@
+//! Provenance of the strings found in a replay.
+//!
+//! Strings in a replay are length prefixed: a positive length denotes a single byte encoding
+//! while a negative length denotes UTF-16. The default parse decodes them into plain `String`s
+//! and forgets how they were stored, which is fine for most uses but not for lossless rewriting
+//! or for diagnosing mojibake in player names. `ParserBuilder::preserve_text_encodings` opts into
+//! recording the original encoding of each decoded string in `Replay::strings`.
+
+use crate::models::HeaderPropRef;
+use encoding_rs::WINDOWS_1252;
+use std::borrow::Cow;
+
+/// The encoding of a string as it was stored in the replay. Replays store strings with a single
+/// byte encoding (decoded as Windows-1252) or UTF-16; there is no UTF-8 encoding.
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone, Copy, Hash)]
+pub enum TextEncoding {
+    Windows1252,
+    Utf16,
+}
+
+/// A decoded string along with the encoding it was stored with in the replay
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone)]
+pub struct RlString {
+    pub value: String,
+    pub encoding: TextEncoding,
+}
+
+impl RlString {
+    /// Encodes the string as it would appear in a replay: a 32 bit length followed by the null
+    /// terminated text in the original encoding. An empty string is encoded with a length of
+    /// zero and no null terminator.
+    ///
+    /// ```
+    /// # use boxcars::{RlString, TextEncoding};
+    /// let s = RlString {
+    ///     value: String::from("hi"),
+    ///     encoding: TextEncoding::Utf16,
+    /// };
+    /// assert_eq!(s.encode(), vec![0xfd, 0xff, 0xff, 0xff, b'h', 0, b'i', 0, 0, 0]);
+    /// ```
+    pub fn encode(&self) -> Vec<u8> {
+        if self.value.is_empty() {
+            return vec![0; 4];
+        }
+
+        match self.encoding {
+            TextEncoding::Windows1252 => {
+                let (data, _, _) = WINDOWS_1252.encode(&self.value);
+                encode_bytes(&data)
+            }
+            TextEncoding::Utf16 => {
+                let units = self.value.encode_utf16().collect::<Vec<_>>();
+                let len = -(units.len() as i32 + 1);
+                let mut result = Vec::with_capacity(4 + (units.len() + 1) * 2);
+                result.extend_from_slice(&len.to_le_bytes());
+                for unit in units {
+                    result.extend_from_slice(&unit.to_le_bytes());
+                }
+                result.extend_from_slice(&[0, 0]);
+                result
+            }
+        }
+    }
+}
+
+fn encode_bytes(data: &[u8]) -> Vec<u8> {
+    let len = data.len() as i32 + 1;
+    let mut result = Vec::with_capacity(4 + data.len() + 1);
+    result.extend_from_slice(&len.to_le_bytes());
+    result.extend_from_slice(data);
+    result.push(0);
+    result
+}
+
+/// The strings of a replay that were decoded from either a single byte encoding or UTF-16, with
+/// their original encoding. Each list parallels the corresponding section of the `Replay`.
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone)]
+pub struct ReplayStrings {
+    pub game_type: RlString,
+
+    /// Name and string header properties keyed by their path in the syntax understood by
+    /// `HeaderPath::parse` (eg: `PlayerStats[0].Name`)
+    pub properties: Vec<(String, RlString)>,
+    pub levels: Vec<RlString>,
+
+    /// The user and text of each debug info entry
+    pub debug_info: Vec<(RlString, RlString)>,
+    pub tick_marks: Vec<RlString>,
+    pub packages: Vec<RlString>,
+    pub objects: Vec<RlString>,
+    pub names: Vec<RlString>,
+}
+
+/// Pairs the strings recorded while parsing the header (in the order they were parsed) with the
+/// header properties that they were decoded into.
+pub(crate) fn header_strings(
+    recorded: Vec<RlString>,
+    properties: &[(Cow<'_, str>, HeaderPropRef<'_>)],
+) -> Option<(RlString, Vec<(String, RlString)>)> {
+    let mut recorded = recorded.into_iter();
+    let game_type = recorded.next()?;
+    let mut result = Vec::new();
+    pair_properties(&mut recorded, properties, "", &mut result);
+    Some((game_type, result))
+}
+
+fn pair_properties(
+    recorded: &mut impl Iterator<Item = RlString>,
+    properties: &[(Cow<'_, str>, HeaderPropRef<'_>)],
+    prefix: &str,
+    result: &mut Vec<(String, RlString)>,
+) {
+    for (key, prop) in properties {
+        let path = if prefix.is_empty() {
+            key.to_string()
+        } else {
+            format!("{}.{}", prefix, key)
+        };
+
+        match prop {
+            HeaderPropRef::Name(_) | HeaderPropRef::Str(_) => {
+                if let Some(s) = recorded.next() {
+                    result.push((path, s));
+                }
+            }
+
+            // Only the quirks mode of byte properties decodes text (and leaves the value empty)
+            HeaderPropRef::Byte { value: None, .. } => {
+                if let Some(s) = recorded.next() {
+                    result.push((path, s));
+                }
+            }
+            HeaderPropRef::Array(elems) => {
+                for (i, elem) in elems.iter().enumerate() {
+                    let elem_path = format!("{}[{}]", path, i);
+                    pair_properties(recorded, elem, &elem_path, result);
+                }
+            }
+            HeaderPropRef::Struct { fields, .. } => {
+                pair_properties(recorded, fields, &path, result);
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rl(value: &str, encoding: TextEncoding) -> RlString {
+        RlString {
+            value: String::from(value),
+            encoding,
+        }
+    }
+
+    #[test]
+    fn encode_single_byte() {
+        assert_eq!(
+            rl("abc", TextEncoding::Windows1252).encode(),
+            vec![4, 0, 0, 0, b'a', b'b', b'c', 0]
+        );
+        assert_eq!(
+            rl("é", TextEncoding::Windows1252).encode(),
+            vec![2, 0, 0, 0, 0xe9, 0]
+        );
+        assert_eq!(rl("", TextEncoding::Utf16).encode(), vec![0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn pair_header_strings() {
+        let properties = vec![
+            (Cow::Borrowed("TeamSize"), HeaderPropRef::Int(3)),
+            (
+                Cow::Borrowed("Goals"),
+                HeaderPropRef::Array(vec![vec![(
+                    Cow::Borrowed("PlayerName"),
+                    HeaderPropRef::Str(Cow::Borrowed("a")),
+                )]]),
+            ),
+            (
+                Cow::Borrowed("MapName"),
+                HeaderPropRef::Name(Cow::Borrowed("b")),
+            ),
+        ];
+
+        let recorded = vec![
+            rl("game", TextEncoding::Windows1252),
+            rl("a", TextEncoding::Utf16),
+            rl("b", TextEncoding::Windows1252),
+        ];
+
+        let (game_type, props) = header_strings(recorded, &properties).unwrap();
+        assert_eq!(game_type, rl("game", TextEncoding::Windows1252));
+        assert_eq!(
+            props,
+            vec![
+                (
+                    String::from("Goals[0].PlayerName"),
+                    rl("a", TextEncoding::Utf16)
+                ),
+                (String::from("MapName"), rl("b", TextEncoding::Windows1252)),
+            ]
+        );
+    }
+}
@@ -0,0 +1,44 @@
+#![cfg(feature = "rayon")]
+
+use boxcars::{FileError, ParserBuilder};
+use std::path::PathBuf;
+
+#[test]
+fn parse_many_matches_sequential_order() {
+    let paths = [
+        "assets/replays/good/rumble.replay",
+        "assets/replays/good/3381.replay",
+        "assets/replays/good/b2a19.replay",
+    ];
+    let buffers = paths
+        .iter()
+        .map(|x| std::fs::read(x).unwrap())
+        .collect::<Vec<_>>();
+
+    let config = ParserBuilder::new(&[])
+        .always_check_crc()
+        .never_parse_network_data();
+    let results = boxcars::parse_many(&buffers, &config);
+    assert_eq!(results.len(), buffers.len());
+
+    for (data, result) in buffers.iter().zip(results) {
+        let expected = config.with_data(data).parse().unwrap();
+        assert_eq!(result.unwrap(), expected);
+    }
+}
+
+#[test]
+fn parse_many_files_reports_failures_in_place() {
+    let paths = vec![
+        PathBuf::from("assets/replays/good/rumble.replay"),
+        PathBuf::from("assets/replays/does-not-exist.replay"),
+        PathBuf::from("assets/replays/bad/fuzz-corpus.replay"),
+    ];
+
+    let config = ParserBuilder::new(&[]).never_parse_network_data();
+    let results = boxcars::parse_many_files(&paths, &config);
+
+    assert_eq!(results[0].as_ref().unwrap().header().team0_score(), Some(5));
+    assert!(matches!(results[1], Err(FileError::Io(ref p, _)) if p == &paths[1]));
+    assert!(matches!(results[2], Err(FileError::Parse(ref p, _)) if p == &paths[2]));
+}
@@ -0,0 +1,149 @@
+#![cfg(feature = "cli")]
+
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+
+const REPLAY: &str = "assets/replays/good/small-frames.replay";
+
+fn boxcars(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_boxcars"))
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+fn stdout(output: &Output) -> String {
+    assert!(output.status.success(), "{:?}", output);
+    String::from_utf8(output.stdout.clone()).unwrap()
+}
+
+#[test]
+fn test_help() {
+    for flag in ["-h", "--help"] {
+        let out = stdout(&boxcars(&[flag]));
+        assert!(out.starts_with("Usage: boxcars"));
+    }
+
+    let out = stdout(&boxcars(&["frames", "--help"]));
+    assert!(out.starts_with("Usage: boxcars"));
+}
+
+#[test]
+fn test_unrecognized_command() {
+    let output = boxcars(&["nope", REPLAY]);
+    assert_eq!(output.status.code(), Some(2));
+    let err = String::from_utf8(output.stderr).unwrap();
+    assert!(err.starts_with("unrecognized command: nope"));
+}
+
+#[test]
+fn test_json() {
+    let out = stdout(&boxcars(&["json", REPLAY]));
+    let replay: serde_json::Value = serde_json::from_str(&out).unwrap();
+    assert_eq!(replay["game_type"], "TAGame.Replay_Soccar_TA");
+    assert_eq!(
+        replay["network_frames"]["frames"].as_array().unwrap().len(),
+        231
+    );
+
+    let out = stdout(&boxcars(&["json", "--header-only", REPLAY]));
+    let replay: serde_json::Value = serde_json::from_str(&out).unwrap();
+    assert!(replay["network_frames"].is_null());
+}
+
+#[test]
+fn test_header() {
+    let out = stdout(&boxcars(&["header", REPLAY]));
+    let goal = out
+        .lines()
+        .find(|x| x.starts_with("Goals[0].PlayerName"))
+        .unwrap();
+    assert!(goal.ends_with(" Nadir"));
+}
+
+#[test]
+fn test_frames() {
+    let out = stdout(&boxcars(&["frames", "--from", "1", "--to", "3", REPLAY]));
+    let frames: Vec<serde_json::Value> = serde_json::from_str(&out).unwrap();
+    assert_eq!(frames.len(), 2);
+
+    // The range is clamped to the frames in the replay
+    let out = stdout(&boxcars(&[
+        "frames", "--from", "230", "--to", "500", REPLAY,
+    ]));
+    let frames: Vec<serde_json::Value> = serde_json::from_str(&out).unwrap();
+    assert_eq!(frames.len(), 1);
+}
+
+#[test]
+fn test_objects() {
+    let out = stdout(&boxcars(&["objects", REPLAY]));
+    let mut lines = out.lines();
+    assert_eq!(lines.next(), Some("objects:"));
+    assert_eq!(lines.next().map(str::trim), Some("0 Core.Object"));
+    assert!(out.contains("\nnames:\n"));
+    assert!(out.contains("\nclass indices:\n"));
+}
+
+#[test]
+fn test_verify() {
+    let out = stdout(&boxcars(&["verify", REPLAY]));
+    let lines = out.lines().collect::<Vec<_>>();
+    assert_eq!(lines.len(), 2);
+    assert!(lines[0].starts_with("header") && lines[0].ends_with(" ok"));
+    assert!(lines[1].starts_with("body") && lines[1].ends_with(" ok"));
+}
+
+#[test]
+fn test_verify_stdin() {
+    let data = std::fs::read(REPLAY).unwrap();
+    let mut child = Command::new(env!("CARGO_BIN_EXE_boxcars"))
+        .args(["verify", "-"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(&data).unwrap();
+    let out = stdout(&child.wait_with_output().unwrap());
+    assert_eq!(out.matches(" ok\n").count(), 2);
+}
+
+#[test]
+fn test_verify_truncated() {
+    let data = std::fs::read(REPLAY).unwrap();
+    let dir = std::path::Path::new(env!("CARGO_TARGET_TMPDIR")).join("cli-verify");
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("truncated.replay");
+    std::fs::write(&path, &data[..data.len() / 2]).unwrap();
+
+    let output = boxcars(&["verify", path.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(1));
+    let err = String::from_utf8(output.stderr).unwrap();
+    assert!(err.contains("section size exceeds replay size"));
+}
+
+#[test]
+fn test_stats() {
+    let out = stdout(&boxcars(&["stats", REPLAY]));
+    let mut lines = out.lines();
+    assert_eq!(lines.next(), Some("frames:         231"));
+    assert_eq!(lines.next(), Some("new actors:     20"));
+    assert_eq!(lines.next(), Some("deleted actors: 0"));
+    assert_eq!(lines.next(), Some("updates:        193"));
+}
+
+#[test]
+fn test_scan() {
+    let dir = std::path::Path::new(env!("CARGO_TARGET_TMPDIR")).join("cli-scan");
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::copy(REPLAY, dir.join("small-frames.replay")).unwrap();
+    let dir = dir.to_str().unwrap();
+
+    let out = stdout(&boxcars(&["scan", dir]));
+    assert_eq!(out.trim(), "scanned: 1 succeeded: 1 failed: 0");
+
+    let out = stdout(&boxcars(&["scan", "--json", dir]));
+    let report: serde_json::Value = serde_json::from_str(&out).unwrap();
+    assert_eq!(report["scanned"], 1);
+    assert_eq!(report["failures"].as_array().unwrap().len(), 0);
+}
@@ -1,11 +1,13 @@
 //! Verifies that decoded network frames (and the `Attribute` payloads within
 //! them) survive a `serde_json` serialize -> deserialize round trip unchanged.
 //!
-//! The top-level `Replay` JSON is intentionally lossy/one-way, but the network
-//! `Frame` path is now fully round-trippable so downstream tooling can persist
-//! trimmed "replay clips" as fixtures and read them back into real boxcars data.
+//! The default top-level `Replay` JSON deserializes back, though header
+//! property types that share a JSON representation are lost. The tagged
+//! representation (`Replay::tagged`) and the network `Frame` path are fully
+//! round-trippable so downstream tooling can persist replays or trimmed
+//! "replay clips" as fixtures and read them back into real boxcars data.
 
-use boxcars::{Frame, ParserBuilder};
+use boxcars::{ColumnarFrames, Frame, ParserBuilder, Replay, Tagged};
 
 #[test]
 fn network_frames_roundtrip_through_json() {
@@ -23,3 +25,82 @@ fn network_frames_roundtrip_through_json() {
 
     assert_eq!(frames, restored, "frames did not round trip");
 }
+
+#[test]
+fn tagged_replay_roundtrip_through_json() {
+    for data in [
+        &include_bytes!("../assets/replays/good/3381.replay")[..],
+        &include_bytes!("../assets/replays/good/rumble.replay")[..],
+    ] {
+        let replay = ParserBuilder::new(data)
+            .always_check_crc()
+            .must_parse_network_data()
+            .parse()
+            .unwrap();
+
+        let json = serde_json::to_string(&replay.tagged()).unwrap();
+        let Tagged(restored): Tagged<Replay> = serde_json::from_str(&json).unwrap();
+        assert_eq!(replay, restored, "replay did not round trip");
+    }
+}
+
+#[test]
+fn default_replay_roundtrip_through_json() {
+    for data in [
+        &include_bytes!("../assets/replays/good/3381.replay")[..],
+        &include_bytes!("../assets/replays/good/rumble.replay")[..],
+    ] {
+        let replay = ParserBuilder::new(data)
+            .always_check_crc()
+            .must_parse_network_data()
+            .parse()
+            .unwrap();
+
+        let json = serde_json::to_string(&replay).unwrap();
+        let restored: Replay = serde_json::from_str(&json).unwrap();
+        assert_eq!(replay.network_frames, restored.network_frames);
+        assert_eq!(replay.properties.len(), restored.properties.len());
+
+        // Names and quadwords are restored as strings, which serialize the same
+        let json2 = serde_json::to_string(&restored).unwrap();
+        assert_eq!(json, json2, "replay did not round trip");
+    }
+}
+
+#[test]
+fn columnar_frames_roundtrip() {
+    for data in [
+        &include_bytes!("../assets/replays/good/3381.replay")[..],
+        &include_bytes!("../assets/replays/good/rumble.replay")[..],
+    ] {
+        let replay = ParserBuilder::new(data)
+            .must_parse_network_data()
+            .parse()
+            .unwrap();
+
+        let network = replay.network_frames.unwrap();
+        let columns = ColumnarFrames::from_frames(&network.frames);
+        assert_eq!(columns.len(), network.frames.len());
+        assert_eq!(columns.to_frames().unwrap(), network.frames);
+        assert_eq!(ColumnarFrames::from(network), columns);
+    }
+}
+
+#[test]
+fn columnar_frames_decoded_directly() {
+    for data in [
+        &include_bytes!("../assets/replays/good/3381.replay")[..],
+        &include_bytes!("../assets/replays/good/rumble.replay")[..],
+    ] {
+        let builder = ParserBuilder::new(data).must_parse_network_data();
+        let replay = builder.clone().parse().unwrap();
+        let columnar = builder.columnar_frames().parse().unwrap();
+        assert!(columnar.network_frames.is_none());
+
+        let columns = columnar.columnar_frames.unwrap();
+        assert_eq!(
+            columns,
+            ColumnarFrames::from(replay.network_frames.unwrap())
+        );
+    }
+}
@@ -1,7 +1,9 @@
 use boxcars::attributes::{ActiveActor, Demolish, Pickup, RigidBody, StatEvent, Welded};
 use boxcars::{
-    self, ActorId, ParseError, ParserBuilder, Quaternion, Trajectory, Vector3f, Vector3i,
+    self, ActorId, HeaderPropRef, LimitError, Limits, NetworkError, ParseError, ParserBuilder,
+    Quaternion, Trajectory, Vector3f, Vector3i,
 };
+use std::borrow::Cow;
 
 #[test]
 fn test_sample1() {
@@ -489,3 +491,1030 @@ fn test_dodges_refreshed_counter() {
     values.dedup();
     assert_eq!(vec![0, 1, 2, 3], values);
 }
+
+#[test]
+fn test_replay_header_view() {
+    let data = include_bytes!("../assets/replays/good/rumble.replay");
+    let replay = ParserBuilder::new(&data[..])
+        .always_check_crc()
+        .never_parse_network_data()
+        .parse()
+        .unwrap();
+
+    let header = replay.header();
+    assert_eq!(header.team_size(), Some(3));
+    assert_eq!(header.team0_score(), Some(5));
+    assert_eq!(header.team1_score(), Some(2));
+    assert_eq!(header.num_frames(), Some(7744));
+    assert_eq!(header.record_fps(), Some(30.0));
+    assert_eq!(header.build_version(), Some("160829.43482.139308"));
+    assert_eq!(header.match_type(), Some("Online"));
+    assert_eq!(header.id(), Some("AC1CDA0D46ECE4D35CA0048C662D4D54"));
+
+    let goals = header.goals().unwrap();
+    assert_eq!(goals[0].frame(), Some(441));
+    assert_eq!(goals[0].player_name(), Some("Cakeboss"));
+    assert_eq!(goals[0].player_team(), Some(1));
+
+    let stats = header.player_stats().unwrap();
+    let player = stats
+        .iter()
+        .find(|x| x.name() == Some("comagoosie"))
+        .unwrap();
+    assert_eq!(player.platform(), Some("OnlinePlatform_Steam"));
+    assert_eq!(player.online_id(), Some(76561198101748375));
+    assert_eq!(player.score(), Some(90));
+    assert_eq!(player.is_bot(), Some(false));
+}
+
+#[test]
+fn test_retain_unknown_header_property() {
+    // Rename the type of the first int property (TeamSize) to simulate a
+    // property type introduced in a future patch
+    let mut data = include_bytes!("../assets/replays/good/rumble.replay").to_vec();
+    let needle = b"IntProperty";
+    let pos = data
+        .windows(needle.len())
+        .position(|x| x == needle)
+        .unwrap();
+    data[pos..pos + needle.len()].copy_from_slice(b"NewProperty");
+
+    let err = ParserBuilder::new(&data[..])
+        .never_check_crc()
+        .never_parse_network_data()
+        .parse()
+        .unwrap_err();
+    assert!(err.to_string().contains("NewProperty"));
+
+    let replay = ParserBuilder::new(&data[..])
+        .never_check_crc()
+        .never_parse_network_data()
+        .retain_unknown_properties()
+        .parse()
+        .unwrap();
+
+    assert_eq!(
+        replay.properties[0],
+        (
+            String::from("TeamSize"),
+            boxcars::HeaderProp::Unknown {
+                kind: String::from("NewProperty"),
+                raw: vec![3, 0, 0, 0],
+            }
+        )
+    );
+    assert_eq!(replay.header().team0_score(), Some(5));
+}
+
+#[test]
+fn test_preserve_text_encodings() {
+    let data = include_bytes!("../assets/replays/good/b2a19.replay");
+    let replay = ParserBuilder::new(&data[..])
+        .never_parse_network_data()
+        .parse()
+        .unwrap();
+    assert!(replay.strings.is_none());
+
+    let replay = ParserBuilder::new(&data[..])
+        .never_parse_network_data()
+        .preserve_text_encodings()
+        .parse()
+        .unwrap();
+
+    let strings = replay.strings.as_ref().unwrap();
+    assert_eq!(strings.game_type.value, replay.game_type);
+    assert_eq!(
+        strings.game_type.encoding,
+        boxcars::TextEncoding::Windows1252
+    );
+    assert_eq!(strings.names.len(), replay.names.len());
+    assert_eq!(strings.objects.len(), replay.objects.len());
+
+    let (path, name) = strings
+        .properties
+        .iter()
+        .find(|(_, x)| x.encoding == boxcars::TextEncoding::Utf16)
+        .unwrap();
+    assert_eq!(path, "PlayerStats[1].Name");
+    assert_eq!(name.value, "frosty †");
+    assert_eq!(
+        replay.query_as::<&str>(path).unwrap(),
+        vec![name.value.as_str()]
+    );
+
+    // Re-encoding the string reproduces the bytes found in the replay
+    let encoded = name.encode();
+    assert!(data.windows(encoded.len()).any(|x| x == encoded));
+}
+
+#[test]
+fn test_parse_borrowed() {
+    let data = include_bytes!("../assets/replays/good/b2a19.replay");
+    let builder = ParserBuilder::new(&data[..]).must_parse_network_data();
+    let expected = builder.clone().parse().unwrap();
+    let replay = builder.parse_borrowed().unwrap();
+
+    assert!(replay.objects.iter().all(|x| matches!(x, Cow::Borrowed(_))));
+
+    // UTF-16 player names are decoded and can't be borrowed
+    let Some(HeaderPropRef::Array(stats)) = replay.get("PlayerStats") else {
+        panic!("expected player stats");
+    };
+    let name = stats[1]
+        .iter()
+        .find(|(key, _)| key == "Name")
+        .map(|(_, prop)| prop)
+        .unwrap();
+    assert_eq!(
+        name,
+        &HeaderPropRef::Str(Cow::Owned(String::from("frosty †")))
+    );
+    assert!(matches!(name, HeaderPropRef::Str(Cow::Owned(_))));
+
+    assert_eq!(replay.into_owned(), expected);
+}
+
+#[test]
+fn test_only_classes() {
+    let data = include_bytes!("../assets/replays/good/rumble.replay");
+    let replay = ParserBuilder::new(&data[..])
+        .must_parse_network_data()
+        .parse()
+        .unwrap();
+    let filtered = ParserBuilder::new(&data[..])
+        .must_parse_network_data()
+        .only_classes(["TAGame.Ball_TA"])
+        .parse()
+        .unwrap();
+
+    let balls = replay
+        .objects
+        .iter()
+        .enumerate()
+        .filter(|(_, name)| name.starts_with("Archetypes.Ball."))
+        .map(|(i, _)| boxcars::ObjectId(i as i32))
+        .collect::<Vec<_>>();
+
+    let frames = replay.network_frames.unwrap().frames;
+    let filtered_frames = filtered.network_frames.unwrap().frames;
+    assert_eq!(frames.len(), filtered_frames.len());
+
+    let mut ball_actors = std::collections::HashSet::new();
+    let mut kept = 0;
+    for (frame, filtered_frame) in frames.iter().zip(filtered_frames.iter()) {
+        assert_eq!(frame.new_actors, filtered_frame.new_actors);
+        for actor in &frame.new_actors {
+            if balls.contains(&actor.object_id) {
+                ball_actors.insert(actor.actor_id);
+            } else {
+                ball_actors.remove(&actor.actor_id);
+            }
+        }
+
+        let expected = frame
+            .updated_actors
+            .iter()
+            .filter(|x| ball_actors.contains(&x.actor_id))
+            .cloned()
+            .collect::<Vec<_>>();
+        kept += expected.len();
+        assert_eq!(expected, filtered_frame.updated_actors);
+    }
+
+    assert!(kept > 0);
+}
+
+#[test]
+fn test_stop_early() {
+    let data = include_bytes!("../assets/replays/good/rumble.replay");
+    let frames = ParserBuilder::new(&data[..])
+        .must_parse_network_data()
+        .parse()
+        .unwrap()
+        .network_frames
+        .unwrap()
+        .frames;
+
+    let stopped = ParserBuilder::new(&data[..])
+        .must_parse_network_data()
+        .stop_after_frames(100)
+        .parse()
+        .unwrap()
+        .network_frames
+        .unwrap()
+        .frames;
+    assert_eq!(stopped.as_slice(), &frames[..100]);
+
+    let stopped = ParserBuilder::new(&data[..])
+        .must_parse_network_data()
+        .stop_after_time(10.0)
+        .parse()
+        .unwrap()
+        .network_frames
+        .unwrap()
+        .frames;
+    let expected = frames
+        .iter()
+        .take_while(|x| x.time - frames[0].time <= 10.0)
+        .count();
+    assert!(expected > 0 && expected < frames.len());
+    assert_eq!(stopped.as_slice(), &frames[..expected]);
+
+    let stopped = ParserBuilder::new(&data[..])
+        .must_parse_network_data()
+        .stop_after_frames(frames.len() + 100)
+        .parse()
+        .unwrap()
+        .network_frames
+        .unwrap()
+        .frames;
+    assert_eq!(stopped, frames);
+}
+
+#[test]
+fn test_limits() {
+    let data = include_bytes!("../assets/replays/good/rumble.replay");
+    let parse = |limits: Limits| {
+        ParserBuilder::new(&data[..])
+            .must_parse_network_data()
+            .with_limits(limits)
+            .parse()
+    };
+
+    let network_limit = |limit: LimitError| {
+        Err(ParseError::NetworkError(Box::new(
+            NetworkError::LimitExceeded(limit),
+        )))
+    };
+
+    let limits = Limits {
+        max_frames: Some(100),
+        ..Limits::default()
+    };
+    assert_eq!(parse(limits), network_limit(LimitError::Frames(100)));
+
+    let limits = Limits {
+        max_live_actors: Some(10),
+        ..Limits::default()
+    };
+    assert_eq!(parse(limits), network_limit(LimitError::LiveActors(10)));
+
+    let limits = Limits {
+        max_updates: Some(1000),
+        ..Limits::default()
+    };
+    assert_eq!(parse(limits), network_limit(LimitError::Updates(1000)));
+
+    let limits = Limits {
+        max_output_bytes: Some(100_000),
+        ..Limits::default()
+    };
+    assert_eq!(
+        parse(limits),
+        network_limit(LimitError::OutputBytes(100_000))
+    );
+
+    let limits = Limits {
+        max_string_size: Some(8),
+        ..Limits::default()
+    };
+    assert!(matches!(
+        parse(limits),
+        Err(ParseError::ParseError(_, _, err))
+            if *err == ParseError::LimitExceeded(LimitError::StringSize(8))
+    ));
+
+    let limits = Limits {
+        max_frames: Some(100_000),
+        max_live_actors: Some(1000),
+        max_updates: Some(10_000_000),
+        max_string_size: Some(10_000),
+        max_output_bytes: Some(1 << 30),
+    };
+    assert!(parse(limits).is_ok());
+}
+
+#[test]
+fn test_limits_within_frame() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    let data = include_bytes!("../assets/replays/good/rumble.replay");
+    let frames = ParserBuilder::new(&data[..])
+        .must_parse_network_data()
+        .parse()
+        .unwrap()
+        .network_frames
+        .unwrap()
+        .frames;
+
+    // The first frame with more than one update
+    let (ind, frame) = frames
+        .iter()
+        .enumerate()
+        .find(|(_, x)| x.updated_actors.len() > 1)
+        .unwrap();
+    let previous: usize = frames[..ind].iter().map(|x| x.updated_actors.len()).sum();
+    let limit = previous + frame.updated_actors.len() - 1;
+
+    let completed = Arc::new(AtomicUsize::new(0));
+    let reported = Arc::clone(&completed);
+    let replay = ParserBuilder::new(&data[..])
+        .must_parse_network_data()
+        .with_limits(Limits {
+            max_updates: Some(limit),
+            ..Limits::default()
+        })
+        .on_progress(move |x| {
+            reported.store(x.frames, Ordering::SeqCst);
+            std::ops::ControlFlow::Continue(())
+        })
+        .parse();
+
+    assert_eq!(
+        replay,
+        Err(ParseError::NetworkError(Box::new(
+            NetworkError::LimitExceeded(LimitError::Updates(limit))
+        )))
+    );
+
+    // Decoding stopped in the middle of the frame that exceeded the limit
+    assert_eq!(completed.load(Ordering::SeqCst), ind);
+}
+
+#[test]
+fn test_progress() {
+    use std::ops::ControlFlow;
+    use std::sync::{Arc, Mutex};
+
+    let data = include_bytes!("../assets/replays/good/rumble.replay");
+    let reports = Arc::new(Mutex::new(Vec::new()));
+    let recorder = Arc::clone(&reports);
+    let replay = ParserBuilder::new(&data[..])
+        .must_parse_network_data()
+        .on_progress(move |progress| {
+            recorder.lock().unwrap().push(progress);
+            ControlFlow::Continue(())
+        })
+        .parse()
+        .unwrap();
+
+    let frames = replay.network_frames.unwrap().frames;
+    let reports = reports.lock().unwrap();
+    assert_eq!(reports.len(), frames.len());
+    for (i, report) in reports.iter().enumerate() {
+        assert_eq!(report.frames, i + 1);
+        assert_eq!(report.total_frames, frames.len());
+    }
+
+    assert!(reports
+        .windows(2)
+        .all(|x| x[0].bits_consumed < x[1].bits_consumed));
+
+    let reports = Arc::new(Mutex::new(Vec::new()));
+    let recorder = Arc::clone(&reports);
+    ParserBuilder::new(&data[..])
+        .must_parse_network_data()
+        .stop_after_frames(10)
+        .on_progress(move |progress| {
+            recorder.lock().unwrap().push(progress);
+            ControlFlow::Continue(())
+        })
+        .parse()
+        .unwrap();
+    let reports = reports.lock().unwrap();
+    assert_eq!(reports.len(), 10);
+    assert!(reports.iter().all(|x| x.total_frames == 10));
+
+    let replay = ParserBuilder::new(&data[..])
+        .ignore_network_data_on_error()
+        .on_progress(|_| ControlFlow::Break(()))
+        .parse()
+        .unwrap();
+    assert!(replay.network_frames.is_none());
+}
+
+#[test]
+fn test_corpus_scan() {
+    let report = boxcars::corpus::Scanner::new()
+        .scan_dir("assets/replays/bad")
+        .unwrap();
+
+    assert_eq!(report.scanned, 7);
+    assert_eq!(report.successes(), 0);
+
+    let missing = report
+        .failures
+        .iter()
+        .find(|x| x.path.ends_with("fuzz-large-object-id.replay"))
+        .unwrap();
+    assert_eq!(
+        missing.kind,
+        boxcars::corpus::FailureKind::Frame("MissingAttribute")
+    );
+    assert_eq!(missing.missing.as_deref(), Some("TAGame®Ball_TA:GameEvent"));
+    assert_eq!(
+        missing.build_version.as_deref(),
+        Some("160513.59355.132594")
+    );
+
+    let kinds = report.by_kind();
+    assert_eq!(
+        kinds[&boxcars::corpus::FailureKind::Network("TooManyFrames")].len(),
+        1
+    );
+}
+
+#[test]
+fn test_knockout_analytics() {
+    use boxcars::analytics::knockout::{self, KnockoutActionKind, KnockoutEventKind};
+
+    let data = include_bytes!("../assets/replays/good/f770.replay");
+    let replay = ParserBuilder::new(&data[..])
+        .must_parse_network_data()
+        .parse()
+        .unwrap();
+    let report = knockout::analyze(&replay);
+
+    assert_eq!(report.players.len(), 8);
+    assert!(report.players.iter().all(|x| x.identity.is_some()));
+    assert_eq!(report.stats.len(), 8);
+    assert_eq!(report.players[1].name, "XxCamiHdz10xX");
+    assert_eq!(report.stats[1].knockouts, 8);
+    assert_eq!(report.players[6].name, "BronzeBasket");
+    assert_eq!(report.stats[6].elimination_order, Some(1));
+
+    let count = |kind| report.timeline.iter().filter(|x| x.kind == kind).count() as i32;
+    let knockouts: i32 = report.stats.iter().map(|x| x.knockouts).sum();
+    let deaths: i32 = report.stats.iter().map(|x| x.knockout_deaths).sum();
+    assert_eq!(count(KnockoutEventKind::Knockout), knockouts);
+    assert_eq!(count(KnockoutEventKind::KnockedOut), deaths);
+    assert_eq!(count(KnockoutEventKind::Eliminated), 6);
+
+    let eliminated = report
+        .timeline
+        .iter()
+        .find(|x| x.kind == KnockoutEventKind::Eliminated)
+        .unwrap();
+    assert_eq!(report.players[eliminated.player].name, "ButteryStuff");
+
+    let attack = &report.actions[0];
+    assert_eq!(attack.kind, KnockoutActionKind::Attack);
+    assert_eq!(attack.component.as_deref(), Some("Dodge"));
+    assert!(report
+        .actions
+        .iter()
+        .any(|x| x.kind == KnockoutActionKind::Grab));
+
+    // A grabbed car is stunned until it is thrown
+    let stun = &report.stuns[0];
+    assert_eq!(report.players[stun.player].name, "BronzeBasket");
+    assert_eq!(stun.max_stun_time, 3.0);
+    let impulse = &report.impulses[0];
+    assert_eq!(impulse.player, stun.player);
+    assert_eq!(Some(impulse.frame), stun.end_frame);
+    let length =
+        impulse.direction.x.powi(2) + impulse.direction.y.powi(2) + impulse.direction.z.powi(2);
+    assert!((length - 1.0).abs() < 1e-4);
+
+    // The hardest hit of the match launches the car in the impulse's direction, so the car's
+    // velocity replicated right after the hit, (868, 5572, 1993), points the same way
+    let hit = report
+        .impulses
+        .iter()
+        .find(|x| x.speed > 1_000_000.0)
+        .unwrap();
+    assert_eq!(hit.frame, 1998);
+    let expected = [0.145, 0.932, 0.333];
+    let actual = [hit.direction.x, hit.direction.y, hit.direction.z];
+    for (actual, expected) in actual.iter().zip(expected) {
+        assert!((actual - expected).abs() < 0.02, "{actual} != {expected}");
+    }
+
+    let data = include_bytes!("../assets/replays/good/rumble.replay");
+    let replay = ParserBuilder::new(&data[..])
+        .must_parse_network_data()
+        .parse()
+        .unwrap();
+    let report = knockout::analyze(&replay);
+    assert!(!report.players.is_empty());
+    assert!(report.timeline.is_empty());
+    assert!(report.actions.is_empty());
+    assert!(report.stuns.is_empty());
+}
+
+#[test]
+fn test_rumble_analytics() {
+    use boxcars::analytics::rumble::{self, RumbleTarget};
+
+    let data = include_bytes!("../assets/replays/good/pickup_timer.replay");
+    let replay = ParserBuilder::new(&data[..])
+        .must_parse_network_data()
+        .parse()
+        .unwrap();
+    let report = rumble::analyze(&replay);
+
+    assert_eq!(report.players.len(), 6);
+    assert_eq!(report.items.len(), 90);
+
+    let spring = &report.items[5];
+    assert_eq!(report.players[spring.player].name, "Lexore");
+    assert_eq!(spring.item, "CarSpring");
+    assert_eq!(spring.granted_frame, 357);
+    assert_eq!(spring.activated_frame, Some(375));
+    assert_eq!(spring.removed_frame, Some(401));
+    assert_eq!(spring.target, Some(RumbleTarget::Player(0)));
+    assert_eq!(report.players[0].name, "The Dark Moon04");
+
+    let freeze = report
+        .items
+        .iter()
+        .find(|x| x.item == "BallFreeze")
+        .unwrap();
+    assert_eq!(freeze.target, Some(RumbleTarget::Ball));
+    assert!(freeze.hit);
+
+    let velcro = report
+        .items
+        .iter()
+        .find(|x| x.item == "BallVelcro" && x.broken)
+        .unwrap();
+    assert!(velcro.hit);
+    assert!(velcro.activated_frame.is_some());
+
+    let items: usize = (0..report.players.len())
+        .map(|x| report.player_items(x).count())
+        .sum();
+    assert_eq!(items, report.items.len());
+
+    // Items that were granted but never used are reported too
+    let unused: Vec<_> = report
+        .items
+        .iter()
+        .filter(|x| x.activated_frame.is_none())
+        .collect();
+    assert_eq!(unused.len(), 8);
+
+    // The timers of all six players run out before the first items are granted
+    let expiry = &report.timer_expiries[0];
+    assert_eq!((expiry.frame, expiry.timers), (335, 6));
+    assert!(expiry.frame < spring.granted_frame);
+
+    // Older replays replicate the item that a car holds with its attached pickup
+    let data = include_bytes!("../assets/replays/good/rumble.replay");
+    let replay = ParserBuilder::new(&data[..])
+        .must_parse_network_data()
+        .parse()
+        .unwrap();
+    let report = rumble::analyze(&replay);
+    assert!(report.timer_expiries.is_empty());
+
+    let spring = report
+        .items
+        .iter()
+        .find(|x| x.item == "BallSpring")
+        .unwrap();
+    assert_eq!(spring.granted_frame, 301);
+    assert_eq!(spring.activated_frame, Some(322));
+    assert_eq!(spring.removed_frame, Some(345));
+}
+
+#[test]
+fn test_dropshot_analytics() {
+    use boxcars::analytics::dropshot::{self, TileState};
+
+    let data = include_bytes!("../assets/replays/good/2266.replay");
+    let replay = ParserBuilder::new(&data[..])
+        .must_parse_network_data()
+        .parse()
+        .unwrap();
+    let report = dropshot::analyze(&replay);
+
+    assert_eq!(report.players.len(), 8);
+    assert_eq!(report.tiles.len(), 140);
+    assert!(report.tiles[0]
+        .object
+        .ends_with("BreakOutActor_Platform_TA_1"));
+
+    let first = &report.damages[0];
+    assert_eq!(first.frame, 178);
+    assert_eq!(
+        report.players[first.player.unwrap()].name,
+        "Nadir | gizmo.gg"
+    );
+    assert_eq!(first.tiles, vec![109]);
+    assert_eq!(first.direct_hit, Some(109));
+    assert_eq!(first.damage_index, Some(0));
+    assert_eq!(report.tiles[109].side, Some(0));
+
+    let second = &report.damages[1];
+    assert_eq!(second.frame, 410);
+    assert_eq!(second.tiles.len(), 4);
+    assert_eq!(second.damage_index, Some(1));
+
+    let states = report.states_at(178);
+    assert_eq!(states[109], TileState::Damaged);
+    assert_eq!(
+        states
+            .iter()
+            .filter(|&&x| x != TileState::Undamaged)
+            .count(),
+        1
+    );
+
+    let grid: Vec<_> = report.grid().collect();
+    assert_eq!(grid.len(), report.frames);
+    assert_eq!(grid[177][109], TileState::Undamaged);
+    assert_eq!(grid[178], states);
+
+    let damage: usize = (0..report.players.len())
+        .map(|x| report.player_damage(x))
+        .sum();
+    let credited = report.damages.iter().filter(|x| x.player.is_some());
+    assert_eq!(damage, credited.map(|x| x.tiles.len()).sum::<usize>());
+}
+
+#[test]
+fn test_ball_analytics() {
+    use boxcars::analytics::ball::{self, BallEventKind};
+
+    let data = include_bytes!("../assets/replays/good/d4f3b_heat.replay");
+    let replay = ParserBuilder::new(&data[..])
+        .must_parse_network_data()
+        .parse()
+        .unwrap();
+    let report = ball::analyze(&replay);
+    assert_eq!(report.balls.len(), 9);
+    assert_eq!(report.balls[0].object, "Archetypes.Ball.Ball_God");
+    assert_eq!(report.balls[0].destroyed_frame, Some(520));
+    assert_eq!(report.balls[1].created_frame, 520);
+
+    let speeds: Vec<_> = report
+        .ball_events(0)
+        .map(|x| (x.frame, x.kind.clone()))
+        .collect();
+    assert_eq!(
+        speeds,
+        vec![
+            (170, BallEventKind::TargetSpeed { speed: 2985.0 }),
+            (227, BallEventKind::TargetSpeed { speed: 3070.0 }),
+            (311, BallEventKind::TargetSpeed { speed: 3155.0 }),
+        ]
+    );
+
+    let data = include_bytes!("../assets/replays/good/9a2cd.replay");
+    let replay = ParserBuilder::new(&data[..])
+        .must_parse_network_data()
+        .parse()
+        .unwrap();
+    let report = ball::analyze(&replay);
+    assert_eq!(report.balls.len(), 4);
+    let events: Vec<_> = report.events.iter().take(3).map(|x| &x.kind).collect();
+    assert_eq!(
+        events,
+        vec![
+            &BallEventKind::Beamed { beamed: true },
+            &BallEventKind::ActiveBeams { beams: 1 },
+            &BallEventKind::ActiveBeams { beams: 2 },
+        ]
+    );
+    assert!(report
+        .events
+        .iter()
+        .any(|x| x.frame == 621 && x.kind == BallEventKind::GoalDeactivated { goal: Some(1) }));
+    assert!(report
+        .events
+        .iter()
+        .any(|x| x.frame == 741 && x.kind == BallEventKind::BeamBroken { value: 1 }));
+
+    let data = include_bytes!("../assets/replays/good/sonic_spin.replay");
+    let replay = ParserBuilder::new(&data[..])
+        .must_parse_network_data()
+        .parse()
+        .unwrap();
+    let report = ball::analyze(&replay);
+    let teams: Vec<_> = report
+        .ball_events(3)
+        .filter_map(|x| match x.kind {
+            BallEventKind::FireTeam { team, .. } => Some(team),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(report.balls[3].object, "Archetypes.Ball.Ball_Fire_Obstacle");
+    assert_eq!(teams, vec![Some(0), Some(0), Some(1)]);
+}
+
+#[test]
+fn test_loadout_analytics() {
+    use boxcars::analytics::loadout::{self, LoadoutSlot};
+
+    let data = include_bytes!("../assets/replays/good/9a2cd.replay");
+    let replay = ParserBuilder::new(&data[..])
+        .must_parse_network_data()
+        .parse()
+        .unwrap();
+    let report = loadout::analyze(&replay);
+    assert_eq!(report.loadouts.len(), report.players.len() * 2);
+
+    let player = report
+        .players
+        .iter()
+        .position(|x| x.name == "clockberg")
+        .unwrap();
+    let loadouts: Vec<_> = report.player_loadouts(player).collect();
+    assert_eq!(loadouts.len(), 2);
+
+    let (blue, orange) = (loadouts[0], loadouts[1]);
+    assert_eq!(blue.team, Some(0));
+    assert_eq!(orange.team, Some(1));
+    assert_eq!(blue.paint.map(|x| x.primary_color), Some(55));
+
+    let decal = blue.item(LoadoutSlot::Decal).unwrap();
+    assert_eq!(decal.product_id, Some(3488));
+    assert_eq!(decal.painted(), Some(5));
+    assert_eq!(
+        decal.attributes[0].name,
+        "TAGame.ProductAttribute_Painted_TA"
+    );
+    assert_eq!(orange.item(LoadoutSlot::Decal).unwrap().painted(), Some(1));
+
+    assert_eq!(
+        blue.item(LoadoutSlot::Boost).unwrap().product_id,
+        Some(3472)
+    );
+    assert_eq!(
+        orange.item(LoadoutSlot::Boost).unwrap().product_id,
+        Some(3386)
+    );
+    assert_eq!(blue.item(LoadoutSlot::Topper), None);
+
+    let titled = report
+        .loadouts
+        .iter()
+        .find(|x| x.title.as_deref() == Some("Season10GrandChampion"))
+        .unwrap();
+    let title = titled.item(LoadoutSlot::Title).unwrap();
+    assert_eq!(title.product_id, None);
+    assert_eq!(
+        title.attributes[0].name,
+        "TAGame.ProductAttribute_TitleID_TA"
+    );
+
+    let data = include_bytes!("../assets/replays/good/07e9.replay");
+    let replay = ParserBuilder::new(&data[..])
+        .must_parse_network_data()
+        .parse()
+        .unwrap();
+    let report = loadout::analyze(&replay);
+    assert_eq!(report.loadouts.len(), 1);
+    assert_eq!(report.loadouts[0].team, None);
+    assert_eq!(
+        report.loadouts[0]
+            .item(LoadoutSlot::Antenna)
+            .unwrap()
+            .product_id,
+        Some(490)
+    );
+}
+
+#[test]
+fn test_hitbox_from_loadout() {
+    use boxcars::analytics::loadout::{self, LoadoutSlot};
+    use boxcars::hitbox::Hitbox;
+
+    let data = include_bytes!("../assets/replays/good/9a2cd.replay");
+    let replay = ParserBuilder::new(&data[..])
+        .must_parse_network_data()
+        .parse()
+        .unwrap();
+    let report = loadout::analyze(&replay);
+    let hitboxes: Vec<_> = report
+        .loadouts
+        .iter()
+        .filter(|x| x.team == Some(0))
+        .map(|x| {
+            let body = x.item(LoadoutSlot::Body).and_then(|x| x.product_id);
+            Hitbox::from_body(body.unwrap())
+        })
+        .collect();
+    assert!(hitboxes.contains(&Some(Hitbox::Merc)));
+    assert!(hitboxes.contains(&Some(Hitbox::Octane)));
+
+    // At kickoff, cars are spread out from the ball at the center of the field
+    let frame = &replay.network_frames.as_ref().unwrap().frames[0];
+    let object = |actor_id| {
+        frame
+            .new_actors
+            .iter()
+            .find(|x| x.actor_id == actor_id)
+            .map(|x| replay.objects[usize::from(x.object_id)].as_str())
+    };
+    let updates = |name: &'static str| {
+        let objects = &replay.objects;
+        frame
+            .updated_actors
+            .iter()
+            .filter(move |x| objects[usize::from(x.object_id)] == name)
+    };
+
+    let bodies: std::collections::HashMap<_, _> = updates("TAGame.RBActor_TA:ReplicatedRBState")
+        .filter_map(|x| match x.attribute {
+            boxcars::Attribute::RigidBody(body) => Some((x.actor_id, body)),
+            _ => None,
+        })
+        .collect();
+    let ball = bodies
+        .iter()
+        .find(|(&actor_id, _)| object(actor_id).is_some_and(|x| x.starts_with("Archetypes.Ball.")))
+        .map(|(_, body)| body.location)
+        .unwrap();
+
+    // Each car is measured with the hitbox of the body in its player's loadout
+    let mut cars = 0;
+    for update in updates("Engine.Pawn:PlayerReplicationInfo") {
+        let boxcars::Attribute::ActiveActor(pri) = update.attribute else {
+            continue;
+        };
+
+        let player = report
+            .players
+            .iter()
+            .position(|x| x.actor_id == pri.actor)
+            .unwrap();
+        let team = report.players[player].team;
+        let body = report
+            .loadouts
+            .iter()
+            .find(|x| x.player == player && x.team == team)
+            .and_then(|x| x.item(LoadoutSlot::Body))
+            .and_then(|x| x.product_id)
+            .unwrap();
+        let dims = Hitbox::from_body(body).unwrap().dimensions();
+        let distance = dims
+            .distance(&bodies[&update.actor_id], replay.net_version, ball)
+            .unwrap();
+        assert!(distance > 1000.0);
+        cars += 1;
+    }
+    assert_eq!(cars, 6);
+}
+
+#[test]
+fn test_player_identity() {
+    use boxcars::identity::{self, Platform, PlayerIdentity};
+
+    let data = include_bytes!("../assets/replays/good/13c2.replay");
+    let replay = ParserBuilder::new(&data[..])
+        .must_parse_network_data()
+        .parse()
+        .unwrap();
+    let players = identity::join_players(&replay);
+    assert_eq!(players.len(), 4);
+    assert!(players.iter().all(|x| x.stats.is_some()));
+
+    // The player was renamed after the header's stats were recorded
+    let renamed = &players[0];
+    assert_eq!(renamed.name, "Aaka");
+    assert_eq!(renamed.stats.as_ref().unwrap().name(), Some("Aakorn"));
+    let id: PlayerIdentity = "steam:76561199088249641".parse().unwrap();
+    assert_eq!(renamed.identity.as_ref(), Some(&id));
+
+    let epic = players
+        .iter()
+        .find(|x| x.name == "ozarn.")
+        .and_then(|x| x.identity.as_ref())
+        .unwrap();
+    assert_eq!(epic.platform, Platform::Epic);
+    assert_eq!(epic.to_string(), "epic:62c2cffa806543e4add327f76cf9d7b6");
+
+    // Bots have no identity and are joined by name
+    let data = include_bytes!("../assets/replays/good/65e98.replay");
+    let replay = ParserBuilder::new(&data[..])
+        .must_parse_network_data()
+        .parse()
+        .unwrap();
+    let players = identity::join_players(&replay);
+    let bot = players.iter().find(|x| x.name == "Boomer").unwrap();
+    assert_eq!(bot.identity, None);
+    assert!(bot.actor_id.is_some());
+    assert!(bot.stats.is_some());
+
+    // Analytics also identify bots by name
+    let report = boxcars::analytics::loadout::analyze(&replay);
+    let bots = report.players.iter().filter(|x| x.identity.is_none());
+    assert_eq!(
+        bots.map(|x| x.name.as_str()).collect::<Vec<_>>(),
+        vec!["Marley", "Saltie", "Rainmaker", "Boomer"]
+    );
+    assert!(report
+        .players
+        .iter()
+        .any(|x| x.name == "Henry" && x.identity.is_some()));
+
+    let switch = players.iter().find(|x| x.name == "Henry").unwrap();
+    let stats = PlayerIdentity::from_stats(switch.stats.as_ref().unwrap());
+    assert_eq!(stats.as_ref(), switch.identity.as_ref());
+    assert_eq!(stats.unwrap().platform, Platform::Switch);
+}
+
+#[test]
+fn test_parties() {
+    use boxcars::identity;
+
+    let data = include_bytes!("../assets/replays/good/13c2.replay");
+    let replay = ParserBuilder::new(&data[..])
+        .must_parse_network_data()
+        .parse()
+        .unwrap();
+    let parties = identity::parties(&replay);
+    assert_eq!(parties.len(), 2);
+
+    let party = &parties[0];
+    assert_eq!(
+        party.leader.to_string(),
+        "epic:62c2cffa806543e4add327f76cf9d7b6"
+    );
+    assert!(!party.is_solo());
+    let members: Vec<_> = party
+        .members
+        .iter()
+        .map(|x| (x.name.as_str(), x.reservation))
+        .collect();
+    assert_eq!(members, vec![("ozarn.", Some(0)), ("Veiled", Some(1))]);
+
+    let party = &parties[1];
+    assert_eq!(party.leader.to_string(), "steam:76561198996198614");
+    let members: Vec<_> = party
+        .members
+        .iter()
+        .map(|x| (x.name.as_str(), x.reservation))
+        .collect();
+    assert_eq!(members, vec![("elvn .", Some(2)), ("Aaka", Some(3))]);
+
+    // Everyone queued alone
+    let data = include_bytes!("../assets/replays/good/029d.replay");
+    let replay = ParserBuilder::new(&data[..])
+        .must_parse_network_data()
+        .parse()
+        .unwrap();
+    let parties = identity::parties(&replay);
+    assert_eq!(parties.len(), 8);
+    assert!(parties.iter().all(|x| x.is_solo()));
+}
+
+#[test]
+fn test_match_info() {
+    use boxcars::analytics::match_info;
+
+    let data = include_bytes!("../assets/replays/good/181d6.replay");
+    let info = match_info::parse(&data[..]).unwrap();
+    assert_eq!(
+        info.match_guid.as_deref(),
+        Some("3102849011EE7A893CDBA1B11737A86E")
+    );
+    assert_eq!(info.game_server_id.as_deref(), Some("2026860"));
+    assert_eq!(info.server_region.as_deref(), Some("USE"));
+    assert_eq!(info.playlist, Some(3));
+    assert_eq!(info.bot_match, Some(true));
+    assert_eq!(info.bot_skill, Some(940201477));
+    assert_eq!(info.series_length, None);
+
+    // Current replays replicate the match guid as `MatchGUID`
+    let data = include_bytes!("../assets/replays/good/256.replay");
+    let info = match_info::parse(&data[..]).unwrap();
+    assert_eq!(
+        info.match_guid.as_deref(),
+        Some("12CE9DDC11F093E75E0ABD97C907051E")
+    );
+
+    // Older replays replicate the server id as a number
+    let data = include_bytes!("../assets/replays/good/e978.replay");
+    let info = match_info::parse(&data[..]).unwrap();
+    assert_eq!(info.game_server_id.as_deref(), Some("671468"));
+    assert_eq!(info.mutator_index, Some(-1));
+    assert_eq!(info.bot_skill, Some(1065353216));
+    assert_eq!(info.series_length, Some(5));
+
+    // Decoding the first frames collects the same info as decoding the whole replay
+    let data = include_bytes!("../assets/replays/good/13c2.replay");
+    let replay = ParserBuilder::new(&data[..])
+        .must_parse_network_data()
+        .parse()
+        .unwrap();
+    let info = match_info::parse(&data[..]).unwrap();
+    assert_eq!(info, match_info::analyze(&replay));
+    assert_eq!(info.match_start_epoch, Some(1765567449));
+
+    // The server's address is replicated near the end of the match
+    let data = include_bytes!("../assets/replays/good/160c.replay");
+    let replay = ParserBuilder::new(&data[..])
+        .must_parse_network_data()
+        .parse()
+        .unwrap();
+    let info = match_info::parse(&data[..]).unwrap();
+    assert_eq!(info.server_ip, None);
+    assert_eq!(
+        match_info::analyze(&replay).server_ip.as_deref(),
+        Some("45.63.50.200:7854")
+    );
+}